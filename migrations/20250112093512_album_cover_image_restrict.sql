@@ -0,0 +1,6 @@
+ALTER TABLE albums
+DROP CONSTRAINT albums_cover_image_id_fkey,
+ADD CONSTRAINT albums_cover_image_id_fkey
+    FOREIGN KEY (cover_image_id)
+    REFERENCES images(id)
+    ON DELETE RESTRICT;
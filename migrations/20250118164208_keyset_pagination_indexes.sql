@@ -0,0 +1,13 @@
+ALTER TABLE albums
+ALTER COLUMN created_at SET NOT NULL;
+
+CREATE INDEX photos_created_at_id_idx
+ON photos (created_at DESC, id DESC)
+WHERE is_deleted = false;
+
+CREATE INDEX photos_trash_owner_created_at_id_idx
+ON photos (owner_user_id, created_at DESC, id DESC)
+WHERE is_deleted = true;
+
+CREATE INDEX albums_created_at_id_idx
+ON albums (created_at DESC, id DESC);
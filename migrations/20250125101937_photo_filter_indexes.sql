@@ -0,0 +1,10 @@
+CREATE INDEX photos_tags_gin_idx
+ON photos USING GIN (tags);
+
+CREATE INDEX photos_category_created_at_id_idx
+ON photos (category, created_at DESC, id DESC)
+WHERE is_deleted = false;
+
+CREATE INDEX photos_album_id_created_at_id_idx
+ON photos (album_id, created_at DESC, id DESC)
+WHERE is_deleted = false;
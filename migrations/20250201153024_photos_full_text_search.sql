@@ -0,0 +1,18 @@
+-- array_to_string is only STABLE, generated columns require IMMUTABLE expressions
+CREATE FUNCTION photo_tags_to_text(tags TEXT[])
+RETURNS TEXT
+LANGUAGE sql
+IMMUTABLE
+PARALLEL SAFE
+AS $$ SELECT array_to_string(tags, ' ') $$;
+
+ALTER TABLE photos
+ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A')
+        || setweight(to_tsvector('english', photo_tags_to_text(tags)), 'B')
+        || setweight(to_tsvector('english', description), 'C')
+    ) STORED;
+
+CREATE INDEX photos_search_vector_gin_idx
+ON photos USING GIN (search_vector);
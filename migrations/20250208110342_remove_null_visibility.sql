@@ -0,0 +1,23 @@
+-- Postgres cannot drop a value from an enum, so the type is recreated without the 'NULL'
+-- sentinel added by 20250104084711_add_null_visibility.sql
+ALTER TYPE visibility RENAME TO visibility_with_null;
+
+CREATE TYPE visibility AS ENUM ('Public', 'Private');
+
+DROP TRIGGER update_image_visibility_trigger ON photos;
+
+ALTER TABLE images
+ALTER COLUMN visibility TYPE visibility USING visibility::text::visibility;
+
+ALTER TABLE albums
+ALTER COLUMN visibility TYPE visibility USING visibility::text::visibility;
+
+ALTER TABLE photos
+ALTER COLUMN visibility TYPE visibility USING visibility::text::visibility;
+
+CREATE TRIGGER update_image_visibility_trigger
+AFTER UPDATE OF visibility ON photos
+FOR EACH ROW
+EXECUTE FUNCTION update_image_visibility();
+
+DROP TYPE visibility_with_null;
@@ -0,0 +1,25 @@
+ALTER TABLE photos
+ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+ALTER TABLE albums
+ADD COLUMN version integer NOT NULL DEFAULT 1;
+
+-- Every update, including soft deletes and restores, yields a new version
+CREATE OR REPLACE FUNCTION increment_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version = OLD.version + 1;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER increment_photo_version_trigger
+BEFORE UPDATE ON photos
+FOR EACH ROW
+EXECUTE FUNCTION increment_version();
+
+CREATE TRIGGER increment_album_version_trigger
+BEFORE UPDATE ON albums
+FOR EACH ROW
+EXECUTE FUNCTION increment_version();
@@ -0,0 +1,15 @@
+CREATE TYPE rendition_size AS ENUM ('Small', 'Medium', 'Large');
+
+-- Downscaled copies of an image generated at upload, stored next to the original
+CREATE TABLE image_renditions(
+    image_id uuid NOT NULL
+        REFERENCES images(id)
+        ON DELETE CASCADE,
+    size rendition_size NOT NULL,
+    PRIMARY KEY(image_id, size),
+    width integer NOT NULL,
+    height integer NOT NULL,
+    format image_format NOT NULL,
+    file_size BIGINT NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,15 @@
+-- Photos whose image is uploaded by the client straight to S3, created once the upload is complete
+CREATE TABLE pending_uploads(
+    id uuid NOT NULL,
+    PRIMARY KEY(id),
+    owner_user_id uuid NOT NULL,
+    filename TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL,
+    category TEXT NOT NULL DEFAULT '',
+    tags TEXT[] NOT NULL DEFAULT '{}'::text[],
+    album_id uuid,
+    visibility visibility NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT NOW(),
+    expires_at timestamptz NOT NULL
+);
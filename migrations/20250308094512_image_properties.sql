@@ -0,0 +1,9 @@
+CREATE TYPE color_type AS ENUM ('Gray', 'GrayAlpha', 'Rgb', 'Rgba');
+
+-- Learnt at upload, the images uploaded before or never decoded at upload have no dimensions
+ALTER TABLE images
+    ADD COLUMN width integer,
+    ADD COLUMN height integer,
+    ADD COLUMN color_type color_type,
+    ADD COLUMN bit_depth smallint,
+    ADD COLUMN sha256 bytea;
@@ -0,0 +1,3 @@
+-- Serves the purge of the expired pending uploads
+CREATE INDEX pending_uploads_expires_at_idx
+ON pending_uploads (expires_at);
@@ -0,0 +1,4 @@
+DELETE FROM albums
+WHERE albums.id = $1
+AND ($2::integer[] IS NULL OR albums.version = ANY($2))
+RETURNING albums.cover_image_id AS "cover_image_id!";
\ No newline at end of file
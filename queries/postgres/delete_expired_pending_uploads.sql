@@ -0,0 +1,14 @@
+DELETE FROM pending_uploads
+WHERE pending_uploads.id IN (
+    SELECT
+        expired_uploads.id
+    FROM
+        pending_uploads AS expired_uploads
+    WHERE
+        expired_uploads.expires_at < NOW()
+    ORDER BY
+        expired_uploads.expires_at
+    LIMIT $1
+    FOR UPDATE SKIP LOCKED
+)
+RETURNING pending_uploads.id;
@@ -0,0 +1,2 @@
+DELETE FROM pending_uploads
+WHERE pending_uploads.id = $1;
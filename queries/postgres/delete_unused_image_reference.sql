@@ -0,0 +1,13 @@
+DELETE FROM images
+WHERE images.id = $1
+AND NOT EXISTS (
+    SELECT 1
+    FROM photos
+    WHERE photos.image_id = $1
+)
+AND NOT EXISTS (
+    SELECT 1
+    FROM albums
+    WHERE albums.cover_image_id = $1
+)
+RETURNING images.id;
@@ -0,0 +1,3 @@
+UPDATE photos
+SET album_id = NULL
+WHERE photos.album_id = $1;
@@ -6,12 +6,18 @@ SELECT
     albums.owner_user_id AS "album_owner_user_id!",
     albums.cover_image_id AS "image_reference_id!",
     albums.created_at AS "album_created_at!",
+    albums.version AS "version!",
 
     images.id AS "image_id!",
     images.owner_user_id AS "image_owner_user_id!",
     images.url AS "url!",
     images.file_size AS "size!",
     images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
     images.created_at AS "image_created_at!"
 FROM
     albums
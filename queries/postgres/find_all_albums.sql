@@ -6,14 +6,27 @@ SELECT
     albums.owner_user_id AS "album_owner_user_id!",
     albums.cover_image_id AS "image_reference_id!",
     albums.created_at AS "album_created_at!",
+    albums.version AS "version!",
 
     images.id AS "image_id!",
     images.owner_user_id AS "image_owner_user_id!",
     images.url AS "url!",
     images.file_size AS "size!",
     images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
     images.created_at AS "image_created_at!"
 FROM
     albums
 LEFT JOIN
-    images ON albums.cover_image_id = images.id;
+    images ON albums.cover_image_id = images.id
+WHERE
+    $2::timestamptz IS NULL
+    OR (albums.created_at, albums.id) < ($2::timestamptz, $3::uuid)
+ORDER BY
+    albums.created_at DESC,
+    albums.id DESC
+LIMIT $1;
@@ -0,0 +1,40 @@
+SELECT
+    photos.id AS "photo_id!",
+    photos.title AS "title!",
+    photos.description AS "description!",
+    photos.visibility AS "visibility!: _",
+    photos.owner_user_id AS "photo_owner_user_id!",
+    photos.tags AS "tags!: Vec<String>",
+    photos.category AS "category!: _",
+    photos.album_id AS "album_id?",
+    photos.image_id AS "image_reference_id!",
+    photos.is_deleted AS "is_deleted!",
+    photos.created_at AS "photo_created_at!",
+    photos.version AS "version!",
+
+    images.id AS "image_id!",
+    images.url AS "url!",
+    images.owner_user_id AS "image_owner_user_id!",
+    images.file_size AS "size!",
+    images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
+    images.created_at AS "image_created_at!"
+FROM
+    photos
+LEFT JOIN
+    images ON photos.image_id = images.id
+WHERE
+    photos.owner_user_id = $1
+    AND photos.is_deleted = true
+    AND (
+        $3::timestamptz IS NULL
+        OR (photos.created_at, photos.id) < ($3::timestamptz, $4::uuid)
+    )
+ORDER BY
+    photos.created_at DESC,
+    photos.id DESC
+LIMIT $2;
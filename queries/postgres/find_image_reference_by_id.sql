@@ -5,8 +5,19 @@ SELECT
     images.file_size AS "size!",
     images.visibility AS "visibility!: _",
     images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
     images.created_at AS "created_at!"
 FROM
     images
 WHERE
-    images.id = $1;
\ No newline at end of file
+    images.id = $1
+    AND NOT EXISTS (
+        SELECT 1
+        FROM photos
+        WHERE photos.image_id = images.id
+        AND photos.is_deleted = true
+    );
\ No newline at end of file
@@ -0,0 +1,13 @@
+SELECT
+    image_renditions.image_id,
+    image_renditions.size AS "size!: _",
+    image_renditions.width,
+    image_renditions.height,
+    image_renditions.format AS "format!: _",
+    image_renditions.file_size,
+    image_renditions.created_at
+FROM
+    image_renditions
+WHERE
+    image_renditions.image_id = $1
+    AND image_renditions.size = $2;
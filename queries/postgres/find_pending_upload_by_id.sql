@@ -0,0 +1,16 @@
+SELECT
+    pending_uploads.id,
+    pending_uploads.owner_user_id,
+    pending_uploads.filename,
+    pending_uploads.title,
+    pending_uploads.description,
+    pending_uploads.category,
+    pending_uploads.tags,
+    pending_uploads.album_id,
+    pending_uploads.visibility AS "visibility!: _",
+    pending_uploads.created_at,
+    pending_uploads.expires_at
+FROM
+    pending_uploads
+WHERE
+    pending_uploads.id = $1;
@@ -10,16 +10,23 @@ SELECT
     photos.image_id AS "image_reference_id!",
     photos.is_deleted AS "is_deleted!",
     photos.created_at AS "photo_created_at!",
+    photos.version AS "version!",
 
     images.id AS "image_id!",
     images.url AS "url!",
     images.owner_user_id AS "image_owner_user_id!",
     images.file_size AS "size!",
     images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
     images.created_at AS "image_created_at!"
 FROM
     photos
 LEFT JOIN
     images ON photos.image_id = images.id
 WHERE
-    photos.id = $1;
\ No newline at end of file
+    photos.id = $1
+    AND photos.is_deleted = false;
\ No newline at end of file
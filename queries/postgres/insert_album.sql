@@ -7,5 +7,6 @@ RETURNING
     visibility AS "visibility!: _",
     owner_user_id AS "owner_user_id!",
     cover_image_id AS "cover_image_id!",
-    created_at AS "created_at!"
+    created_at AS "created_at!",
+    version AS "version!"
 
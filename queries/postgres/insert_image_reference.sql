@@ -1,3 +1,3 @@
-INSERT INTO images ( id, owner_user_id, visibility, url, file_size, format )
-VALUES ( $1, $2, $3, $4, $5, $6 )
-RETURNING id, owner_user_id, visibility AS "visibility!: _", url, file_size AS "size!", format AS "format: _", created_at AS "created_at!"
+INSERT INTO images ( id, owner_user_id, visibility, url, file_size, format, width, height, color_type, bit_depth, sha256 )
+VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11 )
+RETURNING id, owner_user_id, visibility AS "visibility!: _", url, file_size AS "size!", format AS "format: _", width, height, color_type AS "color_type: _", bit_depth, sha256, created_at AS "created_at!"
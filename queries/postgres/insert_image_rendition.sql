@@ -0,0 +1,3 @@
+INSERT INTO image_renditions ( image_id, size, width, height, format, file_size )
+VALUES ( $1, $2, $3, $4, $5, $6 )
+RETURNING image_id, size AS "size!: _", width, height, format AS "format!: _", file_size, created_at
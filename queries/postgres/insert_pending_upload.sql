@@ -0,0 +1,3 @@
+INSERT INTO pending_uploads ( id, owner_user_id, filename, title, description, category, tags, album_id, visibility, expires_at )
+VALUES ( $1, $2, $3, $4, $5, $6, $7, $8, $9, $10 )
+RETURNING id, owner_user_id, filename, title, description, category, tags, album_id, visibility AS "visibility!: _", created_at, expires_at
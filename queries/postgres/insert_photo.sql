@@ -10,4 +10,5 @@ RETURNING id,
           album_id AS "album_id?",
           image_id,
           is_deleted,
-          created_at AS "created_at!"
\ No newline at end of file
+          created_at AS "created_at!",
+          version AS "version!"
\ No newline at end of file
@@ -0,0 +1,8 @@
+INSERT INTO image_renditions ( image_id, size, width, height, format, file_size )
+VALUES ( $1, $2, $3, $4, $5, $6 )
+ON CONFLICT ( image_id, size ) DO UPDATE
+SET width = EXCLUDED.width,
+    height = EXCLUDED.height,
+    format = EXCLUDED.format,
+    file_size = EXCLUDED.file_size,
+    created_at = NOW()
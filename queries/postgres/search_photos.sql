@@ -0,0 +1,59 @@
+WITH search_query AS (
+    SELECT websearch_to_tsquery('english', $1) AS tsquery
+), ranked_photos AS (
+    SELECT
+        photos.*,
+        ts_rank(photos.search_vector, search_query.tsquery) AS rank,
+        search_query.tsquery
+    FROM
+        photos,
+        search_query
+    WHERE
+        photos.is_deleted = false
+        AND photos.search_vector @@ search_query.tsquery
+)
+SELECT
+    ranked_photos.id AS "photo_id!",
+    ranked_photos.title AS "title!",
+    ranked_photos.description AS "description!",
+    ranked_photos.visibility AS "visibility!: _",
+    ranked_photos.owner_user_id AS "photo_owner_user_id!",
+    ranked_photos.tags AS "tags!: Vec<String>",
+    ranked_photos.category AS "category!: _",
+    ranked_photos.album_id AS "album_id?",
+    ranked_photos.image_id AS "image_reference_id!",
+    ranked_photos.is_deleted AS "is_deleted!",
+    ranked_photos.created_at AS "photo_created_at!",
+    ranked_photos.version AS "version!",
+
+    images.id AS "image_id!",
+    images.owner_user_id AS "image_owner_user_id!",
+    images.url AS "url!",
+    images.file_size AS "size!",
+    images.format AS "format!: _",
+    images.width,
+    images.height,
+    images.color_type AS "color_type: _",
+    images.bit_depth,
+    images.sha256,
+    images.created_at AS "image_created_at!",
+
+    ranked_photos.rank AS "rank!",
+    ts_headline(
+        'english',
+        ranked_photos.title || ' ' || ranked_photos.description,
+        ranked_photos.tsquery,
+        'StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=20, MinWords=5'
+    ) AS "headline!"
+FROM
+    ranked_photos
+LEFT JOIN
+    images ON ranked_photos.image_id = images.id
+WHERE
+    $3::real IS NULL
+    OR (ranked_photos.rank, ranked_photos.created_at, ranked_photos.id) < ($3::real, $4::timestamptz, $5::uuid)
+ORDER BY
+    ranked_photos.rank DESC,
+    ranked_photos.created_at DESC,
+    ranked_photos.id DESC
+LIMIT $2;
@@ -0,0 +1,8 @@
+UPDATE photos
+SET
+    is_deleted = photos.is_deleted OR photos.owner_user_id = albums.owner_user_id,
+    album_id = NULL
+FROM albums
+WHERE photos.album_id = albums.id
+AND albums.id = $1
+RETURNING photos.image_id AS "image_id!", photos.is_deleted AS "is_deleted!";
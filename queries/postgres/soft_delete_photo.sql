@@ -0,0 +1,5 @@
+UPDATE photos
+SET is_deleted = true
+WHERE photos.id = $1
+AND photos.is_deleted = false
+AND ($2::integer[] IS NULL OR photos.version = ANY($2));
\ No newline at end of file
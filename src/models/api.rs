@@ -1,10 +1,14 @@
+use actix_web::http::header::{EntityTag, IfMatch};
+use actix_web::web;
 use serde::{Deserialize, Serialize};
-use crate::models::service::image::ImageTransformOptions;
+use crate::models::service::pagination::{Cursor, InvalidCursorError, PageRequest};
 
-use crate::models::service::Visibility;
+use crate::models::service::{ExpectedVersion, Visibility};
 
 pub mod photo;
 pub mod album;
+pub mod image;
+pub mod upload;
 
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub enum VisibilityApi {
@@ -34,16 +38,79 @@ impl From<Visibility> for VisibilityApi {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
-pub struct ImageTransformOptionsApi {
-    huerotate: Option<i32>,
-    #[serde(deserialize_with = "serde_tuple::deserialize_tuple")]
-    #[serde(default)]
-    thumbnail: Option<(u32, u32)>
+pub struct PageQueryApi {
+    limit: Option<u32>,
+    cursor: Option<String>,
 }
 
-impl From<ImageTransformOptionsApi> for ImageTransformOptions {
-    fn from(convert_options_api: ImageTransformOptionsApi) -> Self {
-        Self::new(convert_options_api.huerotate, convert_options_api.thumbnail)
+impl TryFrom<PageQueryApi> for PageRequest {
+    type Error = InvalidCursorError;
+
+    fn try_from(page_query_api: PageQueryApi) -> Result<Self, Self::Error> {
+        let cursor = page_query_api.cursor
+            .as_deref()
+            .map(Cursor::decode)
+            .transpose()?;
+
+        Ok(PageRequest::new(page_query_api.limit, cursor))
+    }
+}
+
+/// The strong `ETag` of a version of a photo or an album.
+pub fn version_entity_tag(version: i32) -> EntityTag {
+    EntityTag::new_strong(version.to_string())
+}
+
+/// A conditional write without an `If-Match` header.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct MissingIfMatchError;
+
+impl ExpectedVersion {
+    /// Reads the mandatory `If-Match` header of a write. Entity tags are compared with the
+    /// strong comparison function, so weak or foreign tags never match.
+    pub fn try_from(if_match: Option<web::Header<IfMatch>>) -> Result<Self, MissingIfMatchError> {
+        match if_match.map(web::Header::into_inner) {
+            None => Err(MissingIfMatchError),
+            Some(IfMatch::Any) => Ok(ExpectedVersion::Any),
+            Some(IfMatch::Items(entity_tags)) => Ok(ExpectedVersion::OneOf(
+                entity_tags.iter()
+                    .filter(|entity_tag| !entity_tag.weak)
+                    .filter_map(|entity_tag| entity_tag.tag().parse().ok())
+                    .collect()
+            )),
+        }
+    }
+}
+
+/// A member of a JSON Merge Patch (RFC 7396) document set to `null`, while the
+/// underlying field cannot be cleared.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct NotNullableFieldError(pub &'static str);
+
+/// Deserializes a JSON Merge Patch (RFC 7396) member: with `#[serde(default)]` a missing
+/// member is `None`, an explicit `null` is `Some(None)` and a value is `Some(Some(_))`.
+pub mod serde_merge_patch {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+        where
+            T: Deserialize<'de>,
+            D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+
+    /// Rejects `null` for the members that cannot be cleared.
+    pub fn not_nullable<T>(field: &'static str, member: Option<Option<T>>) -> Result<Option<T>, super::NotNullableFieldError> {
+        match member {
+            Some(None) => Err(super::NotNullableFieldError(field)),
+            member => Ok(member.flatten()),
+        }
+    }
+
+    /// Turns `null` into the empty value of the members that can be cleared.
+    pub fn null_as_default<T: Default>(member: Option<Option<T>>) -> Option<T> {
+        member.map(Option::unwrap_or_default)
     }
 }
 
@@ -112,4 +179,24 @@ pub mod serde_tuple {
         }
         Ok(None)
     }
-}
\ No newline at end of file
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_only_expect_strong_versions() {
+        let if_match = IfMatch::Items(vec![
+            EntityTag::new_strong("3".to_string()),
+            EntityTag::new_weak("4".to_string()),
+            EntityTag::new_strong("not-a-version".to_string()),
+        ]);
+
+        let expected_version = ExpectedVersion::try_from(Some(web::Header(if_match))).unwrap();
+
+        assert_eq!(expected_version, ExpectedVersion::OneOf(vec![3]));
+        assert_eq!(ExpectedVersion::try_from(Some(web::Header(IfMatch::Any))), Ok(ExpectedVersion::Any));
+        assert_eq!(ExpectedVersion::try_from(None), Err(MissingIfMatchError));
+    }
+}
@@ -1,15 +1,15 @@
 use actix_multipart::form::MultipartForm;
 use actix_multipart::form::tempfile::TempFile;
 use serde::{Deserialize, Serialize};
-use crate::models::api::VisibilityApi;
+use crate::models::api::{serde_merge_patch, NotNullableFieldError, VisibilityApi};
+use crate::models::api::image::ImagePropertiesApi;
 use actix_multipart::form::json::Json as MpJson;
 use chrono::{DateTime, Utc};
 use url::Url;
 use uuid::Uuid;
-use crate::models::service::album::{Album, CreateAlbumWithCover, UpdateAlbum};
+use crate::models::service::album::{Album, CreateAlbumWithCover, DeleteAlbum, DeleteAlbumPhotosMode, UpdateAlbum};
 use crate::models::service::Visibility;
 use crate::models::service::image::UploadImage;
-use crate::models::service::photo::UpdatePhoto;
 
 #[derive(Debug, MultipartForm)]
 pub struct CreateAlbumApi {
@@ -57,6 +57,8 @@ pub struct AlbumApi {
     pub cover_image_id: Uuid,
     #[serde(rename = "coverImageUrl", with = "crate::models::api::serde_url")]
     cover_image_url: Url,
+    #[serde(rename = "coverImageProperties")]
+    pub cover_image_properties: ImagePropertiesApi,
 }
 
 impl From<Album> for AlbumApi {
@@ -69,24 +71,61 @@ impl From<Album> for AlbumApi {
             created_at: album.created_at(),
             cover_image_id: album.cover_image_id(),
             cover_image_url: album.cover_image_url().clone(),
+            cover_image_properties: ImagePropertiesApi::from(album.cover_image_properties()),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct PatchAlbumApi {
-    pub title: Option<String>,
-    #[serde(rename = "albumId")]
-    pub album_id: Option<Uuid>,
-    pub visibility: Option<Visibility>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub visibility: Option<Option<VisibilityApi>>,
+    #[serde(rename = "coverPhotoId", default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub cover_photo_id: Option<Option<Uuid>>,
 }
 
 impl UpdateAlbum {
-    pub fn from(album_id: Uuid, patch_album_api: PatchAlbumApi) -> Self {
-        Self::new(
+    pub fn try_from(album_id: Uuid, patch_album_api: PatchAlbumApi) -> Result<Self, NotNullableFieldError> {
+        Ok(Self::new(
             &album_id,
-            patch_album_api.title.as_ref(),
-            patch_album_api.visibility.as_ref(),
-        )
+            serde_merge_patch::not_nullable("title", patch_album_api.title)?,
+            serde_merge_patch::null_as_default(patch_album_api.description),
+            serde_merge_patch::not_nullable("visibility", patch_album_api.visibility)?.map(Visibility::from),
+            serde_merge_patch::not_nullable("coverPhotoId", patch_album_api.cover_photo_id)?,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteAlbumPhotosModeApi {
+    Detach,
+    Delete,
+}
+
+impl From<DeleteAlbumPhotosModeApi> for DeleteAlbumPhotosMode {
+    fn from(delete_album_photos_mode_api: DeleteAlbumPhotosModeApi) -> Self {
+        match delete_album_photos_mode_api {
+            DeleteAlbumPhotosModeApi::Detach => DeleteAlbumPhotosMode::Detach,
+            DeleteAlbumPhotosModeApi::Delete => DeleteAlbumPhotosMode::Delete,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeleteAlbumQueryApi {
+    pub photos: Option<DeleteAlbumPhotosModeApi>,
+}
+
+impl DeleteAlbum {
+    pub fn from(album_id: Uuid, delete_album_query_api: DeleteAlbumQueryApi) -> Self {
+        let photos_mode = delete_album_query_api.photos
+            .unwrap_or(DeleteAlbumPhotosModeApi::Detach);
+
+        Self::new(&album_id, DeleteAlbumPhotosMode::from(photos_mode))
     }
 }
\ No newline at end of file
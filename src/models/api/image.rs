@@ -0,0 +1,459 @@
+use std::future::{ready, Ready};
+use std::ops::RangeInclusive;
+use std::time::SystemTime;
+
+use actix_web::dev::Payload;
+use actix_web::http::header::{Accept, ByteRangeSpec, Header, IfModifiedSince, IfNoneMatch, Quality, Range};
+use actix_web::{web, FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::models::api::serde_tuple;
+use crate::models::service::image::{ByteRange, ColorType, DownloadCondition, Flip, ImageEncodingOptions, ImageTransformation, ImageTransformOptions, ImageProperties, InvalidImageTransformationError, RenditionSize, ResizeFit, Rotation};
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ImagePropertiesApi {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    #[serde(rename = "colorType")]
+    pub color_type: Option<ColorTypeApi>,
+    #[serde(rename = "bitDepth")]
+    pub bit_depth: Option<u8>,
+    pub sha256: Option<String>,
+}
+
+impl From<&ImageProperties> for ImagePropertiesApi {
+    fn from(image_properties: &ImageProperties) -> Self {
+        Self {
+            width: image_properties.width(),
+            height: image_properties.height(),
+            color_type: image_properties.color_type().map(ColorTypeApi::from),
+            bit_depth: image_properties.bit_depth(),
+            sha256: image_properties.sha256().map(hex::encode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorTypeApi {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl From<ColorType> for ColorTypeApi {
+    fn from(color_type: ColorType) -> Self {
+        match color_type {
+            ColorType::Gray => ColorTypeApi::Gray,
+            ColorType::GrayAlpha => ColorTypeApi::GrayAlpha,
+            ColorType::Rgb => ColorTypeApi::Rgb,
+            ColorType::Rgba => ColorTypeApi::Rgba,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct ImageTransformOptionsApi {
+    huerotate: Option<i32>,
+    #[serde(deserialize_with = "serde_tuple::deserialize_tuple")]
+    #[serde(default)]
+    thumbnail: Option<(u32, u32)>,
+    // e.g. `crop:10,10,400,300|rotate:90|grayscale`
+    ops: Option<String>,
+    format: Option<ImageOutputFormatApi>,
+    quality: Option<u8>,
+    // Not supported by the JPEG encoder, only `false` is accepted
+    progressive: Option<bool>,
+    compression: Option<u8>,
+    strip: Option<bool>,
+    size: Option<RenditionSizeApi>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenditionSizeApi {
+    Small,
+    Medium,
+    Large,
+}
+
+impl From<RenditionSizeApi> for RenditionSize {
+    fn from(rendition_size_api: RenditionSizeApi) -> Self {
+        match rendition_size_api {
+            RenditionSizeApi::Small => RenditionSize::Small,
+            RenditionSizeApi::Medium => RenditionSize::Medium,
+            RenditionSizeApi::Large => RenditionSize::Large,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageOutputFormatApi {
+    Webp,
+    Avif,
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Png,
+}
+
+impl ImageOutputFormatApi {
+    // In order of preference, WebP being much faster to encode than AVIF
+    const NEGOTIABLE_FORMATS: [ImageOutputFormatApi; 4] = [
+        ImageOutputFormatApi::Webp,
+        ImageOutputFormatApi::Avif,
+        ImageOutputFormatApi::Jpeg,
+        ImageOutputFormatApi::Png,
+    ];
+
+    fn mime_type(&self) -> mime::Mime {
+        match self {
+            ImageOutputFormatApi::Webp => "image/webp".parse().unwrap(),
+            ImageOutputFormatApi::Avif => "image/avif".parse().unwrap(),
+            ImageOutputFormatApi::Jpeg => mime::IMAGE_JPEG,
+            ImageOutputFormatApi::Png => mime::IMAGE_PNG,
+        }
+    }
+
+    // Wildcards do not select any format, the image then keeps its original format
+    fn negotiate(accept: &Accept) -> Option<Self> {
+        let quality = |output_format: &ImageOutputFormatApi| accept.iter()
+            .find(|quality_item| quality_item.item == output_format.mime_type())
+            .map(|quality_item| quality_item.quality)
+            .filter(|quality| *quality > Quality::ZERO);
+
+        Self::NEGOTIABLE_FORMATS
+            .iter()
+            .filter_map(|output_format| quality(output_format).map(|quality| (*output_format, quality)))
+            .fold(None, |best: Option<(Self, Quality)>, (output_format, quality)| match best {
+                Some((_, best_quality)) if best_quality >= quality => best,
+                _ => Some((output_format, quality)),
+            })
+            .map(|(output_format, _)| output_format)
+    }
+}
+
+impl From<ImageOutputFormatApi> for ImageFormat {
+    fn from(output_format_api: ImageOutputFormatApi) -> Self {
+        match output_format_api {
+            ImageOutputFormatApi::Webp => ImageFormat::WebP,
+            ImageOutputFormatApi::Avif => ImageFormat::Avif,
+            ImageOutputFormatApi::Jpeg => ImageFormat::Jpeg,
+            ImageOutputFormatApi::Png => ImageFormat::Png,
+        }
+    }
+}
+
+impl ImageTransformOptions {
+    // The `format` query parameter takes precedence over the `Accept` header
+    pub fn try_from(
+        convert_options_api: ImageTransformOptionsApi,
+        accept: Option<web::Header<Accept>>
+    ) -> Result<Self, InvalidImageTransformationError> {
+        if let Some(rendition_size) = convert_options_api.size {
+            let ImageTransformOptionsApi { huerotate: None, thumbnail: None, ops: None, format: None, quality: None, progressive: None, compression: None, strip: None, .. } = convert_options_api else {
+                return Err(InvalidImageTransformationError::RenditionSizeWithOptions);
+            };
+            return Ok(Self::rendition(RenditionSize::from(rendition_size)));
+        }
+
+        let operations = convert_options_api.ops
+            .as_deref()
+            .map(parse_operations)
+            .transpose()?
+            .unwrap_or_default();
+        let output_format = convert_options_api.format
+            .or_else(|| accept.and_then(|accept| ImageOutputFormatApi::negotiate(&accept)))
+            .map(ImageFormat::from);
+        if convert_options_api.progressive == Some(true) {
+            return Err(InvalidImageTransformationError::UnsupportedEncoding("progressive JPEG encoding"));
+        }
+        if convert_options_api.quality.is_some() && output_format == Some(ImageFormat::WebP) {
+            return Err(InvalidImageTransformationError::UnsupportedEncoding("quality of the lossless WebP encoder"));
+        }
+        let encoding = ImageEncodingOptions::new(
+            check_range(convert_options_api.quality, 1..=100, "quality between 1 and 100")?,
+            check_range(convert_options_api.compression, 0..=9, "compression between 0 and 9")?,
+            convert_options_api.strip.unwrap_or(true),
+        );
+
+        let thumbnail = match convert_options_api.thumbnail {
+            Some((width, height)) if !(1..=MAX_DIMENSION).contains(&width) || !(1..=MAX_DIMENSION).contains(&height) => {
+                return Err(InvalidImageTransformationError::InvalidParameters("thumbnail=width,height, with 0 < width, height <= 8192"));
+            },
+            thumbnail => thumbnail,
+        };
+
+        Ok(Self::new(convert_options_api.huerotate, thumbnail, operations, output_format, encoding))
+    }
+}
+
+// `If-None-Match` takes precedence over `If-Modified-Since`, its entity tags being compared
+// with the weak comparison (RFC 9110, section 13.1.2)
+impl FromRequest for DownloadCondition {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // An absent `If-None-Match` header parses as an empty list of entity tags
+        let if_none_match = req.headers()
+            .contains_key(IfNoneMatch::name())
+            .then(|| IfNoneMatch::parse(req).ok())
+            .flatten();
+        let download_condition = match (if_none_match, IfModifiedSince::parse(req).ok()) {
+            (Some(IfNoneMatch::Any), _) => DownloadCondition::IfNoneMatchAny,
+            (Some(IfNoneMatch::Items(entity_tags)), _) => DownloadCondition::IfNoneMatch(
+                entity_tags.iter().map(|entity_tag| entity_tag.tag().to_string()).collect()
+            ),
+            (None, Some(IfModifiedSince(http_date))) => {
+                DownloadCondition::IfModifiedSince(DateTime::<Utc>::from(SystemTime::from(http_date)))
+            },
+            (None, None) => DownloadCondition::None,
+        };
+
+        ready(Ok(download_condition))
+    }
+}
+
+impl ByteRange {
+    // A request for several ranges gets the whole image
+    pub fn from(range: Option<web::Header<Range>>) -> Option<Self> {
+        match range.map(web::Header::into_inner) {
+            Some(Range::Bytes(byte_range_specs)) if byte_range_specs.len() == 1 => match byte_range_specs[0] {
+                ByteRangeSpec::FromTo(first, last) => Some(ByteRange::FromTo(first, last)),
+                ByteRangeSpec::From(first) => Some(ByteRange::From(first)),
+                ByteRangeSpec::Last(length) => Some(ByteRange::Suffix(length)),
+            },
+            _ => None,
+        }
+    }
+}
+
+const MAX_OPERATIONS: usize = 10;
+const MAX_DIMENSION: u32 = 8192;
+const MAX_BLUR_SIGMA: f32 = 50.0;
+
+fn parse_operations(ops: &str) -> Result<Vec<ImageTransformation>, InvalidImageTransformationError> {
+    let operations: Vec<&str> = ops.split('|').collect();
+    if operations.len() > MAX_OPERATIONS {
+        return Err(InvalidImageTransformationError::TooManyOperations(MAX_OPERATIONS));
+    }
+
+    operations.into_iter()
+        .map(|operation| {
+            let (name, parameters) = operation.split_once(':').unwrap_or((operation, ""));
+            parse_operation(name.trim(), parameters.trim())
+        })
+        .collect()
+}
+
+fn parse_operation(name: &str, parameters: &str) -> Result<ImageTransformation, InvalidImageTransformationError> {
+    let invalid_parameters = |usage: &'static str| InvalidImageTransformationError::InvalidParameters(usage);
+
+    match name {
+        "crop" => {
+            let usage = "crop:x,y,width,height";
+            let [x, y, width, height] = parse_numbers(parameters).ok_or_else(|| invalid_parameters(usage))?;
+            if width == 0 || height == 0 {
+                return Err(invalid_parameters(usage));
+            }
+            Ok(ImageTransformation::Crop { x, y, width, height })
+        },
+        "rotate" => match parameters {
+            "90" => Ok(ImageTransformation::Rotate(Rotation::Rotate90)),
+            "180" => Ok(ImageTransformation::Rotate(Rotation::Rotate180)),
+            "270" => Ok(ImageTransformation::Rotate(Rotation::Rotate270)),
+            _ => Err(invalid_parameters("rotate:90|180|270")),
+        },
+        "flip" => match parameters {
+            "h" | "horizontal" => Ok(ImageTransformation::Flip(Flip::Horizontal)),
+            "v" | "vertical" => Ok(ImageTransformation::Flip(Flip::Vertical)),
+            _ => Err(invalid_parameters("flip:h|v")),
+        },
+        "resize" => {
+            let usage = "resize:widthxheight[,fit=contain|cover|fill]";
+            let (size, fit) = parameters.split_once(',').unwrap_or((parameters, "fit=contain"));
+            let fit = match fit.trim() {
+                "fit=contain" => ResizeFit::Contain,
+                "fit=cover" => ResizeFit::Cover,
+                "fit=fill" => ResizeFit::Fill,
+                _ => return Err(invalid_parameters(usage)),
+            };
+            let (width, height) = size.split_once('x').ok_or_else(|| invalid_parameters(usage))?;
+            let (width, height) = width.trim().parse::<u32>().ok()
+                .zip(height.trim().parse::<u32>().ok())
+                .filter(|(width, height)| (1..=MAX_DIMENSION).contains(width) && (1..=MAX_DIMENSION).contains(height))
+                .ok_or_else(|| invalid_parameters(usage))?;
+            Ok(ImageTransformation::Resize { width, height, fit })
+        },
+        "grayscale" if parameters.is_empty() => Ok(ImageTransformation::Grayscale),
+        "grayscale" => Err(invalid_parameters("grayscale")),
+        "blur" => parameters.parse::<f32>().ok()
+            .filter(|sigma| *sigma > 0.0 && *sigma <= MAX_BLUR_SIGMA)
+            .map(ImageTransformation::Blur)
+            .ok_or_else(|| invalid_parameters("blur:sigma, with 0 < sigma <= 50")),
+        "brightness" => parameters.parse::<i32>().ok()
+            .filter(|brightness| (-255..=255).contains(brightness))
+            .map(ImageTransformation::Brightness)
+            .ok_or_else(|| invalid_parameters("brightness:value, with -255 <= value <= 255")),
+        "contrast" => parameters.parse::<f32>().ok()
+            .filter(|contrast| (-100.0..=100.0).contains(contrast))
+            .map(ImageTransformation::Contrast)
+            .ok_or_else(|| invalid_parameters("contrast:value, with -100 <= value <= 100")),
+        _ => Err(InvalidImageTransformationError::UnknownOperation(name.to_string())),
+    }
+}
+
+fn check_range(value: Option<u8>, range: RangeInclusive<u8>, usage: &'static str) -> Result<Option<u8>, InvalidImageTransformationError> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(InvalidImageTransformationError::InvalidParameters(usage)),
+        _ => Ok(value),
+    }
+}
+
+fn parse_numbers<const N: usize>(parameters: &str) -> Option<[u32; N]> {
+    let numbers = parameters.split(',')
+        .map(|number| number.trim().parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    numbers.try_into().ok()
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use std::str::FromStr;
+
+    use actix_web::http::header::{EntityTag, Header, ACCEPT};
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn should_negotiate_the_output_format() {
+        let parse_accept = |accept: &str| {
+            let request = TestRequest::default().insert_header((ACCEPT, accept)).to_http_request();
+            Accept::parse(&request).unwrap()
+        };
+        let browser_accept = parse_accept("image/avif,image/webp,image/apng,image/*,*/*;q=0.8");
+        let avif_accept = parse_accept("image/webp;q=0.5,image/avif");
+        let wildcard_accept = parse_accept("image/*,*/*;q=0.8");
+        let refused_accept = parse_accept("image/webp;q=0");
+
+        assert_eq!(ImageOutputFormatApi::negotiate(&browser_accept), Some(ImageOutputFormatApi::Webp));
+        assert_eq!(ImageOutputFormatApi::negotiate(&avif_accept), Some(ImageOutputFormatApi::Avif));
+        assert_eq!(ImageOutputFormatApi::negotiate(&wildcard_accept), None);
+        assert_eq!(ImageOutputFormatApi::negotiate(&refused_accept), None);
+    }
+
+    #[test]
+    fn should_parse_an_ordered_pipeline() {
+        let operations = parse_operations("crop:10,10,400,300|rotate:90|resize:800x600,fit=cover|grayscale|blur:2").unwrap();
+
+        assert_eq!(operations, vec![
+            ImageTransformation::Crop { x: 10, y: 10, width: 400, height: 300 },
+            ImageTransformation::Rotate(Rotation::Rotate90),
+            ImageTransformation::Resize { width: 800, height: 600, fit: ResizeFit::Cover },
+            ImageTransformation::Grayscale,
+            ImageTransformation::Blur(2.0),
+        ]);
+    }
+
+    #[test]
+    fn should_reject_invalid_operations() {
+        assert_eq!(parse_operations("sepia"), Err(InvalidImageTransformationError::UnknownOperation("sepia".to_string())));
+        assert_eq!(parse_operations("rotate:45"), Err(InvalidImageTransformationError::InvalidParameters("rotate:90|180|270")));
+        assert_eq!(parse_operations("crop:10,10,0,300"), Err(InvalidImageTransformationError::InvalidParameters("crop:x,y,width,height")));
+        assert_eq!(parse_operations("resize:100000x10"), Err(InvalidImageTransformationError::InvalidParameters("resize:widthxheight[,fit=contain|cover|fill]")));
+        assert_eq!(parse_operations("blur:NaN"), Err(InvalidImageTransformationError::InvalidParameters("blur:sigma, with 0 < sigma <= 50")));
+        assert_eq!(parse_operations(&["grayscale"; 11].join("|")), Err(InvalidImageTransformationError::TooManyOperations(10)));
+    }
+
+    #[test]
+    fn should_share_the_rendition_key_of_equivalent_options() {
+        let rendition_key = |query: &str| {
+            let convert_options_api = web::Query::<ImageTransformOptionsApi>::from_query(query).unwrap().into_inner();
+            ImageTransformOptions::try_from(convert_options_api, None).unwrap().rendition_key(ImageFormat::Png)
+        };
+
+        assert_eq!(rendition_key("format=jpg&ops=flip:h"), rendition_key("ops=flip:horizontal&format=jpeg&strip=true"));
+        assert_ne!(rendition_key("format=jpeg&ops=flip:h"), rendition_key("format=jpeg&ops=flip:v"));
+        assert_ne!(rendition_key("format=jpeg"), rendition_key("format=jpeg&quality=50"));
+    }
+
+    #[test]
+    fn should_not_combine_a_rendition_size_with_other_options() {
+        let try_from = |query: &str| {
+            let convert_options_api = web::Query::<ImageTransformOptionsApi>::from_query(query).unwrap().into_inner();
+            ImageTransformOptions::try_from(convert_options_api, None)
+        };
+
+        assert_eq!(try_from("size=medium").unwrap().rendition_size(), Some(RenditionSize::Medium));
+        assert_eq!(try_from("size=small&format=png").unwrap_err(), InvalidImageTransformationError::RenditionSizeWithOptions);
+    }
+
+    #[test]
+    fn should_reject_an_oversized_thumbnail() {
+        let try_from = |query: &str| {
+            let convert_options_api = web::Query::<ImageTransformOptionsApi>::from_query(query).unwrap().into_inner();
+            ImageTransformOptions::try_from(convert_options_api, None)
+        };
+
+        let usage = "thumbnail=width,height, with 0 < width, height <= 8192";
+        assert_eq!(try_from("thumbnail=100000,10").unwrap_err(), InvalidImageTransformationError::InvalidParameters(usage));
+        assert_eq!(try_from("thumbnail=0,10").unwrap_err(), InvalidImageTransformationError::InvalidParameters(usage));
+        assert!(try_from("thumbnail=8192,8192").is_ok());
+    }
+
+    #[test]
+    fn should_reject_the_encoder_settings_that_are_not_supported() {
+        let try_from = |query: &str| {
+            let convert_options_api = web::Query::<ImageTransformOptionsApi>::from_query(query).unwrap().into_inner();
+            ImageTransformOptions::try_from(convert_options_api, None)
+        };
+
+        assert_eq!(try_from("format=jpeg&progressive=true").unwrap_err(), InvalidImageTransformationError::UnsupportedEncoding("progressive JPEG encoding"));
+        assert_eq!(try_from("format=webp&quality=50").unwrap_err(), InvalidImageTransformationError::UnsupportedEncoding("quality of the lossless WebP encoder"));
+        assert!(try_from("format=jpeg&progressive=false&quality=50").is_ok());
+    }
+
+    #[actix_web::test]
+    async fn should_prefer_if_none_match_to_if_modified_since() {
+        let download_condition = |headers: &[(&str, &str)]| {
+            let req = headers.iter().fold(TestRequest::default(), |req, header| req.insert_header(*header)).to_http_request();
+            DownloadCondition::extract(&req)
+        };
+        let if_modified_since = ("If-Modified-Since", "Thu, 01 Jan 1970 00:00:00 GMT");
+
+        assert_eq!(
+            download_condition(&[("If-None-Match", "W/\"abc\", \"def\""), if_modified_since]).await.unwrap(),
+            DownloadCondition::IfNoneMatch(vec!["abc".to_string(), "def".to_string()])
+        );
+        assert_eq!(
+            download_condition(&[if_modified_since]).await.unwrap(),
+            DownloadCondition::IfModifiedSince(DateTime::<Utc>::UNIX_EPOCH)
+        );
+        assert_eq!(download_condition(&[("If-None-Match", "*")]).await.unwrap(), DownloadCondition::IfNoneMatchAny);
+        assert_eq!(download_condition(&[]).await.unwrap(), DownloadCondition::None);
+    }
+
+    #[test]
+    fn should_resolve_a_single_byte_range() {
+        let byte_range = |range: &str| ByteRange::from(Some(web::Header(Range::from_str(range).unwrap())));
+
+        let content_range = byte_range("bytes=100-199").unwrap().resolve(1000).unwrap();
+        assert_eq!((content_range.first(), content_range.last(), content_range.size()), (100, 199, 1000));
+        let content_range = byte_range("bytes=900-").unwrap().resolve(1000).unwrap();
+        assert_eq!((content_range.first(), content_range.last()), (900, 999));
+        let content_range = byte_range("bytes=-2000").unwrap().resolve(1000).unwrap();
+        assert_eq!((content_range.first(), content_range.last()), (0, 999));
+        let content_range = byte_range("bytes=500-5000").unwrap().resolve(1000).unwrap();
+        assert_eq!((content_range.first(), content_range.last()), (500, 999));
+
+        assert_eq!(byte_range("bytes=1000-").unwrap().resolve(1000), None);
+        assert_eq!(byte_range("bytes=-0").unwrap().resolve(1000), None);
+        assert_eq!(byte_range("bytes=0-1,5-6"), None);
+    }
+}
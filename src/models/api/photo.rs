@@ -1,12 +1,13 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use url::Url;
 use uuid::Uuid;
 use actix_multipart::form::MultipartForm;
 use actix_multipart::form::tempfile::TempFile;
 use actix_multipart::form::json::Json as MpJson;
-use crate::models::api::VisibilityApi;
-use crate::models::service::photo::{Photo, UpdatePhoto, UploadPhoto};
+use crate::models::api::{serde_merge_patch, NotNullableFieldError, VisibilityApi};
+use crate::models::api::image::ImagePropertiesApi;
+use crate::models::service::photo::{InvalidPhotoQueryError, Photo, PhotoQuery, PhotoSearchResult, UpdatePhoto, UploadPhoto};
 use crate::models::service::Visibility;
 use crate::models::service::image::{UploadImage, UploadImageError};
 
@@ -26,6 +27,8 @@ pub struct PhotoApi {
     pub image_id: Uuid,
     #[serde(rename = "imageUrl", with = "crate::models::api::serde_url")]
     pub image_url: Url,
+    #[serde(rename = "imageProperties")]
+    pub image_properties: ImagePropertiesApi,
 }
 
 impl From<Photo> for PhotoApi {
@@ -41,10 +44,34 @@ impl From<Photo> for PhotoApi {
             created_at: photo.created_at(),
             image_id: photo.image().id().clone(),
             image_url: photo.image().url().clone(),
+            image_properties: ImagePropertiesApi::from(photo.image().properties()),
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PhotoSearchResultApi {
+    #[serde(flatten)]
+    pub photo: PhotoApi,
+    pub rank: f32,
+    pub headline: String,
+}
+
+impl From<PhotoSearchResult> for PhotoSearchResultApi {
+    fn from(photo_search_result: PhotoSearchResult) -> Self {
+        Self {
+            photo: PhotoApi::from(photo_search_result.photo().clone()),
+            rank: photo_search_result.rank(),
+            headline: photo_search_result.headline().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhotoSearchQueryApi {
+    pub q: String,
+}
+
 #[derive(Debug, MultipartForm)]
 pub struct UploadPhotoApi {
     #[multipart(limit = "100MB")]
@@ -88,21 +115,186 @@ impl TryFrom<UploadPhotoApi> for UploadPhoto {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct PatchPhotoApi {
-    pub title: Option<String>,
-    #[serde(rename = "albumId")]
-    pub album_id: Option<Uuid>,
-    pub visibility: Option<Visibility>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub title: Option<Option<String>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub category: Option<Option<String>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub tags: Option<Option<Vec<String>>>,
+    #[serde(rename = "albumId", default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub album_id: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "serde_merge_patch::deserialize")]
+    pub visibility: Option<Option<VisibilityApi>>,
 }
 
 impl UpdatePhoto {
-    pub fn from(photo_id: Uuid, patch_photo_api: PatchPhotoApi) -> Self {
-        Self::new(
+    pub fn try_from(photo_id: Uuid, patch_photo_api: PatchPhotoApi) -> Result<Self, NotNullableFieldError> {
+        Ok(Self::new(
             &photo_id,
-            patch_photo_api.title.as_ref(),
-            patch_photo_api.album_id.as_ref(),
-            patch_photo_api.visibility.as_ref(),
-        )
+            serde_merge_patch::not_nullable("title", patch_photo_api.title)?,
+            serde_merge_patch::null_as_default(patch_photo_api.description),
+            serde_merge_patch::null_as_default(patch_photo_api.category),
+            serde_merge_patch::null_as_default(patch_photo_api.tags),
+            patch_photo_api.album_id,
+            serde_merge_patch::not_nullable("visibility", patch_photo_api.visibility)?.map(Visibility::from),
+        ))
+    }
+}
+
+/// Filters of `GET /photos`, collected from the raw query pairs since `tag` can be repeated.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(from = "Vec<(String, String)>")]
+pub struct PhotoQueryApi {
+    pub tags: Vec<String>,
+    pub category: Option<String>,
+    pub album_id: Option<String>,
+    pub visibility: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub owner: Option<String>,
+}
+
+impl From<Vec<(String, String)>> for PhotoQueryApi {
+    fn from(query_pairs: Vec<(String, String)>) -> Self {
+        let mut photo_query_api = PhotoQueryApi::default();
+        for (key, value) in query_pairs {
+            match key.as_str() {
+                "tag" => photo_query_api.tags.push(value),
+                "category" => photo_query_api.category = Some(value),
+                "album_id" => photo_query_api.album_id = Some(value),
+                "visibility" => photo_query_api.visibility = Some(value),
+                "created_after" => photo_query_api.created_after = Some(value),
+                "created_before" => photo_query_api.created_before = Some(value),
+                "owner" => photo_query_api.owner = Some(value),
+                _ => {}
+            }
+        }
+        photo_query_api
     }
-}
\ No newline at end of file
+}
+
+impl PhotoQuery {
+    const OWNER_ME: &'static str = "me";
+
+    pub fn try_from(photo_query_api: PhotoQueryApi, authenticated_user_id: &Uuid) -> Result<Self, InvalidPhotoQueryError> {
+        let album_id = photo_query_api.album_id
+            .map(|album_id| Uuid::parse_str(&album_id))
+            .transpose()
+            .map_err(|_| InvalidPhotoQueryError::InvalidAlbum)?;
+        let visibility = photo_query_api.visibility
+            .map(|visibility| match visibility.to_lowercase().as_str() {
+                "public" => Ok(Visibility::Public),
+                "private" => Ok(Visibility::Private),
+                _ => Err(InvalidPhotoQueryError::InvalidVisibility),
+            })
+            .transpose()?;
+        let created_after = photo_query_api.created_after
+            .map(|created_after| parse_query_date(&created_after).ok_or(InvalidPhotoQueryError::InvalidCreatedAfter))
+            .transpose()?;
+        let created_before = photo_query_api.created_before
+            .map(|created_before| parse_query_date(&created_before).ok_or(InvalidPhotoQueryError::InvalidCreatedBefore))
+            .transpose()?;
+        if let (Some(created_after), Some(created_before)) = (created_after, created_before) {
+            if created_after >= created_before {
+                return Err(InvalidPhotoQueryError::InvalidDateRange);
+            }
+        }
+        let owner_user_id = match photo_query_api.owner.as_deref() {
+            None => None,
+            Some(Self::OWNER_ME) => Some(*authenticated_user_id),
+            Some(_) => return Err(InvalidPhotoQueryError::InvalidOwner),
+        };
+        let category = photo_query_api.category.filter(|category| !category.is_empty());
+        let tags = photo_query_api.tags
+            .into_iter()
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        Ok(Self::new(tags, category, album_id, visibility, created_after, created_before, owner_user_id))
+    }
+}
+
+/// Accepts either an RFC 3339 timestamp or a plain `YYYY-MM-DD` date (midnight UTC).
+fn parse_query_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date_time| date_time.and_utc()))
+}
+
+#[allow(unused_imports, dead_code)]
+mod tests {
+    use super::*;
+
+    fn photo_query_api(query_string: &str) -> PhotoQueryApi {
+        serde_urlencoded::from_str(query_string).unwrap()
+    }
+
+    #[test]
+    fn should_apply_merge_patch_semantics() {
+        let photo_id = Uuid::new_v4();
+        let patch_photo_api: PatchPhotoApi = serde_json::from_str(
+            r#"{"description": "new description", "tags": null, "albumId": null}"#
+        ).unwrap();
+
+        let update_photo = UpdatePhoto::try_from(photo_id, patch_photo_api).unwrap();
+
+        assert_eq!(update_photo.title(), &None);
+        assert_eq!(update_photo.description(), &Some("new description".to_string()));
+        assert_eq!(update_photo.category(), &None);
+        assert_eq!(update_photo.tags(), &Some(vec![]));
+        assert_eq!(update_photo.album_id(), &Some(None));
+        assert_eq!(update_photo.visibility(), &None);
+    }
+
+    #[test]
+    fn should_reject_clearing_a_required_field() {
+        let patch_photo_api: PatchPhotoApi = serde_json::from_str(r#"{"title": null}"#).unwrap();
+
+        let error = UpdatePhoto::try_from(Uuid::new_v4(), patch_photo_api).unwrap_err();
+
+        assert_eq!(error, NotNullableFieldError("title"));
+    }
+
+    #[test]
+    fn should_collect_repeated_tags() {
+        let user_id = Uuid::new_v4();
+        let api = photo_query_api("tag=sea&tag=sunset&category=travel&owner=me&limit=10");
+
+        let photo_query = PhotoQuery::try_from(api, &user_id).unwrap();
+
+        assert_eq!(photo_query.tags(), &vec!["sea".to_string(), "sunset".to_string()]);
+        assert_eq!(photo_query.category(), Some("travel"));
+        assert_eq!(photo_query.owner_user_id(), Some(&user_id));
+    }
+
+    #[test]
+    fn should_parse_dates_and_visibility() {
+        let api = photo_query_api("visibility=public&created_after=2025-01-01&created_before=2025-02-01T10%3A00%3A00Z");
+
+        let photo_query = PhotoQuery::try_from(api, &Uuid::new_v4()).unwrap();
+
+        assert_eq!(photo_query.visibility(), Some(&Visibility::Public));
+        assert_eq!(photo_query.created_after().unwrap().to_rfc3339(), "2025-01-01T00:00:00+00:00");
+        assert_eq!(photo_query.created_before().unwrap().to_rfc3339(), "2025-02-01T10:00:00+00:00");
+    }
+
+    #[test]
+    fn should_reject_invalid_filters() {
+        let user_id = Uuid::new_v4();
+        let invalid_query = |query_string| PhotoQuery::try_from(photo_query_api(query_string), &user_id).unwrap_err();
+
+        assert_eq!(invalid_query("album_id=nope"), InvalidPhotoQueryError::InvalidAlbum);
+        assert_eq!(invalid_query("visibility=secret"), InvalidPhotoQueryError::InvalidVisibility);
+        assert_eq!(invalid_query("created_after=yesterday"), InvalidPhotoQueryError::InvalidCreatedAfter);
+        assert_eq!(invalid_query("created_after=2025-02-01&created_before=2025-01-01"), InvalidPhotoQueryError::InvalidDateRange);
+        assert_eq!(invalid_query("owner=someone"), InvalidPhotoQueryError::InvalidOwner);
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use uuid::Uuid;
+
+use crate::models::api::photo::UploadPhotoMetadataApi;
+use crate::models::service::image::UploadImageError;
+use crate::models::service::upload::{CreatePendingUpload, PresignedUpload};
+use crate::models::service::Visibility;
+
+/// The metadata of the photo, the image being uploaded afterwards to the presigned URL.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePendingUploadApi {
+    pub filename: String,
+    #[serde(flatten)]
+    pub metadata: UploadPhotoMetadataApi,
+}
+
+impl TryFrom<CreatePendingUploadApi> for CreatePendingUpload {
+    type Error = UploadImageError;
+
+    fn try_from(create_pending_upload_api: CreatePendingUploadApi) -> Result<Self, Self::Error> {
+        let CreatePendingUploadApi { filename, metadata } = create_pending_upload_api;
+        let album_id = metadata.album_id
+            .map(|uuid_str| Uuid::parse_str(&uuid_str))
+            .transpose()
+            .map_err(|_| UploadImageError::InvalidAlbum)?;
+
+        Ok(Self::new(
+            filename,
+            metadata.title,
+            album_id,
+            metadata.description,
+            metadata.category,
+            metadata.tags,
+            Visibility::from(metadata.visibility),
+        ))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingUploadApi {
+    pub id: Uuid,
+    #[serde(rename = "uploadUrl", with = "crate::models::api::serde_url")]
+    pub upload_url: Url,
+    #[serde(rename = "expiresAt", with = "crate::models::api::serde_date")]
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<PresignedUpload> for PendingUploadApi {
+    fn from(presigned_upload: PresignedUpload) -> Self {
+        Self {
+            id: *presigned_upload.pending_upload().id(),
+            upload_url: presigned_upload.upload_url().clone(),
+            expires_at: presigned_upload.pending_upload().expires_at(),
+        }
+    }
+}
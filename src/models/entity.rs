@@ -5,10 +5,12 @@ use image::ImageFormat;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::models::service::image::{ColorType, RenditionSize};
 use crate::models::service::Visibility;
 
 pub mod photo;
 pub mod album;
+pub mod upload;
 
 #[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
 pub struct ImageReferenceEntity {
@@ -18,9 +20,63 @@ pub struct ImageReferenceEntity {
     pub size: i64,
     pub visibility: VisibilityEntity,
     pub format: ImageFormatEntity,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub color_type: Option<ColorTypeEntity>,
+    pub bit_depth: Option<i16>,
+    pub sha256: Option<Vec<u8>>,
     pub created_at: chrono::DateTime<Utc>
 }
 
+#[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
+pub struct ImageRenditionEntity {
+    pub image_id: Uuid,
+    pub size: RenditionSizeEntity,
+    pub width: i32,
+    pub height: i32,
+    pub format: ImageFormatEntity,
+    pub file_size: i64,
+    pub created_at: chrono::DateTime<Utc>
+}
+
+#[derive(Clone, Debug, Copy, Eq, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "rendition_size")]
+pub enum RenditionSizeEntity {
+    Small,
+    Medium,
+    Large,
+}
+
+impl From<RenditionSize> for RenditionSizeEntity {
+    fn from(rendition_size: RenditionSize) -> Self {
+        match rendition_size {
+            RenditionSize::Small => Self::Small,
+            RenditionSize::Medium => Self::Medium,
+            RenditionSize::Large => Self::Large,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, Eq, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "color_type")]
+pub enum ColorTypeEntity {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl From<ColorType> for ColorTypeEntity {
+    fn from(color_type: ColorType) -> Self {
+        match color_type {
+            ColorType::Gray => Self::Gray,
+            ColorType::GrayAlpha => Self::GrayAlpha,
+            ColorType::Rgb => Self::Rgb,
+            ColorType::Rgba => Self::Rgba,
+        }
+    }
+}
+
 #[derive(sqlx::Type, Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
 #[sqlx(type_name = "image_format")]
 pub enum ImageFormatEntity {
@@ -71,7 +127,6 @@ impl From<ImageFormat> for ImageFormatEntity {
 pub enum VisibilityEntity {
     Public,
     Private,
-    Null,
 }
 
 impl From<Visibility> for VisibilityEntity {
@@ -88,7 +143,6 @@ impl fmt::Display for VisibilityEntity {
         let visibility_str = match *self {
             VisibilityEntity::Public => "Public",
             VisibilityEntity::Private => "Private",
-            VisibilityEntity::Null => "NULL",
         };
         write!(f, "{}", visibility_str)
     }
@@ -99,7 +153,6 @@ impl From<VisibilityEntity> for Visibility {
         match value {
             VisibilityEntity::Public => Visibility::Public,
             VisibilityEntity::Private => Visibility::Private,
-            VisibilityEntity::Null => panic!("Visibility::NULL from db"),
         }
     }
 }
@@ -1,5 +1,5 @@
 use uuid::Uuid;
-use crate::models::entity::{ImageReferenceEntity, ImageFormatEntity, VisibilityEntity};
+use crate::models::entity::{ColorTypeEntity, ImageReferenceEntity, ImageFormatEntity, VisibilityEntity};
 
 #[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
 pub struct AlbumEntity {
@@ -10,6 +10,7 @@ pub struct AlbumEntity {
     pub visibility: VisibilityEntity,
     pub cover_image: ImageReferenceEntity,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    pub version: i32,
 }
 
 #[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
@@ -21,12 +22,18 @@ pub struct AlbumCoverImageReferenceEntity {
     pub album_owner_user_id: Uuid,
     pub image_reference_id: Uuid,
     pub album_created_at: chrono::DateTime<chrono::Utc>,
+    pub version: i32,
 
     pub image_id: Uuid,
     pub image_owner_user_id: Uuid,
     pub url: String,
     pub size: i64,
     pub format: ImageFormatEntity,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub color_type: Option<ColorTypeEntity>,
+    pub bit_depth: Option<i16>,
+    pub sha256: Option<Vec<u8>>,
     pub image_created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -45,13 +52,27 @@ impl From<AlbumCoverImageReferenceEntity> for AlbumEntity {
                 size: album_cover_image_entity.size,
                 visibility: album_cover_image_entity.visibility,
                 format: album_cover_image_entity.format,
+                width: album_cover_image_entity.width,
+                height: album_cover_image_entity.height,
+                color_type: album_cover_image_entity.color_type,
+                bit_depth: album_cover_image_entity.bit_depth,
+                sha256: album_cover_image_entity.sha256,
                 created_at: album_cover_image_entity.image_created_at,
             },
             created_at: album_cover_image_entity.album_created_at,
+            version: album_cover_image_entity.version,
         }
     }
 }
 
+/// What is left to clean up once an album is deleted: its cover image, and the images of the
+/// photos moved to the trash along with it.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct DeletedAlbumEntity {
+    pub cover_image_id: Uuid,
+    pub deleted_photo_image_ids: Vec<Uuid>,
+}
+
 #[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
 pub struct AlbumNoCoverImageReferenceEntity {
     pub id: Uuid,
@@ -61,4 +82,5 @@ pub struct AlbumNoCoverImageReferenceEntity {
     pub visibility: VisibilityEntity,
     pub cover_image_id: Uuid,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    pub version: i32,
 }
\ No newline at end of file
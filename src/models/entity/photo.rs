@@ -1,6 +1,6 @@
 use uuid::Uuid;
 
-use crate::models::entity::{ImageReferenceEntity, ImageFormatEntity, VisibilityEntity};
+use crate::models::entity::{ColorTypeEntity, ImageReferenceEntity, ImageFormatEntity, VisibilityEntity};
 
 #[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
 pub struct PhotoEntity {
@@ -15,6 +15,7 @@ pub struct PhotoEntity {
     pub image: ImageReferenceEntity,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub is_deleted: bool,
+    pub version: i32,
 }
 
 impl From<PhotoImageReferenceEntity> for PhotoEntity {
@@ -35,10 +36,16 @@ impl From<PhotoImageReferenceEntity> for PhotoEntity {
                 size: photo_image_entity.size,
                 visibility: photo_image_entity.visibility,
                 format: photo_image_entity.format,
+                width: photo_image_entity.width,
+                height: photo_image_entity.height,
+                color_type: photo_image_entity.color_type,
+                bit_depth: photo_image_entity.bit_depth,
+                sha256: photo_image_entity.sha256,
                 created_at: photo_image_entity.image_created_at,
             },
             created_at: photo_image_entity.photo_created_at,
             is_deleted: photo_image_entity.is_deleted,
+            version: photo_image_entity.version,
         }
     }
 }
@@ -56,12 +63,18 @@ pub struct PhotoImageReferenceEntity {
     pub image_reference_id: Uuid,
     pub is_deleted: bool,
     pub photo_created_at: chrono::DateTime<chrono::Utc>,
+    pub version: i32,
 
     pub image_id: Uuid,
     pub image_owner_user_id: Uuid,
     pub url: String,
     pub size: i64,
     pub format: ImageFormatEntity,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub color_type: Option<ColorTypeEntity>,
+    pub bit_depth: Option<i16>,
+    pub sha256: Option<Vec<u8>>,
     pub image_created_at: chrono::DateTime<chrono::Utc>,
 }
 
@@ -78,4 +91,78 @@ pub struct PhotoNoImageReferenceEntity {
     pub image_id: Uuid,
     pub is_deleted: bool,
     pub created_at: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
+    pub version: i32,
+}
+#[derive(Debug, PartialEq, Clone)]
+pub struct PhotoSearchResultEntity {
+    pub photo: PhotoEntity,
+    pub rank: f32,
+    pub headline: String,
+}
+
+impl From<PhotoImageReferenceSearchEntity> for PhotoSearchResultEntity {
+    fn from(search_entity: PhotoImageReferenceSearchEntity) -> Self {
+        let photo_image_entity = PhotoImageReferenceEntity {
+            photo_id: search_entity.photo_id,
+            title: search_entity.title,
+            description: search_entity.description,
+            visibility: search_entity.visibility,
+            photo_owner_user_id: search_entity.photo_owner_user_id,
+            tags: search_entity.tags,
+            category: search_entity.category,
+            album_id: search_entity.album_id,
+            image_reference_id: search_entity.image_reference_id,
+            is_deleted: search_entity.is_deleted,
+            photo_created_at: search_entity.photo_created_at,
+            version: search_entity.version,
+            image_id: search_entity.image_id,
+            image_owner_user_id: search_entity.image_owner_user_id,
+            url: search_entity.url,
+            size: search_entity.size,
+            format: search_entity.format,
+            width: search_entity.width,
+            height: search_entity.height,
+            color_type: search_entity.color_type,
+            bit_depth: search_entity.bit_depth,
+            sha256: search_entity.sha256,
+            image_created_at: search_entity.image_created_at,
+        };
+
+        PhotoSearchResultEntity {
+            photo: PhotoEntity::from(photo_image_entity),
+            rank: search_entity.rank,
+            headline: search_entity.headline,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Debug, PartialEq, Clone)]
+pub struct PhotoImageReferenceSearchEntity {
+    pub photo_id: Uuid,
+    pub title: String,
+    pub description: String,
+    pub visibility: VisibilityEntity,
+    pub photo_owner_user_id: Uuid,
+    pub tags: Vec<String>,
+    pub category: String,
+    pub album_id: Option<Uuid>,
+    pub image_reference_id: Uuid,
+    pub is_deleted: bool,
+    pub photo_created_at: chrono::DateTime<chrono::Utc>,
+    pub version: i32,
+
+    pub image_id: Uuid,
+    pub image_owner_user_id: Uuid,
+    pub url: String,
+    pub size: i64,
+    pub format: ImageFormatEntity,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub color_type: Option<ColorTypeEntity>,
+    pub bit_depth: Option<i16>,
+    pub sha256: Option<Vec<u8>>,
+    pub image_created_at: chrono::DateTime<chrono::Utc>,
+
+    pub rank: f32,
+    pub headline: String,
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::models::entity::VisibilityEntity;
+
+#[derive(sqlx::FromRow, Debug, Eq, PartialEq, Clone)]
+pub struct PendingUploadEntity {
+    pub id: Uuid,
+    pub owner_user_id: Uuid,
+    pub filename: String,
+    pub title: String,
+    pub description: String,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub album_id: Option<Uuid>,
+    pub visibility: VisibilityEntity,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
@@ -5,6 +5,7 @@ pub mod photo;
 pub mod album;
 pub mod pagination;
 pub mod image;
+pub mod upload;
 
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Visibility {
@@ -30,4 +31,27 @@ impl fmt::Display for Visibility {
         };
         write!(f, "{}", visibility_str)
     }
-}
\ No newline at end of file
+}
+/// The versions of a photo or an album a conditional write is allowed to overwrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedVersion {
+    Any,
+    OneOf(Vec<i32>),
+}
+
+impl ExpectedVersion {
+    pub fn matches(&self, version: i32) -> bool {
+        match self {
+            ExpectedVersion::Any => true,
+            ExpectedVersion::OneOf(versions) => versions.contains(&version),
+        }
+    }
+
+    /// The versions to compare with in a conditional statement, `None` matching any version.
+    pub fn versions(&self) -> Option<Vec<i32>> {
+        match self {
+            ExpectedVersion::Any => None,
+            ExpectedVersion::OneOf(versions) => Some(versions.clone()),
+        }
+    }
+}
@@ -5,7 +5,8 @@ use uuid::Uuid;
 
 use crate::models::entity::album::AlbumEntity;
 use crate::models::service::Visibility;
-use crate::models::service::image::UploadImage;
+use crate::models::service::image::{ImageProperties, ImageReference, ImageRendition, UploadImage};
+use crate::models::service::pagination::{Cursor, Paginated};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Album {
@@ -16,7 +17,9 @@ pub struct Album {
     owner_user_id: Uuid,
     cover_image_id: Uuid,
     cover_image_url: Url,
+    cover_image_properties: ImageProperties,
     created_at: chrono::DateTime<Utc>,
+    version: i32,
 }
 
 impl Album {
@@ -28,7 +31,9 @@ impl Album {
         owner_user_id: Uuid,
         cover_image_id: Uuid,
         cover_image_url: Url,
+        cover_image_properties: ImageProperties,
         created_at: chrono::DateTime<Utc>,
+        version: i32,
     ) -> Self {
         Album {
             id,
@@ -38,7 +43,9 @@ impl Album {
             owner_user_id,
             cover_image_id,
             cover_image_url,
+            cover_image_properties,
             created_at,
+            version,
         }
     }
     pub fn id(&self) -> Uuid {
@@ -65,6 +72,18 @@ impl Album {
     pub fn cover_image_url(&self) -> &Url {
         &self.cover_image_url
     }
+    pub fn cover_image_properties(&self) -> &ImageProperties {
+        &self.cover_image_properties
+    }
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Paginated for Album {
+    fn cursor(&self) -> Cursor {
+        Cursor::new(self.created_at, self.id)
+    }
 }
 
 impl From<AlbumEntity> for Album {
@@ -77,7 +96,9 @@ impl From<AlbumEntity> for Album {
             owner_user_id: album_entity.owner_user_id,
             cover_image_id: album_entity.cover_image.id,
             cover_image_url: Url::parse(album_entity.cover_image.url.as_str()).unwrap(), // TODO:
+            cover_image_properties: ImageReference::from(album_entity.cover_image).properties().clone(),
             created_at: album_entity.created_at,
+            version: album_entity.version,
         }
     }
 }
@@ -119,6 +140,8 @@ pub struct CreateAlbum {
     cover_image_reference_url: url::Url,
     cover_image_size: u64,
     cover_image_format: ImageFormat,
+    cover_image_renditions: Vec<ImageRendition>,
+    cover_image_properties: ImageProperties,
 }
 
 impl CreateAlbum {
@@ -133,7 +156,25 @@ impl CreateAlbum {
         cover_image_size: u64, 
         cover_image_format: ImageFormat
     ) -> Self {
-        Self { title, description, visibility, owner_user_id, cover_image_id, cover_image_url, cover_image_reference_url, cover_image_size, cover_image_format }
+        Self {
+            title,
+            description,
+            visibility,
+            owner_user_id,
+            cover_image_id,
+            cover_image_url,
+            cover_image_reference_url,
+            cover_image_size,
+            cover_image_format,
+            cover_image_renditions: Vec::new(),
+            cover_image_properties: ImageProperties::default(),
+        }
+    }
+    pub fn with_cover_image_renditions(self, cover_image_renditions: Vec<ImageRendition>) -> Self {
+        Self { cover_image_renditions, ..self }
+    }
+    pub fn with_cover_image_properties(self, cover_image_properties: ImageProperties) -> Self {
+        Self { cover_image_properties, ..self }
     }
     pub fn title(&self) -> &str {
         &self.title
@@ -162,18 +203,33 @@ impl CreateAlbum {
     pub fn cover_image_format(&self) -> &ImageFormat {
         &self.cover_image_format
     }
+    pub fn cover_image_renditions(&self) -> &[ImageRendition] {
+        &self.cover_image_renditions
+    }
+    pub fn cover_image_properties(&self) -> &ImageProperties {
+        &self.cover_image_properties
+    }
 }
 
+/// Partial update of an album, `None` leaves a field unchanged.
 #[derive(Debug, Clone)]
 pub struct UpdateAlbum {
     id: Uuid,
     title: Option<String>,
+    description: Option<String>,
     visibility: Option<Visibility>,
+    cover_photo_id: Option<Uuid>,
 }
 
 impl UpdateAlbum {
-    pub fn new(id: &Uuid, title: Option<&String>, visibility: Option<&Visibility>) -> Self {
-        Self { id: id.clone(), title: title.cloned(), visibility: visibility.cloned() }
+    pub fn new(
+        id: &Uuid,
+        title: Option<String>,
+        description: Option<String>,
+        visibility: Option<Visibility>,
+        cover_photo_id: Option<Uuid>,
+    ) -> Self {
+        Self { id: *id, title, description, visibility, cover_photo_id }
     }
     pub fn id(&self) -> &Uuid {
         &self.id
@@ -181,7 +237,44 @@ impl UpdateAlbum {
     pub fn title(&self) -> &Option<String> {
         &self.title
     }
+    pub fn description(&self) -> &Option<String> {
+        &self.description
+    }
     pub fn visibility(&self) -> Option<Visibility> {
         self.visibility
     }
+    /// Photo of the album whose image becomes the album cover.
+    pub fn cover_photo_id(&self) -> Option<&Uuid> {
+        self.cover_photo_id.as_ref()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.visibility.is_none()
+            && self.cover_photo_id.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteAlbumPhotosMode {
+    Detach,
+    Delete,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeleteAlbum {
+    id: Uuid,
+    photos_mode: DeleteAlbumPhotosMode,
+}
+
+impl DeleteAlbum {
+    pub fn new(id: &Uuid, photos_mode: DeleteAlbumPhotosMode) -> Self {
+        Self { id: *id, photos_mode }
+    }
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+    pub fn photos_mode(&self) -> DeleteAlbumPhotosMode {
+        self.photos_mode
+    }
 }
\ No newline at end of file
@@ -1,10 +1,16 @@
-use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
 
 use actix_multipart::form::tempfile::TempFile;
+use bytes::Bytes;
+use chrono::{DateTime, SubsecRound, Utc};
+use futures::stream::BoxStream;
 use image::ImageFormat;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
 use uuid::Uuid;
 
-use crate::models::entity::{ImageFormatEntity, ImageReferenceEntity};
+use crate::models::entity::{ColorTypeEntity, ImageFormatEntity, ImageReferenceEntity, ImageRenditionEntity, RenditionSizeEntity};
 use crate::models::service::Visibility;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,6 +21,7 @@ pub struct ImageReference {
     size: u64,
     visibility: Visibility,
     format: ImageFormat,
+    properties: ImageProperties,
 }
 
 impl ImageReference {
@@ -33,8 +40,11 @@ impl ImageReference {
     pub fn visibility(&self) -> Visibility {
         self.visibility.clone()
     }
-    pub fn new(id: &Uuid, owner_user_id: &Uuid, url: &url::Url, size: u64, format: &ImageFormat, visibility: &Visibility) -> Self {
-        Self { id: id.clone(), owner_user_id: owner_user_id.clone(), url: url.clone(), size, visibility: visibility.clone(), format: format.clone() }
+    pub fn properties(&self) -> &ImageProperties {
+        &self.properties
+    }
+    pub fn new(id: &Uuid, owner_user_id: &Uuid, url: &url::Url, size: u64, format: &ImageFormat, visibility: &Visibility, properties: &ImageProperties) -> Self {
+        Self { id: *id, owner_user_id: *owner_user_id, url: url.clone(), size, visibility: *visibility, format: *format, properties: properties.clone() }
     }
     pub fn owner_user_id(&self) -> &Uuid {
         &self.owner_user_id
@@ -50,6 +60,78 @@ impl From<ImageReferenceEntity> for ImageReference {
             size: image_reference_entity.size as u64,
             visibility: Visibility::from(image_reference_entity.visibility),
             format: ImageFormat::from(image_reference_entity.format),
+            properties: ImageProperties {
+                width: image_reference_entity.width.map(|width| width as u32),
+                height: image_reference_entity.height.map(|height| height as u32),
+                color_type: image_reference_entity.color_type.map(ColorType::from),
+                bit_depth: image_reference_entity.bit_depth.map(|bit_depth| bit_depth as u8),
+                sha256: image_reference_entity.sha256.and_then(|sha256| sha256.try_into().ok()),
+            },
+        }
+    }
+}
+
+// The images uploaded before the properties were recorded, or straight to S3, have none of them,
+// the images whose format cannot be decoded only have their digest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageProperties {
+    width: Option<u32>,
+    height: Option<u32>,
+    color_type: Option<ColorType>,
+    bit_depth: Option<u8>,
+    sha256: Option<[u8; 32]>,
+}
+
+impl ImageProperties {
+    pub fn new(sha256: [u8; 32]) -> Self {
+        Self { sha256: Some(sha256), ..Self::default() }
+    }
+    pub fn with_layout(self, width: u32, height: u32, color_type: ColorType, bit_depth: u8) -> Self {
+        Self { width: Some(width), height: Some(height), color_type: Some(color_type), bit_depth: Some(bit_depth), ..self }
+    }
+    pub fn width(&self) -> Option<u32> {
+        self.width
+    }
+    pub fn height(&self) -> Option<u32> {
+        self.height
+    }
+    pub fn color_type(&self) -> Option<ColorType> {
+        self.color_type
+    }
+    pub fn bit_depth(&self) -> Option<u8> {
+        self.bit_depth
+    }
+    pub fn sha256(&self) -> Option<&[u8; 32]> {
+        self.sha256.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ColorType {
+    Gray,
+    GrayAlpha,
+    Rgb,
+    Rgba,
+}
+
+impl From<image::ColorType> for ColorType {
+    fn from(color_type: image::ColorType) -> Self {
+        match (color_type.has_color(), color_type.has_alpha()) {
+            (false, false) => ColorType::Gray,
+            (false, true) => ColorType::GrayAlpha,
+            (true, false) => ColorType::Rgb,
+            (true, true) => ColorType::Rgba,
+        }
+    }
+}
+
+impl From<ColorTypeEntity> for ColorType {
+    fn from(color_type_entity: ColorTypeEntity) -> Self {
+        match color_type_entity {
+            ColorTypeEntity::Gray => ColorType::Gray,
+            ColorTypeEntity::GrayAlpha => ColorType::GrayAlpha,
+            ColorTypeEntity::Rgb => ColorType::Rgb,
+            ColorTypeEntity::Rgba => ColorType::Rgba,
         }
     }
 }
@@ -78,21 +160,95 @@ impl From<ImageFormatEntity> for ImageFormat {
 }
 
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RenditionSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl RenditionSize {
+    pub const ALL: [RenditionSize; 3] = [RenditionSize::Small, RenditionSize::Medium, RenditionSize::Large];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RenditionSize::Small => "small",
+            RenditionSize::Medium => "medium",
+            RenditionSize::Large => "large",
+        }
+    }
+}
+
+impl From<RenditionSizeEntity> for RenditionSize {
+    fn from(rendition_size_entity: RenditionSizeEntity) -> Self {
+        match rendition_size_entity {
+            RenditionSizeEntity::Small => RenditionSize::Small,
+            RenditionSizeEntity::Medium => RenditionSize::Medium,
+            RenditionSizeEntity::Large => RenditionSize::Large,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImageRendition {
+    size: RenditionSize,
+    width: u32,
+    height: u32,
+    format: ImageFormat,
+    file_size: u64,
+}
+
+impl ImageRendition {
+    pub fn new(size: RenditionSize, width: u32, height: u32, format: ImageFormat, file_size: u64) -> Self {
+        Self { size, width, height, format, file_size }
+    }
+    pub fn size(&self) -> RenditionSize {
+        self.size
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+    pub fn file_size(&self) -> u64 {
+        self.file_size
+    }
+}
+
+impl From<ImageRenditionEntity> for ImageRendition {
+    fn from(image_rendition_entity: ImageRenditionEntity) -> Self {
+        Self {
+            size: RenditionSize::from(image_rendition_entity.size),
+            width: image_rendition_entity.width as u32,
+            height: image_rendition_entity.height as u32,
+            format: ImageFormat::from(image_rendition_entity.format),
+            file_size: image_rendition_entity.file_size as u64,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct UploadImage {
     filename: String,
     visibility: Visibility,
-    bytes: Vec<u8>,
+    file: Arc<NamedTempFile>,
     format: ImageFormat,
     size: usize,
 }
 
 impl UploadImage {
-    pub fn new(filename: &str, bytes: Vec<u8>, format: ImageFormat, visibility: Visibility, size: usize) -> Self {
-        Self { filename: filename.to_string(), bytes, format, visibility, size }
+    // The `limit` of the file of the multipart forms, the presigned uploads are held to it as well
+    pub const MAX_SIZE: u64 = 100_000_000;
+
+    pub fn new(filename: &str, file: NamedTempFile, format: ImageFormat, visibility: Visibility, size: usize) -> Self {
+        Self { filename: filename.to_string(), file: Arc::new(file), format, visibility, size }
     }
-    pub fn bytes(&self) -> &Vec<u8> {
-        &self.bytes
+    pub fn path(&self) -> &Path {
+        self.file.path()
     }
     pub fn format(&self) -> ImageFormat {
         self.format
@@ -106,29 +262,17 @@ impl UploadImage {
     pub fn visibility(&self) -> Visibility {
         self.visibility
     }
-    pub fn try_from(mut temp_file: TempFile, visibility: Visibility) -> Result<Self, UploadImageError> {
+    pub fn try_from(temp_file: TempFile, visibility: Visibility) -> Result<Self, UploadImageError> {
         match temp_file.content_type {
             None => Err(UploadImageError::MissingContentType),
             Some(content_type) => match content_type.type_() {
                 mime::IMAGE => {
-                    let format = match content_type.subtype() {
-                        mime::JPEG => ImageFormat::Jpeg,
-                        mime::PNG => ImageFormat::Png,
-                        mime::GIF => ImageFormat::Gif,
-                        _ => return Err(UploadImageError::UnsupportedMimeType),
-                    };
+                    // The accepted formats are checked against the bytes of the image by the services
+                    let format = ImageFormat::from_mime_type(content_type.essence_str())
+                        .ok_or(UploadImageError::UnsupportedMimeType)?;
 
                     let file_name = temp_file.file_name.unwrap_or_default();
-                    let size = temp_file.size;
-                    let mut bytes = Vec::with_capacity(size);
-                    temp_file.file.read_to_end(&mut bytes).map_err(|_| UploadImageError::CorruptedImage)?;
-                    Ok(Self {
-                        filename: file_name,
-                        bytes,
-                        format,
-                        visibility,
-                        size,
-                    })
+                    Ok(Self::new(&file_name, temp_file.file, format, visibility, temp_file.size))
                 },
                 _ => Err(UploadImageError::BadContentType)
             }
@@ -141,6 +285,7 @@ pub enum UploadImageError {
     MissingContentType,
     BadContentType,
     UnsupportedMimeType,
+    MismatchedFormat,
     CorruptedImage,
     InvalidAlbum
 }
@@ -180,29 +325,181 @@ impl Image {
     pub fn visibility(&self) -> Visibility {
         self.visibility
     }
+    pub fn filename_with_extension(&self) -> String {
+        filename_with_extension(&self.filename, self.format)
+    }
+}
+
+pub struct ImageStream {
+    filename: String,
+    format: ImageFormat,
+    content_length: u64,
+    bytes: BoxStream<'static, anyhow::Result<Bytes>>,
 }
 
+impl ImageStream {
+    pub fn new(filename: &str, format: ImageFormat, content_length: u64, bytes: BoxStream<'static, anyhow::Result<Bytes>>) -> Self {
+        Self { filename: filename.to_string(), format, content_length, bytes }
+    }
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+    // Less than the size of the image for a range
+    pub fn content_length(&self) -> u64 {
+        self.content_length
+    }
+    pub fn filename_with_extension(&self) -> String {
+        filename_with_extension(&self.filename, self.format)
+    }
+    pub fn into_bytes(self) -> BoxStream<'static, anyhow::Result<Bytes>> {
+        self.bytes
+    }
+    pub fn map_bytes<F>(self, f: F) -> Self
+        where
+            F: FnOnce(BoxStream<'static, anyhow::Result<Bytes>>) -> BoxStream<'static, anyhow::Result<Bytes>>,
+    {
+        Self { bytes: f(self.bytes), ..self }
+    }
+}
+
+impl std::fmt::Debug for ImageStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ImageStream")
+            .field("filename", &self.filename)
+            .field("format", &self.format)
+            .field("content_length", &self.content_length)
+            .finish_non_exhaustive()
+    }
+}
+
+fn filename_with_extension(filename: &str, format: ImageFormat) -> String {
+    let Some(extension) = format.extensions_str().first() else {
+        return filename.to_string();
+    };
+
+    std::path::Path::new(filename)
+        .with_extension(extension)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ImageTransformation {
     HueRotate(i32),
     Thumbnail(u32, u32),
-    None,
+    Crop { x: u32, y: u32, width: u32, height: u32 },
+    Rotate(Rotation),
+    Flip(Flip),
+    Resize { width: u32, height: u32, fit: ResizeFit },
+    Blur(f32),
+    Grayscale,
+    Brightness(i32),
+    Contrast(f32),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Rotation {
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ResizeFit {
+    Contain,
+    Cover,
+    Fill,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum InvalidImageTransformationError {
+    TooManyOperations(usize),
+    UnknownOperation(String),
+    InvalidParameters(&'static str),
+    CropOutOfBounds,
+    RenditionSizeWithOptions,
+    UnsupportedEncoding(&'static str),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImageEncodingOptions {
+    quality: Option<u8>,
+    compression: Option<u8>,
+    strip_metadata: bool,
+}
+
+impl ImageEncodingOptions {
+    // `quality` applies to JPEG and AVIF (1-100), `compression` to PNG (0-9)
+    pub fn new(quality: Option<u8>, compression: Option<u8>, strip_metadata: bool) -> Self {
+        Self { quality, compression, strip_metadata }
+    }
+    pub fn quality(&self) -> Option<u8> {
+        self.quality
+    }
+    pub fn compression(&self) -> Option<u8> {
+        self.compression
+    }
+    pub fn strip_metadata(&self) -> bool {
+        self.strip_metadata
+    }
+    pub fn is_customized(&self) -> bool {
+        self.quality.is_some() || self.compression.is_some() || !self.strip_metadata
+    }
+}
+
+impl Default for ImageEncodingOptions {
+    fn default() -> Self {
+        Self { quality: None, compression: None, strip_metadata: true }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ImageTransformOptions {
     huerotate: Option<i32>,
-    thumbnail: Option<(u32, u32)>
+    thumbnail: Option<(u32, u32)>,
+    operations: Vec<ImageTransformation>,
+    format: Option<ImageFormat>,
+    encoding: ImageEncodingOptions,
+    rendition_size: Option<RenditionSize>,
 }
 
 impl ImageTransformOptions {
     const AVAILABLE_TRANSFORMATIONS: usize = 2;
     
-    pub fn new(huerotate: Option<i32>, thumbnail: Option<(u32, u32)>) -> Self {
-        Self { huerotate, thumbnail }
+    pub fn new(
+        huerotate: Option<i32>,
+        thumbnail: Option<(u32, u32)>,
+        operations: Vec<ImageTransformation>,
+        format: Option<ImageFormat>,
+        encoding: ImageEncodingOptions
+    ) -> Self {
+        Self { huerotate, thumbnail, operations, format, encoding, rendition_size: None }
+    }
+
+    pub fn rendition(rendition_size: RenditionSize) -> Self {
+        Self {
+            huerotate: None,
+            thumbnail: None,
+            operations: Vec::new(),
+            format: None,
+            encoding: ImageEncodingOptions::default(),
+            rendition_size: Some(rendition_size),
+        }
+    }
+
+    pub fn rendition_size(&self) -> Option<RenditionSize> {
+        self.rendition_size
     }
     
+    // `huerotate` and `thumbnail` come first, then the operations in the requested order
     pub fn transformations(&self) -> Vec<ImageTransformation> {
-        let mut transformations = Vec::with_capacity(Self::AVAILABLE_TRANSFORMATIONS + 1);
+        let mut transformations = Vec::with_capacity(Self::AVAILABLE_TRANSFORMATIONS + self.operations.len());
 
         if let Some(huerotate) = self.huerotate {
             transformations.push(ImageTransformation::HueRotate(huerotate));
@@ -212,10 +509,156 @@ impl ImageTransformOptions {
             transformations.push(ImageTransformation::Thumbnail(nwidth, nheight));
         }
 
+        transformations.extend_from_slice(&self.operations);
         transformations
     }
     
     pub fn contains_transformations(&self) -> bool {
-        self.thumbnail.is_some() || self.huerotate.is_some()
+        self.thumbnail.is_some() || self.huerotate.is_some() || !self.operations.is_empty()
+    }
+
+    pub fn output_format(&self, original_format: ImageFormat) -> ImageFormat {
+        self.format.unwrap_or(original_format)
+    }
+
+    pub fn encoding(&self) -> &ImageEncodingOptions {
+        &self.encoding
+    }
+
+    pub fn modifies(&self, original_format: ImageFormat) -> bool {
+        self.contains_transformations()
+            || self.output_format(original_format) != original_format
+            || self.encoding.is_customized()
+    }
+
+    // `format=jpg` and `format=jpeg` share the same key
+    pub fn rendition_key(&self, original_format: ImageFormat) -> String {
+        let transformations = self.transformations()
+            .iter()
+            .map(|transformation| format!("{:?}", transformation))
+            .collect::<Vec<_>>()
+            .join("|");
+        let canonical_options = format!(
+            "{};format={:?};quality={:?};compression={:?};strip={}",
+            transformations,
+            self.output_format(original_format),
+            self.encoding.quality,
+            self.encoding.compression,
+            self.encoding.strip_metadata,
+        );
+
+        hex::encode(Sha256::digest(canonical_options.as_bytes()))
+    }
+}
+
+// The SHA-256 checksum is only known when the storage verified the whole image
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ContentHash {
+    entity_tag: String,
+    sha256: Option<[u8; 32]>,
+}
+
+impl ContentHash {
+    pub fn new(entity_tag: &str, sha256: Option<[u8; 32]>) -> Self {
+        Self { entity_tag: entity_tag.to_string(), sha256 }
     }
-}
\ No newline at end of file
+    pub fn entity_tag(&self) -> &str {
+        &self.entity_tag
+    }
+    pub fn sha256(&self) -> Option<&[u8; 32]> {
+        self.sha256.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImageValidators {
+    entity_tag: String,
+    last_modified: DateTime<Utc>,
+    visibility: Visibility,
+}
+
+impl ImageValidators {
+    pub fn new(content_hash: &str, rendition_key: &str, last_modified: DateTime<Utc>, visibility: Visibility) -> Self {
+        let entity_tag = hex::encode(Sha256::digest(format!("{}:{}", content_hash, rendition_key).as_bytes()));
+        Self { entity_tag, last_modified, visibility }
+    }
+    pub fn entity_tag(&self) -> &str {
+        &self.entity_tag
+    }
+    pub fn last_modified(&self) -> DateTime<Utc> {
+        self.last_modified
+    }
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub enum DownloadCondition {
+    #[default]
+    None,
+    IfNoneMatchAny,
+    IfNoneMatch(Vec<String>),
+    IfModifiedSince(DateTime<Utc>),
+}
+
+impl DownloadCondition {
+    pub fn is_not_modified(&self, image_validators: &ImageValidators) -> bool {
+        match self {
+            DownloadCondition::None => false,
+            DownloadCondition::IfNoneMatchAny => true,
+            DownloadCondition::IfNoneMatch(entity_tags) => entity_tags.iter().any(|entity_tag| entity_tag == image_validators.entity_tag()),
+            // HTTP dates have a precision of a second
+            DownloadCondition::IfModifiedSince(modified_since) => image_validators.last_modified().trunc_subsecs(0) <= *modified_since,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ByteRange {
+    // Both positions included
+    FromTo(u64, u64),
+    From(u64),
+    Suffix(u64),
+}
+
+impl ByteRange {
+    pub fn resolve(&self, size: u64) -> Option<ContentRange> {
+        let (first, last) = match *self {
+            ByteRange::FromTo(first, last) if first <= last => (first, last.min(size.checked_sub(1)?)),
+            ByteRange::FromTo(..) => return None,
+            ByteRange::From(first) => (first, size.checked_sub(1)?),
+            ByteRange::Suffix(length) if length > 0 => (size.saturating_sub(length), size.checked_sub(1)?),
+            ByteRange::Suffix(_) => return None,
+        };
+
+        (first <= last).then_some(ContentRange { first, last, size })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ContentRange {
+    first: u64,
+    last: u64,
+    size: u64,
+}
+
+impl ContentRange {
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+    pub fn last(&self) -> u64 {
+        self.last
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageDownload {
+    NotModified(ImageValidators),
+    Modified(Image, ImageValidators),
+    Original(ImageStream, ImageValidators),
+    PartialContent(ImageStream, ContentRange, ImageValidators),
+}
@@ -1,35 +1,196 @@
 use std::fmt::Debug;
+
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Page<T> 
-where 
+pub struct Page<T>
+where
     T: Debug + Clone
 {
     data: Vec<T>,
-    current_page: u32,
-    per_page: u32,
+    next_cursor: Option<String>,
+    has_more: bool,
 }
 
 impl<T> Page<T> where T: Debug + Clone {
     pub fn data(&self) -> &Vec<T> {
         &self.data
     }
-    pub fn current_page(&self) -> u32 {
-        self.current_page
+    pub fn next_cursor(&self) -> Option<&str> {
+        self.next_cursor.as_deref()
     }
-    pub fn per_page(&self) -> u32 {
-        self.per_page
+    pub fn has_more(&self) -> bool {
+        self.has_more
     }
     pub fn map<U>(&self) -> Page<U> where U: From<T> + Clone + Debug {
         let data = self.data.iter().map(|t| U::from(t.clone())).collect();
         Page {
             data,
-            current_page: self.current_page,
-            per_page: self.per_page,
+            next_cursor: self.next_cursor.clone(),
+            has_more: self.has_more,
+        }
+    }
+    pub fn new(data: Vec<T>, next_cursor: Option<Cursor>) -> Self {
+        Self {
+            data,
+            has_more: next_cursor.is_some(),
+            next_cursor: next_cursor.map(|cursor| cursor.encode()),
         }
     }
-    pub fn new(data: Vec<T>, current_page: u32, per_page: u32) -> Self {
-        Self { data, current_page, per_page }
+}
+
+/// Position of an item in a listing ordered by `(created_at, id)` descending, or by
+/// `(rank, created_at, id)` descending for listings ranked by relevance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cursor {
+    rank: Option<f32>,
+    created_at: DateTime<Utc>,
+    id: Uuid,
+}
+
+impl Cursor {
+    const SEPARATOR: char = '|';
+
+    pub fn new(created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self { rank: None, created_at, id }
+    }
+    pub fn ranked(rank: f32, created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self { rank: Some(rank), created_at, id }
+    }
+    pub fn rank(&self) -> Option<f32> {
+        self.rank
+    }
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn encode(&self) -> String {
+        let mut raw_cursor = String::new();
+        if let Some(rank) = self.rank {
+            raw_cursor.push_str(&format!("{}{}", rank, Self::SEPARATOR));
+        }
+        raw_cursor.push_str(&format!(
+            "{}{}{}",
+            self.created_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Self::SEPARATOR,
+            self.id
+        ));
+        BASE64_URL_SAFE_NO_PAD.encode(raw_cursor)
+    }
+
+    pub fn decode(encoded_cursor: &str) -> Result<Self, InvalidCursorError> {
+        let raw_cursor = BASE64_URL_SAFE_NO_PAD
+            .decode(encoded_cursor)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or(InvalidCursorError)?;
+
+        let (rank, raw_cursor) = match raw_cursor.split(Self::SEPARATOR).count() {
+            2 => (None, raw_cursor.as_str()),
+            3 => {
+                let (rank, raw_cursor) = raw_cursor.split_once(Self::SEPARATOR).ok_or(InvalidCursorError)?;
+                let rank = rank.parse::<f32>()
+                    .ok()
+                    .filter(|rank| rank.is_finite())
+                    .ok_or(InvalidCursorError)?;
+                (Some(rank), raw_cursor)
+            }
+            _ => return Err(InvalidCursorError),
+        };
+
+        let (created_at, id) = raw_cursor.split_once(Self::SEPARATOR).ok_or(InvalidCursorError)?;
+        let created_at = DateTime::parse_from_rfc3339(created_at)
+            .map_err(|_| InvalidCursorError)?
+            .with_timezone(&Utc);
+        let id = Uuid::parse_str(id).map_err(|_| InvalidCursorError)?;
+
+        Ok(Self { rank, created_at, id })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct InvalidCursorError;
+
+/// Items that can be listed with keyset pagination.
+pub trait Paginated {
+    fn cursor(&self) -> Cursor;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRequest {
+    limit: u32,
+    cursor: Option<Cursor>,
+}
+
+impl PageRequest {
+    pub const DEFAULT_LIMIT: u32 = 30;
+    pub const MAX_LIMIT: u32 = 100;
+
+    pub fn new(limit: Option<u32>, cursor: Option<Cursor>) -> Self {
+        let limit = limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT);
+        Self { limit, cursor }
+    }
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+    pub fn cursor(&self) -> Option<&Cursor> {
+        self.cursor.as_ref()
+    }
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn cursor_should_survive_an_encode_decode_round_trip() {
+        let created_at = Utc.timestamp_micros(1_736_000_000_123_456).unwrap();
+        let cursor = Cursor::new(created_at, Uuid::new_v4());
+
+        let decoded_cursor = Cursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(cursor, decoded_cursor);
+    }
+
+    #[test]
+    fn ranked_cursor_should_survive_an_encode_decode_round_trip() {
+        let created_at = Utc.timestamp_micros(1_736_000_000_123_456).unwrap();
+        let cursor = Cursor::ranked(0.0607927, created_at, Uuid::new_v4());
+
+        let decoded_cursor = Cursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(cursor, decoded_cursor);
+        assert_eq!(decoded_cursor.rank(), Some(0.0607927));
+    }
+
+    #[test]
+    fn cursor_should_reject_garbage() {
+        assert_eq!(Cursor::decode("not a cursor"), Err(InvalidCursorError));
+        assert_eq!(Cursor::decode(&BASE64_URL_SAFE_NO_PAD.encode("2025-01-01T00:00:00Z|x")), Err(InvalidCursorError));
+        assert_eq!(Cursor::decode(&BASE64_URL_SAFE_NO_PAD.encode(format!("NaN|2025-01-01T00:00:00Z|{}", Uuid::nil()))), Err(InvalidCursorError));
+    }
+
+    #[test]
+    fn page_request_should_clamp_limit() {
+        assert_eq!(PageRequest::new(None, None).limit(), PageRequest::DEFAULT_LIMIT);
+        assert_eq!(PageRequest::new(Some(0), None).limit(), 1);
+        assert_eq!(PageRequest::new(Some(10_000), None).limit(), PageRequest::MAX_LIMIT);
     }
 }
@@ -2,9 +2,10 @@ use chrono::Utc;
 use image::ImageFormat;
 use uuid::Uuid;
 
-use crate::models::entity::photo::PhotoEntity;
+use crate::models::entity::photo::{PhotoEntity, PhotoSearchResultEntity};
 use crate::models::service::Visibility;
-use crate::models::service::image::{ImageReference, UploadImage};
+use crate::models::service::image::{ImageProperties, ImageReference, ImageRendition, UploadImage};
+use crate::models::service::pagination::{Cursor, Paginated};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Photo {
@@ -18,6 +19,7 @@ pub struct Photo {
     visibility: Visibility,
     image: ImageReference,
     created_at: chrono::DateTime<Utc>,
+    version: i32,
 }
 
 impl Photo {
@@ -31,7 +33,8 @@ impl Photo {
         album_id: Option<Uuid>,
         visibility: Visibility,
         image: ImageReference,
-        created_at: chrono::DateTime<Utc>
+        created_at: chrono::DateTime<Utc>,
+        version: i32,
     ) -> Self {
         Self {
             id,
@@ -43,7 +46,8 @@ impl Photo {
             album_id,
             visibility,
             image,
-            created_at
+            created_at,
+            version,
         }
     }
     pub fn id(&self) -> &Uuid {
@@ -76,19 +80,40 @@ impl Photo {
     pub fn created_at(&self) -> chrono::DateTime<Utc> {
         self.created_at
     }
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+}
+
+impl Paginated for Photo {
+    fn cursor(&self) -> Cursor {
+        Cursor::new(self.created_at, self.id)
+    }
 }
 
+/// Partial update of a photo, `None` leaves a field unchanged.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct UpdatePhoto {
     id: Uuid,
     title: Option<String>,
-    album_id: Option<Uuid>,
+    description: Option<String>,
+    category: Option<String>,
+    tags: Option<Vec<String>>,
+    album_id: Option<Option<Uuid>>,
     visibility: Option<Visibility>,
 }
 
 impl UpdatePhoto {
-    pub fn new(id: &Uuid, title: Option<&String>, album_id: Option<&Uuid>, visibility: Option<&Visibility>) -> Self {
-        Self { id: id.clone(), title: title.cloned(), album_id: album_id.cloned(), visibility: visibility.cloned() }
+    pub fn new(
+        id: &Uuid,
+        title: Option<String>,
+        description: Option<String>,
+        category: Option<String>,
+        tags: Option<Vec<String>>,
+        album_id: Option<Option<Uuid>>,
+        visibility: Option<Visibility>,
+    ) -> Self {
+        Self { id: *id, title, description, category, tags, album_id, visibility }
     }
     pub fn id(&self) -> &Uuid {
         &self.id
@@ -96,12 +121,30 @@ impl UpdatePhoto {
     pub fn title(&self) -> &Option<String> {
         &self.title
     }
-    pub fn album_id(&self) -> &Option<Uuid> {
+    pub fn description(&self) -> &Option<String> {
+        &self.description
+    }
+    pub fn category(&self) -> &Option<String> {
+        &self.category
+    }
+    pub fn tags(&self) -> &Option<Vec<String>> {
+        &self.tags
+    }
+    /// `Some(None)` removes the photo from its album.
+    pub fn album_id(&self) -> &Option<Option<Uuid>> {
         &self.album_id
     }
     pub fn visibility(&self) -> &Option<Visibility> {
         &self.visibility
     }
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.category.is_none()
+            && self.tags.is_none()
+            && self.album_id.is_none()
+            && self.visibility.is_none()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -118,6 +161,8 @@ pub struct CreatePhoto {
     image_reference_url: url::Url,
     image_size: u64,
     image_format: ImageFormat,
+    image_renditions: Vec<ImageRendition>,
+    image_properties: ImageProperties,
 }
 
 impl CreatePhoto {
@@ -157,6 +202,18 @@ impl CreatePhoto {
     pub fn image_id(&self) -> &Uuid {
         &self.image_id
     }
+    pub fn image_renditions(&self) -> &[ImageRendition] {
+        &self.image_renditions
+    }
+    pub fn with_image_renditions(self, image_renditions: Vec<ImageRendition>) -> Self {
+        Self { image_renditions, ..self }
+    }
+    pub fn image_properties(&self) -> &ImageProperties {
+        &self.image_properties
+    }
+    pub fn with_image_properties(self, image_properties: ImageProperties) -> Self {
+        Self { image_properties, ..self }
+    }
 
     pub fn new(
         title: &str,
@@ -185,6 +242,8 @@ impl CreatePhoto {
             image_reference_url: image_reference_url.clone(),
             image_size: size,
             image_format: format.clone(),
+            image_renditions: Vec::new(),
+            image_properties: ImageProperties::default(),
         }
     }
 }
@@ -202,6 +261,46 @@ impl From<PhotoEntity> for Photo {
             visibility: Visibility::from(photo_entity.visibility),
             image: ImageReference::from(photo_entity.image),
             created_at: photo_entity.created_at,
+            version: photo_entity.version,
+        }
+    }
+}
+
+/// A photo matching a full-text search, with its relevance and highlighted snippet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhotoSearchResult {
+    photo: Photo,
+    rank: f32,
+    headline: String,
+}
+
+impl PhotoSearchResult {
+    pub fn new(photo: Photo, rank: f32, headline: String) -> Self {
+        Self { photo, rank, headline }
+    }
+    pub fn photo(&self) -> &Photo {
+        &self.photo
+    }
+    pub fn rank(&self) -> f32 {
+        self.rank
+    }
+    pub fn headline(&self) -> &str {
+        &self.headline
+    }
+}
+
+impl Paginated for PhotoSearchResult {
+    fn cursor(&self) -> Cursor {
+        Cursor::ranked(self.rank, self.photo.created_at, self.photo.id)
+    }
+}
+
+impl From<PhotoSearchResultEntity> for PhotoSearchResult {
+    fn from(photo_search_result_entity: PhotoSearchResultEntity) -> Self {
+        Self {
+            photo: Photo::from(photo_search_result_entity.photo),
+            rank: photo_search_result_entity.rank,
+            headline: photo_search_result_entity.headline,
         }
     }
 }
@@ -250,4 +349,64 @@ impl UploadPhoto {
     pub fn album_id(&self) -> &Option<Uuid> {
         &self.album_id
     }
-}
\ No newline at end of file
+}
+/// Filters applied when listing photos, an empty query matches every photo.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhotoQuery {
+    tags: Vec<String>,
+    category: Option<String>,
+    album_id: Option<Uuid>,
+    visibility: Option<Visibility>,
+    created_after: Option<chrono::DateTime<Utc>>,
+    created_before: Option<chrono::DateTime<Utc>>,
+    owner_user_id: Option<Uuid>,
+}
+
+impl PhotoQuery {
+    pub fn new(
+        tags: Vec<String>,
+        category: Option<String>,
+        album_id: Option<Uuid>,
+        visibility: Option<Visibility>,
+        created_after: Option<chrono::DateTime<Utc>>,
+        created_before: Option<chrono::DateTime<Utc>>,
+        owner_user_id: Option<Uuid>,
+    ) -> Self {
+        Self { tags, category, album_id, visibility, created_after, created_before, owner_user_id }
+    }
+    pub fn for_album(album_id: &Uuid) -> Self {
+        Self { album_id: Some(*album_id), ..Self::default() }
+    }
+    /// Photos must contain all of these tags.
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+    pub fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+    pub fn album_id(&self) -> Option<&Uuid> {
+        self.album_id.as_ref()
+    }
+    pub fn visibility(&self) -> Option<&Visibility> {
+        self.visibility.as_ref()
+    }
+    pub fn created_after(&self) -> Option<chrono::DateTime<Utc>> {
+        self.created_after
+    }
+    pub fn created_before(&self) -> Option<chrono::DateTime<Utc>> {
+        self.created_before
+    }
+    pub fn owner_user_id(&self) -> Option<&Uuid> {
+        self.owner_user_id.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum InvalidPhotoQueryError {
+    InvalidAlbum,
+    InvalidVisibility,
+    InvalidCreatedAfter,
+    InvalidCreatedBefore,
+    InvalidDateRange,
+    InvalidOwner,
+}
@@ -0,0 +1,208 @@
+use chrono::{DateTime, Utc};
+use image::ImageFormat;
+use url::Url;
+use uuid::Uuid;
+
+use crate::models::entity::upload::PendingUploadEntity;
+use crate::models::service::Visibility;
+
+/// The photo to create once its image is uploaded by the client to a presigned URL.
+#[derive(Debug, Clone)]
+pub struct CreatePendingUpload {
+    filename: String,
+    title: String,
+    album_id: Option<Uuid>,
+    description: String,
+    category: String,
+    tags: Vec<String>,
+    visibility: Visibility,
+}
+
+impl CreatePendingUpload {
+    pub fn new(
+        filename: String,
+        title: String,
+        album_id: Option<Uuid>,
+        description: String,
+        category: String,
+        tags: Vec<String>,
+        visibility: Visibility,
+    ) -> Self {
+        Self { filename, title, album_id, description, category, tags, visibility }
+    }
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+    pub fn album_id(&self) -> Option<&Uuid> {
+        self.album_id.as_ref()
+    }
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingUpload {
+    id: Uuid,
+    owner_user_id: Uuid,
+    filename: String,
+    title: String,
+    description: String,
+    category: String,
+    tags: Vec<String>,
+    album_id: Option<Uuid>,
+    visibility: Visibility,
+    expires_at: DateTime<Utc>,
+}
+
+impl PendingUpload {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+    pub fn owner_user_id(&self) -> &Uuid {
+        &self.owner_user_id
+    }
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+    pub fn category(&self) -> &str {
+        &self.category
+    }
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+    pub fn album_id(&self) -> &Option<Uuid> {
+        &self.album_id
+    }
+    pub fn visibility(&self) -> &Visibility {
+        &self.visibility
+    }
+    /// When the presigned URL stops accepting the upload.
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+impl From<PendingUploadEntity> for PendingUpload {
+    fn from(pending_upload_entity: PendingUploadEntity) -> Self {
+        Self {
+            id: pending_upload_entity.id,
+            owner_user_id: pending_upload_entity.owner_user_id,
+            filename: pending_upload_entity.filename,
+            title: pending_upload_entity.title,
+            description: pending_upload_entity.description,
+            category: pending_upload_entity.category,
+            tags: pending_upload_entity.tags,
+            album_id: pending_upload_entity.album_id,
+            visibility: Visibility::from(pending_upload_entity.visibility),
+            expires_at: pending_upload_entity.expires_at,
+        }
+    }
+}
+
+/// A pending upload along with the URL the client sends the image to with a `PUT`.
+#[derive(Debug, Clone)]
+pub struct PresignedUpload {
+    pending_upload: PendingUpload,
+    upload_url: Url,
+}
+
+impl PresignedUpload {
+    pub fn new(pending_upload: PendingUpload, upload_url: Url) -> Self {
+        Self { pending_upload, upload_url }
+    }
+    pub fn pending_upload(&self) -> &PendingUpload {
+        &self.pending_upload
+    }
+    pub fn upload_url(&self) -> &Url {
+        &self.upload_url
+    }
+}
+
+/// What is known of an object uploaded to a presigned URL without downloading it: its size,
+/// its entity tag and its first bytes, enough to recognize the format of an image.
+#[derive(Debug, Clone)]
+pub struct UploadedObject {
+    size: u64,
+    entity_tag: String,
+    head: Vec<u8>,
+}
+
+impl UploadedObject {
+    pub fn new(size: u64, entity_tag: &str, head: Vec<u8>) -> Self {
+        Self { size, entity_tag: entity_tag.to_string(), head }
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn entity_tag(&self) -> &str {
+        &self.entity_tag
+    }
+    pub fn head(&self) -> &[u8] {
+        &self.head
+    }
+}
+
+/// An uploaded object recognized as an image, to be moved along with the other images as long
+/// as its entity tag still matches the inspected one.
+#[derive(Debug, Clone)]
+pub struct UploadedImage {
+    upload_id: Uuid,
+    filename: String,
+    format: ImageFormat,
+    visibility: Visibility,
+    size: u64,
+    entity_tag: String,
+}
+
+impl UploadedImage {
+    pub fn new(upload_id: &Uuid, filename: &str, format: ImageFormat, visibility: Visibility, uploaded_object: &UploadedObject) -> Self {
+        Self {
+            upload_id: *upload_id,
+            filename: filename.to_string(),
+            format,
+            visibility,
+            size: uploaded_object.size(),
+            entity_tag: uploaded_object.entity_tag().to_string(),
+        }
+    }
+    pub fn upload_id(&self) -> &Uuid {
+        &self.upload_id
+    }
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+    pub fn visibility(&self) -> Visibility {
+        self.visibility
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn entity_tag(&self) -> &str {
+        &self.entity_tag
+    }
+}
@@ -7,6 +7,9 @@ use crate::setup::DatabaseConfig;
 pub mod photo_repository;
 pub mod album_repository;
 pub mod image_reference_repository;
+pub mod pending_upload_repository;
+mod photo_query_builder;
+mod partial_update_builder;
 
 #[derive(Clone, Debug)]
 pub struct PostgresDatabase {
@@ -39,5 +42,3 @@ impl PostgresDatabase {
         )
     }
 }
-
-const NULL: &'static str = "NULL";
@@ -1,19 +1,28 @@
 use anyhow::{anyhow, Context};
-use sqlx::{Acquire, PgConnection, query_file_as};
+use sqlx::{Acquire, PgConnection, query_file, query_file_as};
 use uuid::Uuid;
 
 use crate::models::entity::{ImageReferenceEntity, VisibilityEntity};
-use crate::models::entity::album::{AlbumCoverImageReferenceEntity, AlbumEntity, AlbumNoCoverImageReferenceEntity};
-use crate::models::service::album::{CreateAlbum, UpdateAlbum};
+use crate::models::entity::album::{AlbumCoverImageReferenceEntity, AlbumEntity, AlbumNoCoverImageReferenceEntity, DeletedAlbumEntity};
+use crate::models::service::album::{CreateAlbum, DeleteAlbum, DeleteAlbumPhotosMode, UpdateAlbum};
+use crate::models::service::ExpectedVersion;
 use crate::models::service::image::ImageReference;
-use crate::repository::{NULL, PostgresDatabase};
+use crate::models::service::pagination::Cursor;
+use crate::repository::PostgresDatabase;
+use crate::repository::partial_update_builder::PartialUpdateBuilder;
 
 #[async_trait::async_trait]
 pub trait AlbumRepository: Clone + Send + Sync + 'static {
     async fn create_album(&self, album: &CreateAlbum) -> anyhow::Result<AlbumEntity>;
-    async fn find_all_albums(&self) -> anyhow::Result<Vec<AlbumEntity>>;
+    async fn find_all_albums(&self, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<AlbumEntity>>;
     async fn find_album_by_id(&self, id: &Uuid) -> anyhow::Result<Option<AlbumEntity>>;
-    async fn update_album(&self, update_album: &UpdateAlbum) -> anyhow::Result<AlbumEntity>;
+    async fn update_album(&self, update_album: &UpdateAlbum, expected_version: &ExpectedVersion) -> anyhow::Result<Option<AlbumEntity>>;
+    /// The photos of the album owner are moved to the trash in the `Delete` mode, the photos
+    /// of the other users are detached in both modes.
+    async fn delete_album(&self, delete_album: &DeleteAlbum, expected_version: &ExpectedVersion) -> anyhow::Result<Option<DeletedAlbumEntity>>;
+    /// Deletes the reference of a former cover image unless a photo or another album still
+    /// uses it, returns whether the image can be removed from the storage.
+    async fn delete_cover_image_if_unused(&self, image_id: &Uuid) -> anyhow::Result<bool>;
 }
 
 #[async_trait::async_trait]
@@ -33,6 +42,7 @@ impl AlbumRepository for PostgresDatabase {
             create_album.cover_image_size(),
             create_album.cover_image_format(),
             create_album.visibility(),
+            create_album.cover_image_properties(),
         );
         
         let album_cover_image = Self::insert_image_reference(
@@ -46,18 +56,30 @@ impl AlbumRepository for PostgresDatabase {
             &mut *tx
         ).await?;
 
+        Self::insert_image_renditions(
+            create_album.cover_image_id(),
+            create_album.cover_image_renditions(),
+            &mut tx
+        ).await?;
+
         tx.commit().await?;
 
         Ok(created_album_entity)
     }
 
-    async fn find_all_albums(&self) -> anyhow::Result<Vec<AlbumEntity>> {
+    async fn find_all_albums(&self, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<AlbumEntity>> {
         let mut conn = self
             .acquire()
             .await
             .with_context(|| "Unable to acquire a database connection".to_string())?;
 
-        let album_entities: Vec<_> = query_file_as!(AlbumCoverImageReferenceEntity, "queries/postgres/find_all_albums.sql")
+        let album_entities: Vec<_> = query_file_as!(
+            AlbumCoverImageReferenceEntity,
+            "queries/postgres/find_all_albums.sql",
+            limit as i64,
+            cursor.map(Cursor::created_at),
+            cursor.map(Cursor::id)
+        )
             .fetch_all(&mut *conn)
             .await?;
 
@@ -78,34 +100,139 @@ impl AlbumRepository for PostgresDatabase {
         Ok(option_album.map(AlbumEntity::from))
     }
 
-    async fn update_album(&self, update_album: &UpdateAlbum) -> anyhow::Result<AlbumEntity> {
+    async fn update_album(&self, update_album: &UpdateAlbum, expected_version: &ExpectedVersion) -> anyhow::Result<Option<AlbumEntity>> {
+        let album_id = update_album.id();
+        let cover_photo_id = update_album.cover_photo_id().copied();
+        let partial_update_builder = PartialUpdateBuilder::new("WITH updated_album AS (UPDATE albums")
+            .set("title", update_album.title().clone())
+            .set("description", update_album.description().clone())
+            .set("visibility", update_album.visibility().map(VisibilityEntity::from))
+            .set_with("cover_image_id", cover_photo_id, |query_builder, cover_photo_id| {
+                query_builder
+                    .push("(SELECT photos.image_id FROM photos WHERE photos.id = ")
+                    .push_bind(cover_photo_id)
+                    .push(")");
+            });
+        let Some(mut query_builder) = partial_update_builder.into_query_builder() else {
+            let album_entity = self.find_album_by_id(album_id).await?;
+            return Ok(album_entity.filter(|album_entity| expected_version.matches(album_entity.version)));
+        };
+
+        query_builder
+            .push(" WHERE albums.id = ")
+            .push_bind(*album_id);
+        if let Some(cover_photo_id) = cover_photo_id {
+            query_builder
+                .push(" AND EXISTS (SELECT 1 FROM photos WHERE photos.album_id = albums.id AND photos.is_deleted = false AND photos.id = ")
+                .push_bind(cover_photo_id)
+                .push(")");
+        }
+        if let Some(versions) = expected_version.versions() {
+            query_builder
+                .push(" AND albums.version = ANY(")
+                .push_bind(versions)
+                .push(")");
+        }
+        query_builder.push(Self::SELECT_UPDATED_ALBUM);
+
         let mut conn = self.acquire()
             .await
             .with_context(|| "Unable to acquire a database connection".to_string())?;
 
-        let album_id = update_album.id();
-        let title = update_album.title().clone().unwrap_or(String::from(NULL));
-        let visibility = update_album.visibility().clone().map(VisibilityEntity::from).unwrap_or(VisibilityEntity::Null);
-
-        let updated_album_entity: AlbumCoverImageReferenceEntity =
-            query_file_as!(
-                AlbumCoverImageReferenceEntity,
-                "queries/postgres/update_album.sql",
-                album_id,
-                title,
-                visibility as _,
-            ).fetch_all(&mut *conn)
-                .await.map_err(|err| anyhow!("Unable to update an album {}", err))?
-                .get(0)
-                .cloned()
-                .take()
-                .ok_or(anyhow!("Unable to update an album"))?;
-
-        Ok(AlbumEntity::from(updated_album_entity))
+        let updated_album_entity: Option<AlbumCoverImageReferenceEntity> = query_builder
+            .build_query_as()
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to update an album {}", err))?;
+
+        Ok(updated_album_entity.map(AlbumEntity::from))
+    }
+
+    async fn delete_album(&self, delete_album: &DeleteAlbum, expected_version: &ExpectedVersion) -> anyhow::Result<Option<DeletedAlbumEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let mut tx = conn.begin().await?;
+
+        let album_id = delete_album.id();
+        let deleted_photo_image_ids = match delete_album.photos_mode() {
+            DeleteAlbumPhotosMode::Detach => {
+                query_file!("queries/postgres/detach_album_photos.sql", album_id)
+                    .execute(&mut *tx)
+                    .await?;
+                Vec::new()
+            },
+            DeleteAlbumPhotosMode::Delete => query_file!("queries/postgres/soft_delete_album_photos.sql", album_id)
+                .fetch_all(&mut *tx)
+                .await?
+                .into_iter()
+                .filter(|photo| photo.is_deleted)
+                .map(|photo| photo.image_id)
+                .collect(),
+        };
+
+        let versions = expected_version.versions();
+        let deleted_album = query_file!("queries/postgres/delete_album.sql", album_id, versions.as_deref())
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|err| anyhow!("Unable to delete an album {}", err))?;
+
+        let Some(deleted_album) = deleted_album else {
+            return Ok(None);
+        };
+
+        tx.commit().await?;
+
+        Ok(Some(DeletedAlbumEntity {
+            cover_image_id: deleted_album.cover_image_id,
+            deleted_photo_image_ids,
+        }))
+    }
+
+    async fn delete_cover_image_if_unused(&self, image_id: &Uuid) -> anyhow::Result<bool> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let deleted_image = query_file!("queries/postgres/delete_unused_image_reference.sql", image_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to delete the cover image of an album {}", err))?;
+
+        Ok(deleted_image.is_some())
     }
 }
 
 impl PostgresDatabase {
+    /// Joins the cover image after the update, as the cover may have changed.
+    const SELECT_UPDATED_ALBUM: &'static str = r#" RETURNING albums.*)
+SELECT
+    updated_album.id AS album_id,
+    updated_album.title,
+    updated_album.description,
+    updated_album.visibility,
+    updated_album.owner_user_id AS album_owner_user_id,
+    updated_album.cover_image_id AS image_reference_id,
+    updated_album.created_at AS album_created_at,
+    updated_album.version,
+
+    images.id AS image_id,
+    images.owner_user_id AS image_owner_user_id,
+    images.url,
+    images.file_size AS size,
+    images.format,
+    images.width,
+    images.height,
+    images.color_type,
+    images.bit_depth,
+    images.sha256,
+    images.created_at AS image_created_at
+FROM
+    updated_album
+JOIN
+    images ON updated_album.cover_image_id = images.id"#;
+
     async fn insert_album(
         create_album: &CreateAlbum,
         cover_image_entity: &ImageReferenceEntity,
@@ -143,9 +270,15 @@ impl PostgresDatabase {
                 size: cover_image_entity.size,
                 visibility,
                 format: cover_image_entity.format.clone(),
+                width: cover_image_entity.width,
+                height: cover_image_entity.height,
+                color_type: cover_image_entity.color_type,
+                bit_depth: cover_image_entity.bit_depth,
+                sha256: cover_image_entity.sha256.clone(),
                 created_at: cover_image_entity.created_at,
             },
             created_at: created_album.created_at,
+            version: created_album.version,
         })
     }
 }
@@ -242,11 +375,65 @@ mod tests {
         assert_eq!(created_album.owner_user_id, owner_user_id);
 
         let album_id = created_album.id;
-        let update_photo = UpdatePhoto::new(&created_photo.id, None, Some(&album_id), None);
-        let moved_photo = pg.update_photo(&update_photo).await.unwrap();
+        let update_photo = UpdatePhoto::new(&created_photo.id, None, None, None, None, Some(Some(album_id)), None);
+        let moved_photo = pg.update_photo(&update_photo, &ExpectedVersion::Any).await.unwrap().unwrap();
         assert_eq!(moved_photo.album_id, Some(album_id));
     }
 
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_only_update_the_provided_album_fields() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let owner_user_id = Uuid::new_v4();
+        let cover_image_url = Url::parse("http://localhost:8080/cover_image").unwrap();
+        let create_album = CreateAlbum::new(
+            "title".to_string(),
+            "description".to_string(),
+            Visibility::Private,
+            owner_user_id,
+            Uuid::new_v4(),
+            cover_image_url.clone(),
+            cover_image_url.clone(),
+            2048,
+            ImageFormat::Jpeg,
+        );
+        let created_album = pg.create_album(&create_album).await.unwrap();
+
+        let image_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let create_photo = CreatePhoto::new(
+            "title",
+            "description",
+            "category",
+            &vec![],
+            &owner_user_id,
+            &image_id,
+            &Some(created_album.id),
+            &Visibility::Private,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        );
+        let created_photo = pg.create_photo(&create_photo).await.unwrap();
+
+        let update_album = UpdateAlbum::new(&created_album.id, Some("NULL".to_string()), None, None, Some(created_photo.id));
+        let updated_album = pg.update_album(&update_album, &ExpectedVersion::Any).await.unwrap().unwrap();
+        assert_eq!(updated_album.title, "NULL");
+        assert_eq!(updated_album.description, "description");
+        assert_eq!(updated_album.visibility, VisibilityEntity::Private);
+        assert_eq!(updated_album.cover_image.id, image_id);
+
+        let update_album = UpdateAlbum::new(&created_album.id, None, None, None, Some(Uuid::new_v4()));
+        assert!(pg.update_album(&update_album, &ExpectedVersion::Any).await.unwrap().is_none());
+
+        let stale_version = ExpectedVersion::OneOf(vec![created_album.version]);
+        let delete_album = DeleteAlbum::new(&created_album.id, DeleteAlbumPhotosMode::Delete);
+        assert!(pg.delete_album(&delete_album, &stale_version).await.unwrap().is_none());
+        assert!(pg.find_photo_by_id(&created_photo.id).await.unwrap().is_some());
+    }
+
     #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
     async fn should_create_and_find_album() {
         let env: &'static str = env!("DATABASE_URL");
@@ -278,7 +465,7 @@ mod tests {
         assert_eq!(created_album.visibility, visibility.into());
         assert_eq!(created_album.owner_user_id, owner_user_id);
 
-        let albums = pg.find_all_albums().await.unwrap();
+        let albums = pg.find_all_albums(30, None).await.unwrap();
 
         assert!(albums.iter().any(|album| album.id == created_album.id));
     }
@@ -317,4 +504,65 @@ mod tests {
         assert_eq!(found_album, Some(created_album));
     }
 
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_delete_album_and_detach_or_delete_its_photos() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        for photos_mode in [DeleteAlbumPhotosMode::Detach, DeleteAlbumPhotosMode::Delete] {
+            let owner_user_id = Uuid::new_v4();
+            let cover_image_id = Uuid::new_v4();
+            let cover_image_url = Url::parse("http://localhost:8080/cover_image").unwrap();
+            let create_album = CreateAlbum::new(
+                "Album Title".to_string(),
+                "Album description".to_string(),
+                Visibility::Private,
+                owner_user_id,
+                cover_image_id,
+                cover_image_url.clone(),
+                cover_image_url.clone(),
+                2048,
+                ImageFormat::Jpeg,
+            );
+            let created_album = pg.create_album(&create_album).await.unwrap();
+
+            let image_url = Url::parse("http://localhost:8080/").unwrap();
+            let create_photo = CreatePhoto::new(
+                "title",
+                "description",
+                "category",
+                &vec!["tag1".to_string()],
+                &owner_user_id,
+                &Uuid::new_v4(),
+                &Some(created_album.id),
+                &Visibility::Private,
+                &image_url,
+                &image_url,
+                1024,
+                &ImageFormat::Png,
+            );
+            let created_photo = pg.create_photo(&create_photo).await.unwrap();
+
+            let delete_album = DeleteAlbum::new(&created_album.id, photos_mode);
+            let deleted_album = pg.delete_album(&delete_album, &ExpectedVersion::Any).await.unwrap().unwrap();
+            assert_eq!(deleted_album.cover_image_id, cover_image_id);
+            assert!(pg.find_album_by_id(&created_album.id).await.unwrap().is_none());
+            assert!(pg.delete_album(&delete_album, &ExpectedVersion::Any).await.unwrap().is_none());
+
+            match photos_mode {
+                DeleteAlbumPhotosMode::Detach => {
+                    let photo = pg.find_photo_by_id(&created_photo.id).await.unwrap().unwrap();
+                    assert_eq!(photo.album_id, None);
+                    assert!(deleted_album.deleted_photo_image_ids.is_empty());
+                }
+                DeleteAlbumPhotosMode::Delete => {
+                    assert_eq!(deleted_album.deleted_photo_image_ids, vec![created_photo.image.id]);
+                    assert!(pg.find_photo_by_id(&created_photo.id).await.unwrap().is_none());
+                    let photo = pg.find_deleted_photo_by_id(&created_photo.id).await.unwrap().unwrap();
+                    assert_eq!(photo.album_id, None);
+                }
+            }
+        }
+    }
+
 }
\ No newline at end of file
@@ -1,14 +1,17 @@
 use anyhow::{anyhow, Context};
-use sqlx::{PgConnection, query_file_as};
+use sqlx::{PgConnection, query_file, query_file_as};
 use uuid::Uuid;
 
-use crate::models::entity::{ImageFormatEntity, ImageReferenceEntity, VisibilityEntity};
-use crate::models::service::image::ImageReference;
+use crate::models::entity::{ColorTypeEntity, ImageFormatEntity, ImageReferenceEntity, ImageRenditionEntity, RenditionSizeEntity, VisibilityEntity};
+use crate::models::service::image::{ImageReference, ImageRendition, RenditionSize};
 use crate::repository::PostgresDatabase;
 
 #[async_trait::async_trait]
 pub trait ImageReferenceRepository: Clone + Send + Sync + 'static {
     async fn find_image_reference_by_id(&self, id: &Uuid) -> anyhow::Result<Option<ImageReferenceEntity>>;
+    async fn find_image_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize) -> anyhow::Result<Option<ImageRenditionEntity>>;
+    /// Records a rendition rendered after the upload, replacing the former one of its size.
+    async fn save_image_rendition(&self, image_id: &Uuid, image_rendition: &ImageRendition) -> anyhow::Result<()>;
 }
 
 
@@ -29,6 +32,42 @@ impl ImageReferenceRepository for PostgresDatabase {
         
         Ok(image_reference_entity)
     }
+
+    async fn find_image_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize) -> anyhow::Result<Option<ImageRenditionEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let image_rendition_entity: Option<ImageRenditionEntity> = query_file_as!(
+            ImageRenditionEntity,
+            "queries/postgres/find_image_rendition.sql",
+            image_id,
+            RenditionSizeEntity::from(rendition_size) as _
+        ).fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(image_rendition_entity)
+    }
+
+    async fn save_image_rendition(&self, image_id: &Uuid, image_rendition: &ImageRendition) -> anyhow::Result<()> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        query_file!(
+            "queries/postgres/save_image_rendition.sql",
+            image_id,
+            RenditionSizeEntity::from(image_rendition.size()) as _,
+            image_rendition.width() as i32,
+            image_rendition.height() as i32,
+            ImageFormatEntity::from(image_rendition.format()) as _,
+            image_rendition.file_size() as i64
+        ).execute(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to save a rendition {}", err))?;
+
+        Ok(())
+    }
 }
 
 impl PostgresDatabase {
@@ -42,7 +81,13 @@ impl PostgresDatabase {
         let size = image_reference.size();
         let format = ImageFormatEntity::from(image_reference.format());
         let visibility = VisibilityEntity::from(image_reference.visibility());
-        
+        let properties = image_reference.properties();
+        let width = properties.width().map(|width| width as i32);
+        let height = properties.height().map(|height| height as i32);
+        let color_type = properties.color_type().map(ColorTypeEntity::from);
+        let bit_depth = properties.bit_depth().map(i16::from);
+        let sha256 = properties.sha256().map(|sha256| sha256.to_vec());
+
         let created_image: ImageReferenceEntity = query_file_as!(
             ImageReferenceEntity,
             "queries/postgres/insert_image_reference.sql",
@@ -51,7 +96,12 @@ impl PostgresDatabase {
             visibility as _,
             url,
             size as i64,
-            format as _
+            format as _,
+            width,
+            height,
+            color_type as _,
+            bit_depth,
+            sha256
         ).fetch_all(conn)
             .await?
             .get(0)
@@ -61,4 +111,71 @@ impl PostgresDatabase {
 
         Ok(created_image)
     }
-}
\ No newline at end of file
+
+    pub async fn insert_image_renditions(
+        image_id: &Uuid,
+        image_renditions: &[ImageRendition],
+        conn: &mut PgConnection,
+    ) -> anyhow::Result<()> {
+        for image_rendition in image_renditions {
+            query_file_as!(
+                ImageRenditionEntity,
+                "queries/postgres/insert_image_rendition.sql",
+                image_id,
+                RenditionSizeEntity::from(image_rendition.size()) as _,
+                image_rendition.width() as i32,
+                image_rendition.height() as i32,
+                ImageFormatEntity::from(image_rendition.format()) as _,
+                image_rendition.file_size() as i64
+            ).fetch_one(&mut *conn)
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use image::ImageFormat;
+    use url::Url;
+    use uuid::Uuid;
+
+    use crate::models::service::image::{ImageRendition, RenditionSize};
+    use crate::models::service::photo::CreatePhoto;
+    use crate::models::service::Visibility;
+    use crate::repository::image_reference_repository::ImageReferenceRepository;
+    use crate::repository::photo_repository::PhotoRepository;
+    use crate::repository::PostgresDatabase;
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_replace_a_saved_rendition() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let image_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let create_photo = CreatePhoto::new(
+            "title",
+            "description",
+            "category",
+            &Vec::new(),
+            &Uuid::new_v4(),
+            &image_id,
+            &None,
+            &Visibility::Private,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        );
+        pg.create_photo(&create_photo).await.unwrap();
+
+        pg.save_image_rendition(&image_id, &ImageRendition::new(RenditionSize::Small, 160, 80, ImageFormat::WebP, 512)).await.unwrap();
+        pg.save_image_rendition(&image_id, &ImageRendition::new(RenditionSize::Small, 160, 90, ImageFormat::WebP, 640)).await.unwrap();
+
+        let image_rendition = pg.find_image_rendition(&image_id, RenditionSize::Small).await.unwrap().unwrap();
+        assert_eq!((image_rendition.width, image_rendition.height, image_rendition.file_size), (160, 90, 640));
+        assert!(pg.find_image_rendition(&image_id, RenditionSize::Large).await.unwrap().is_none());
+    }
+}
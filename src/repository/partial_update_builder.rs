@@ -0,0 +1,75 @@
+use sqlx::{Encode, Postgres, QueryBuilder, Type};
+
+/// Builds the `SET` clause of an `UPDATE` out of optional values.
+///
+/// Only the provided columns are assigned, with their values bound as parameters, so a patch
+/// never has to encode "no change" in the value itself. A value of `Some(None)` sets the
+/// column to `NULL`.
+pub(crate) struct PartialUpdateBuilder<'args> {
+    query_builder: QueryBuilder<'args, Postgres>,
+    assignments: usize,
+}
+
+impl<'args> PartialUpdateBuilder<'args> {
+    /// Starts from the statement up to the updated table, e.g. `UPDATE photos`.
+    pub fn new(update_statement: &str) -> Self {
+        Self { query_builder: QueryBuilder::new(update_statement), assignments: 0 }
+    }
+
+    pub fn set<T>(self, column: &str, value: Option<T>) -> Self
+        where
+            T: 'args + Encode<'args, Postgres> + Type<Postgres> + Send,
+    {
+        self.set_with(column, value, |query_builder, value| {
+            query_builder.push_bind(value);
+        })
+    }
+
+    /// Assigns the expression pushed by `push_expression` when a value is provided.
+    pub fn set_with<T, F>(mut self, column: &str, value: Option<T>, push_expression: F) -> Self
+        where
+            F: FnOnce(&mut QueryBuilder<'args, Postgres>, T),
+    {
+        if let Some(value) = value {
+            let separator = if self.assignments == 0 { " SET " } else { ", " };
+            self.query_builder.push(separator).push(column).push(" = ");
+            push_expression(&mut self.query_builder, value);
+            self.assignments += 1;
+        }
+        self
+    }
+
+    /// Returns the query builder to append the rest of the statement, or `None` when there
+    /// is nothing to update.
+    pub fn into_query_builder(self) -> Option<QueryBuilder<'args, Postgres>> {
+        (self.assignments > 0).then_some(self.query_builder)
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn should_only_set_the_provided_columns() {
+        let mut query_builder = PartialUpdateBuilder::new("UPDATE photos")
+            .set("title", Some("NULL".to_string()))
+            .set::<String>("description", None)
+            .set("album_id", Some(None::<Uuid>))
+            .into_query_builder()
+            .unwrap();
+        query_builder.push(" WHERE photos.id = ").push_bind(Uuid::new_v4());
+
+        assert_eq!(query_builder.sql(), "UPDATE photos SET title = $1, album_id = $2 WHERE photos.id = $3");
+    }
+
+    #[test]
+    fn should_not_build_an_empty_update() {
+        let partial_update_builder = PartialUpdateBuilder::new("UPDATE photos")
+            .set::<String>("title", None);
+
+        assert!(partial_update_builder.into_query_builder().is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{query_file, query_file_as};
+use uuid::Uuid;
+
+use crate::models::entity::upload::PendingUploadEntity;
+use crate::models::entity::VisibilityEntity;
+use crate::models::service::upload::CreatePendingUpload;
+use crate::repository::PostgresDatabase;
+
+#[async_trait::async_trait]
+pub trait PendingUploadRepository: Clone + Send + Sync + 'static {
+    async fn create_pending_upload(
+        &self,
+        id: &Uuid,
+        owner_user_id: &Uuid,
+        create_pending_upload: &CreatePendingUpload,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<PendingUploadEntity>;
+    async fn find_pending_upload_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PendingUploadEntity>>;
+    /// Returns whether the pending upload was deleted by this call, so that a single caller
+    /// completes it.
+    async fn delete_pending_upload(&self, id: &Uuid) -> anyhow::Result<bool>;
+    /// Deletes at most `limit` expired pending uploads, returning their ids.
+    async fn delete_expired_pending_uploads(&self, limit: u32) -> anyhow::Result<Vec<Uuid>>;
+}
+
+#[async_trait::async_trait]
+impl PendingUploadRepository for PostgresDatabase {
+    async fn create_pending_upload(
+        &self,
+        id: &Uuid,
+        owner_user_id: &Uuid,
+        create_pending_upload: &CreatePendingUpload,
+        expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<PendingUploadEntity> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let visibility = VisibilityEntity::from(create_pending_upload.visibility());
+        let pending_upload_entity: PendingUploadEntity = query_file_as!(
+            PendingUploadEntity,
+            "queries/postgres/insert_pending_upload.sql",
+            id,
+            owner_user_id,
+            create_pending_upload.filename(),
+            create_pending_upload.title(),
+            create_pending_upload.description(),
+            create_pending_upload.category(),
+            create_pending_upload.tags(),
+            create_pending_upload.album_id(),
+            visibility as _,
+            expires_at
+        ).fetch_one(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to insert a pending upload {}", err))?;
+
+        Ok(pending_upload_entity)
+    }
+
+    async fn find_pending_upload_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PendingUploadEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let pending_upload_entity: Option<PendingUploadEntity> = query_file_as!(
+            PendingUploadEntity,
+            "queries/postgres/find_pending_upload_by_id.sql",
+            id
+        ).fetch_optional(&mut *conn)
+            .await?;
+
+        Ok(pending_upload_entity)
+    }
+
+    async fn delete_pending_upload(&self, id: &Uuid) -> anyhow::Result<bool> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let deleted_rows = query_file!("queries/postgres/delete_pending_upload.sql", id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to delete a pending upload {}", err))?
+            .rows_affected();
+
+        Ok(deleted_rows > 0)
+    }
+
+    async fn delete_expired_pending_uploads(&self, limit: u32) -> anyhow::Result<Vec<Uuid>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let deleted_ids = query_file!("queries/postgres/delete_expired_pending_uploads.sql", limit as i64)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to delete the expired pending uploads {}", err))?
+            .into_iter()
+            .map(|deleted_row| deleted_row.id)
+            .collect();
+
+        Ok(deleted_ids)
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use uuid::Uuid;
+
+    use crate::models::service::upload::CreatePendingUpload;
+    use crate::models::service::Visibility;
+    use crate::repository::pending_upload_repository::PendingUploadRepository;
+    use crate::repository::PostgresDatabase;
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_only_delete_a_pending_upload_once() {
+        let db_url: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(db_url).await.unwrap();
+        let create_pending_upload = CreatePendingUpload::new(
+            "photo.png".to_string(),
+            "title".to_string(),
+            None,
+            "description".to_string(),
+            "category".to_string(),
+            vec!["tag".to_string()],
+            Visibility::Private,
+        );
+        let id = Uuid::new_v4();
+
+        let created_pending_upload = pg
+            .create_pending_upload(&id, &Uuid::new_v4(), &create_pending_upload, Utc::now() + Duration::minutes(15))
+            .await
+            .unwrap();
+        let found_pending_upload = pg.find_pending_upload_by_id(&id).await.unwrap().unwrap();
+        assert_eq!(found_pending_upload, created_pending_upload);
+        assert_eq!(found_pending_upload.tags, vec!["tag".to_string()]);
+
+        assert!(pg.delete_pending_upload(&id).await.unwrap());
+        assert!(!pg.delete_pending_upload(&id).await.unwrap());
+        assert!(pg.find_pending_upload_by_id(&id).await.unwrap().is_none());
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_delete_the_expired_pending_uploads() {
+        let db_url: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(db_url).await.unwrap();
+        let create_pending_upload = CreatePendingUpload::new(
+            "photo.png".to_string(),
+            "title".to_string(),
+            None,
+            "description".to_string(),
+            "category".to_string(),
+            Vec::new(),
+            Visibility::Private,
+        );
+        let (expired_id, pending_id) = (Uuid::new_v4(), Uuid::new_v4());
+        pg.create_pending_upload(&expired_id, &Uuid::new_v4(), &create_pending_upload, Utc::now() - Duration::minutes(1)).await.unwrap();
+        pg.create_pending_upload(&pending_id, &Uuid::new_v4(), &create_pending_upload, Utc::now() + Duration::minutes(15)).await.unwrap();
+
+        let mut deleted_ids = Vec::new();
+        loop {
+            let deleted_page = pg.delete_expired_pending_uploads(100).await.unwrap();
+            if deleted_page.is_empty() {
+                break;
+            }
+            deleted_ids.extend(deleted_page);
+        }
+
+        assert!(deleted_ids.contains(&expired_id));
+        assert!(!deleted_ids.contains(&pending_id));
+        assert!(pg.find_pending_upload_by_id(&expired_id).await.unwrap().is_none());
+        assert!(pg.find_pending_upload_by_id(&pending_id).await.unwrap().is_some());
+    }
+}
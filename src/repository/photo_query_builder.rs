@@ -0,0 +1,194 @@
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::models::entity::VisibilityEntity;
+use crate::models::service::pagination::Cursor;
+use crate::models::service::photo::PhotoQuery;
+
+/// Builds the `SELECT` of the photo listings out of optional filters.
+///
+/// Every filter appends an `AND` predicate with its value bound as a parameter, so the
+/// builder can be composed freely and always yields rows of `PhotoImageReferenceEntity`.
+pub(crate) struct PhotoQueryBuilder<'args> {
+    query_builder: QueryBuilder<'args, Postgres>,
+}
+
+impl<'args> PhotoQueryBuilder<'args> {
+    /// Not checked by sqlx at compile time: `should_select_every_column_of_the_built_photo_query`
+    /// runs the built query against the database to decode every column.
+    const SELECT_PHOTOS: &'static str = r#"SELECT
+    photos.id AS photo_id,
+    photos.title,
+    photos.description,
+    photos.visibility,
+    photos.owner_user_id AS photo_owner_user_id,
+    photos.tags,
+    photos.category,
+    photos.album_id,
+    photos.image_id AS image_reference_id,
+    photos.is_deleted,
+    photos.created_at AS photo_created_at,
+    photos.version,
+
+    images.id AS image_id,
+    images.owner_user_id AS image_owner_user_id,
+    images.url,
+    images.file_size AS size,
+    images.format,
+    images.width,
+    images.height,
+    images.color_type,
+    images.bit_depth,
+    images.sha256,
+    images.created_at AS image_created_at
+FROM
+    photos
+LEFT JOIN
+    images ON photos.image_id = images.id
+WHERE
+    photos.is_deleted = false"#;
+
+    pub fn select_photos() -> Self {
+        Self { query_builder: QueryBuilder::new(Self::SELECT_PHOTOS) }
+    }
+
+    pub fn filter(self, photo_query: &PhotoQuery) -> Self {
+        self.with_tags(photo_query.tags())
+            .with_category(photo_query.category())
+            .with_album_id(photo_query.album_id())
+            .with_visibility(photo_query.visibility().copied().map(VisibilityEntity::from))
+            .created_after(photo_query.created_after())
+            .created_before(photo_query.created_before())
+            .with_owner_user_id(photo_query.owner_user_id())
+    }
+
+    /// Keeps the photos tagged with all of `tags`, served by the GIN index on `photos.tags`.
+    pub fn with_tags(mut self, tags: &[String]) -> Self {
+        if !tags.is_empty() {
+            self.query_builder
+                .push(" AND photos.tags @> ")
+                .push_bind(tags.to_vec())
+                .push("::text[]");
+        }
+        self
+    }
+
+    pub fn with_category(mut self, category: Option<&str>) -> Self {
+        if let Some(category) = category {
+            self.query_builder
+                .push(" AND photos.category = ")
+                .push_bind(category.to_string());
+        }
+        self
+    }
+
+    pub fn with_album_id(mut self, album_id: Option<&uuid::Uuid>) -> Self {
+        if let Some(album_id) = album_id {
+            self.query_builder
+                .push(" AND photos.album_id = ")
+                .push_bind(*album_id);
+        }
+        self
+    }
+
+    pub fn with_visibility(mut self, visibility: Option<VisibilityEntity>) -> Self {
+        if let Some(visibility) = visibility {
+            self.query_builder
+                .push(" AND photos.visibility = ")
+                .push_bind(visibility);
+        }
+        self
+    }
+
+    pub fn created_after(mut self, created_after: Option<chrono::DateTime<chrono::Utc>>) -> Self {
+        if let Some(created_after) = created_after {
+            self.query_builder
+                .push(" AND photos.created_at >= ")
+                .push_bind(created_after);
+        }
+        self
+    }
+
+    pub fn created_before(mut self, created_before: Option<chrono::DateTime<chrono::Utc>>) -> Self {
+        if let Some(created_before) = created_before {
+            self.query_builder
+                .push(" AND photos.created_at < ")
+                .push_bind(created_before);
+        }
+        self
+    }
+
+    pub fn with_owner_user_id(mut self, owner_user_id: Option<&uuid::Uuid>) -> Self {
+        if let Some(owner_user_id) = owner_user_id {
+            self.query_builder
+                .push(" AND photos.owner_user_id = ")
+                .push_bind(*owner_user_id);
+        }
+        self
+    }
+
+    pub fn after_cursor(mut self, cursor: Option<&Cursor>) -> Self {
+        if let Some(cursor) = cursor {
+            self.query_builder
+                .push(" AND (photos.created_at, photos.id) < (")
+                .push_bind(cursor.created_at())
+                .push(", ")
+                .push_bind(cursor.id())
+                .push(")");
+        }
+        self
+    }
+
+    /// Closes the query with the keyset pagination ordering.
+    pub fn page(mut self, limit: u32) -> QueryBuilder<'args, Postgres> {
+        self.query_builder
+            .push(" ORDER BY photos.created_at DESC, photos.id DESC LIMIT ")
+            .push_bind(limit as i64);
+        self.query_builder
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::models::service::Visibility;
+
+    use super::*;
+
+    #[test]
+    fn should_only_add_the_requested_filters() {
+        let photo_query = PhotoQuery::new(
+            vec!["sea".to_string()],
+            None,
+            None,
+            Some(Visibility::Public),
+            None,
+            None,
+            None,
+        );
+
+        let query_builder = PhotoQueryBuilder::select_photos()
+            .filter(&photo_query)
+            .page(10);
+        let sql = query_builder.sql();
+
+        assert!(sql.ends_with(
+            "photos.is_deleted = false AND photos.tags @> $1::text[] AND photos.visibility = $2 \
+            ORDER BY photos.created_at DESC, photos.id DESC LIMIT $3"
+        ));
+    }
+
+    #[test]
+    fn should_not_filter_an_empty_query() {
+        let query_builder = PhotoQueryBuilder::select_photos()
+            .filter(&PhotoQuery::default())
+            .after_cursor(Some(&Cursor::new(Utc::now(), Uuid::new_v4())))
+            .page(10);
+
+        assert!(query_builder.sql().ends_with(
+            "photos.is_deleted = false AND (photos.created_at, photos.id) < ($1, $2) \
+            ORDER BY photos.created_at DESC, photos.id DESC LIMIT $3"
+        ));
+    }
+}
@@ -1,20 +1,29 @@
 use anyhow::{anyhow, Context};
-use sqlx::{Acquire, PgConnection, query_file_as};
+use sqlx::{Acquire, PgConnection, query_file, query_file_as};
 use sqlx::types::uuid;
 use uuid::Uuid;
 
 use crate::models::entity::{ImageReferenceEntity, VisibilityEntity};
-use crate::models::entity::photo::{PhotoEntity, PhotoImageReferenceEntity, PhotoNoImageReferenceEntity};
+use crate::models::entity::photo::{PhotoEntity, PhotoImageReferenceEntity, PhotoImageReferenceSearchEntity, PhotoNoImageReferenceEntity, PhotoSearchResultEntity};
 use crate::models::service::image::ImageReference;
-use crate::models::service::photo::{CreatePhoto, UpdatePhoto};
-use crate::repository::{NULL, PostgresDatabase};
+use crate::models::service::pagination::Cursor;
+use crate::models::service::ExpectedVersion;
+use crate::models::service::photo::{CreatePhoto, PhotoQuery, UpdatePhoto};
+use crate::repository::PostgresDatabase;
+use crate::repository::partial_update_builder::PartialUpdateBuilder;
+use crate::repository::photo_query_builder::PhotoQueryBuilder;
 
 #[async_trait::async_trait]
 pub trait PhotoRepository: Clone + Send + Sync + 'static {
     async fn create_photo(&self, photo: &CreatePhoto) -> anyhow::Result<PhotoEntity>;
-    async fn find_all_photos(&self, limit: u32, offset: u32) -> anyhow::Result<Vec<PhotoEntity>>;
+    async fn find_all_photos(&self, photo_query: &PhotoQuery, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoEntity>>;
+    async fn search_photos(&self, query: &str, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoSearchResultEntity>>;
     async fn find_photo_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PhotoEntity>>;
-    async fn update_photo(&self, photo: &UpdatePhoto) -> anyhow::Result<PhotoEntity>;
+    async fn update_photo(&self, photo: &UpdatePhoto, expected_version: &ExpectedVersion) -> anyhow::Result<Option<PhotoEntity>>;
+    async fn find_all_deleted_photos(&self, owner_user_id: &Uuid, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoEntity>>;
+    async fn find_deleted_photo_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PhotoEntity>>;
+    async fn delete_photo(&self, id: &Uuid, expected_version: &ExpectedVersion) -> anyhow::Result<bool>;
+    async fn restore_photo(&self, id: &Uuid) -> anyhow::Result<PhotoEntity>;
 }
 
 #[async_trait::async_trait]
@@ -34,6 +43,7 @@ impl PhotoRepository for PostgresDatabase {
             create_photo.image_size(),
             create_photo.image_format(),
             create_photo.visibility(),
+            create_photo.image_properties(),
         );
 
         let created_image_entity = Self::insert_image_reference(
@@ -47,21 +57,128 @@ impl PhotoRepository for PostgresDatabase {
             &mut tx
         ).await?;
 
+        Self::insert_image_renditions(
+            create_photo.image_id(),
+            create_photo.image_renditions(),
+            &mut tx
+        ).await?;
+
         tx.commit().await?;
 
         Ok(created_photo_entity)
     }
 
-    async fn find_all_photos(&self, limit: u32, offset: u32) -> anyhow::Result<Vec<PhotoEntity>> {
+    async fn find_all_photos(&self, photo_query: &PhotoQuery, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let mut query_builder = PhotoQueryBuilder::select_photos()
+            .filter(photo_query)
+            .after_cursor(cursor)
+            .page(limit);
+
+        let photo_image_entities: Vec<PhotoImageReferenceEntity> = query_builder
+            .build_query_as()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to find photos {}", err))?;
+
+        Ok(photo_image_entities.into_iter().map(PhotoEntity::from).collect())
+    }
+
+    async fn search_photos(&self, query: &str, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoSearchResultEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let photo_search_entities: Vec<_> = query_file_as!(
+            PhotoImageReferenceSearchEntity,
+            "queries/postgres/search_photos.sql",
+            query,
+            limit as i64,
+            cursor.and_then(Cursor::rank),
+            cursor.map(Cursor::created_at),
+            cursor.map(Cursor::id)
+        )
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to search photos {}", err))?;
+
+        Ok(photo_search_entities.into_iter().map(PhotoSearchResultEntity::from).collect())
+    }
+
+    async fn find_photo_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PhotoEntity>> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let photo_image_entity: Option<PhotoImageReferenceEntity> = query_file_as!(
+            PhotoImageReferenceEntity,
+            "queries/postgres/find_photo_by_id.sql",
+            id
+        ).fetch_optional(&mut *conn)
+        .await?;
+
+        Ok(photo_image_entity.map(PhotoEntity::from))
+    }
+
+    async fn update_photo(&self, update_photo: &UpdatePhoto, expected_version: &ExpectedVersion) -> anyhow::Result<Option<PhotoEntity>> {
+        let photo_id = update_photo.id();
+        let partial_update_builder = PartialUpdateBuilder::new("UPDATE photos")
+            .set("title", update_photo.title().clone())
+            .set("description", update_photo.description().clone())
+            .set("category", update_photo.category().clone())
+            .set("tags", update_photo.tags().clone())
+            .set("album_id", *update_photo.album_id())
+            .set("visibility", update_photo.visibility().map(VisibilityEntity::from));
+        let Some(mut query_builder) = partial_update_builder.into_query_builder() else {
+            let photo_entity = self.find_photo_by_id(photo_id).await?;
+            return Ok(photo_entity.filter(|photo_entity| expected_version.matches(photo_entity.version)));
+        };
+
+        query_builder
+            .push(" FROM images WHERE photos.image_id = images.id AND photos.is_deleted = false AND photos.id = ")
+            .push_bind(*photo_id);
+        if let Some(Some(album_id)) = update_photo.album_id() {
+            query_builder
+                .push(" AND EXISTS (SELECT 1 FROM albums WHERE albums.id = ")
+                .push_bind(*album_id)
+                .push(")");
+        }
+        if let Some(versions) = expected_version.versions() {
+            query_builder
+                .push(" AND photos.version = ANY(")
+                .push_bind(versions)
+                .push(")");
+        }
+        query_builder.push(Self::RETURNING_PHOTO_IMAGE_REFERENCE);
+
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let updated_photo_entity: Option<PhotoImageReferenceEntity> = query_builder
+            .build_query_as()
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to update a photo {}", err))?;
+
+        Ok(updated_photo_entity.map(PhotoEntity::from))
+    }
+
+    async fn find_all_deleted_photos(&self, owner_user_id: &Uuid, limit: u32, cursor: Option<&Cursor>) -> anyhow::Result<Vec<PhotoEntity>> {
         let mut conn = self.acquire()
             .await
             .with_context(|| "Unable to acquire a database connection".to_string())?;
 
         let photo_image_entities: Vec<_> = query_file_as!(
-            PhotoImageReferenceEntity, 
-            "queries/postgres/find_all_photo.sql",
+            PhotoImageReferenceEntity,
+            "queries/postgres/find_all_deleted_photos_by_owner.sql",
+            owner_user_id,
             limit as i64,
-            offset as i64
+            cursor.map(Cursor::created_at),
+            cursor.map(Cursor::id)
         )
             .fetch_all(&mut *conn)
             .await?;
@@ -69,14 +186,14 @@ impl PhotoRepository for PostgresDatabase {
         Ok(photo_image_entities.into_iter().map(PhotoEntity::from).collect())
     }
 
-    async fn find_photo_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PhotoEntity>> {
+    async fn find_deleted_photo_by_id(&self, id: &Uuid) -> anyhow::Result<Option<PhotoEntity>> {
         let mut conn = self.acquire()
             .await
             .with_context(|| "Unable to acquire a database connection".to_string())?;
 
         let photo_image_entity: Option<PhotoImageReferenceEntity> = query_file_as!(
             PhotoImageReferenceEntity,
-            "queries/postgres/find_photo_by_id.sql",
+            "queries/postgres/find_deleted_photo_by_id.sql",
             id
         ).fetch_optional(&mut *conn)
         .await?;
@@ -84,35 +201,65 @@ impl PhotoRepository for PostgresDatabase {
         Ok(photo_image_entity.map(PhotoEntity::from))
     }
 
-    async fn update_photo(&self, update_photo: &UpdatePhoto) -> anyhow::Result<PhotoEntity> {
+    async fn delete_photo(&self, id: &Uuid, expected_version: &ExpectedVersion) -> anyhow::Result<bool> {
         let mut conn = self.acquire()
             .await
             .with_context(|| "Unable to acquire a database connection".to_string())?;
-        
-        let photo_id = update_photo.id();
-        let album_id = update_photo.album_id().clone().unwrap_or(Uuid::nil());
-        let title = update_photo.title().clone().unwrap_or(String::from(NULL));
-        let visibility = update_photo.visibility().clone().map(VisibilityEntity::from).unwrap_or(VisibilityEntity::Null);
-        
-        let updated_photo_entity: PhotoImageReferenceEntity = query_file_as!(
+
+        let versions = expected_version.versions();
+        let deleted_rows = query_file!("queries/postgres/soft_delete_photo.sql", id, versions.as_deref())
+            .execute(&mut *conn)
+            .await
+            .map_err(|err| anyhow!("Unable to delete a photo {}", err))?
+            .rows_affected();
+
+        Ok(deleted_rows > 0)
+    }
+
+    async fn restore_photo(&self, id: &Uuid) -> anyhow::Result<PhotoEntity> {
+        let mut conn = self.acquire()
+            .await
+            .with_context(|| "Unable to acquire a database connection".to_string())?;
+
+        let restored_photo_entity: PhotoImageReferenceEntity = query_file_as!(
             PhotoImageReferenceEntity,
-            "queries/postgres/update_photo.sql",
-            photo_id,
-            album_id,
-            title,
-            visibility as _,
-        ).fetch_all(&mut *conn)
-            .await.map_err(|err| anyhow!("Unable to update a photo {}", err))?
-            .get(0)
-            .cloned()
-            .take()
-            .ok_or(anyhow!("Unable to update a photo"))?;
-        
-        Ok(PhotoEntity::from(updated_photo_entity))
+            "queries/postgres/restore_photo.sql",
+            id
+        ).fetch_optional(&mut *conn)
+            .await.map_err(|err| anyhow!("Unable to restore a photo {}", err))?
+            .ok_or(anyhow!("Unable to restore a photo"))?;
+
+        Ok(PhotoEntity::from(restored_photo_entity))
     }
 }
 
 impl PostgresDatabase {
+    const RETURNING_PHOTO_IMAGE_REFERENCE: &'static str = r#" RETURNING
+    photos.id AS photo_id,
+    photos.title,
+    photos.description,
+    photos.visibility,
+    photos.owner_user_id AS photo_owner_user_id,
+    photos.tags,
+    photos.category,
+    photos.album_id,
+    photos.image_id AS image_reference_id,
+    photos.is_deleted,
+    photos.created_at AS photo_created_at,
+    photos.version,
+
+    images.id AS image_id,
+    images.owner_user_id AS image_owner_user_id,
+    images.url,
+    images.file_size AS size,
+    images.format,
+    images.width,
+    images.height,
+    images.color_type,
+    images.bit_depth,
+    images.sha256,
+    images.created_at AS image_created_at"#;
+
     pub async fn insert_photo(
         create_photo: &CreatePhoto,
         image_entity: &ImageReferenceEntity,
@@ -163,10 +310,16 @@ impl PostgresDatabase {
                 size: image_entity.size,
                 visibility,
                 format: image_entity.format.clone(),
+                width: image_entity.width,
+                height: image_entity.height,
+                color_type: image_entity.color_type,
+                bit_depth: image_entity.bit_depth,
+                sha256: image_entity.sha256.clone(),
                 created_at: image_entity.created_at,
             },
             created_at: created_photo_image_entity.created_at,
             is_deleted: created_photo_image_entity.is_deleted,
+            version: created_photo_image_entity.version,
         })
     }
 }
@@ -177,7 +330,11 @@ mod tests {
     use url::Url;
     use uuid::Uuid;
 
-    use crate::models::service::photo::{CreatePhoto, UpdatePhoto};
+    use crate::models::entity::ColorTypeEntity;
+    use crate::models::service::image::{ColorType, ImageProperties};
+    use crate::models::service::pagination::Cursor;
+    use crate::models::service::ExpectedVersion;
+use crate::models::service::photo::{CreatePhoto, PhotoQuery, UpdatePhoto};
     use crate::models::service::Visibility;
     use crate::repository::photo_repository::PhotoRepository;
     use crate::repository::PostgresDatabase;
@@ -219,13 +376,163 @@ mod tests {
         let env: &'static str = env!("DATABASE_URL");
         let pg = PostgresDatabase::connect(env).await.unwrap();
 
-        let photos = pg.find_all_photos(30, 0).await.unwrap();
+        let photos = pg.find_all_photos(&PhotoQuery::default(), 30, None).await.unwrap();
         
         for photo in photos {
             assert!(photo.id.is_nil() == false, "Photo ID should be valid");
         }
     }
 
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_page_through_photos_with_a_cursor() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let first_page = pg.find_all_photos(&PhotoQuery::default(), 2, None).await.unwrap();
+        let Some(last_photo) = first_page.last() else {
+            return;
+        };
+
+        let cursor = Cursor::new(last_photo.created_at, last_photo.id);
+        let second_page = pg.find_all_photos(&PhotoQuery::default(), 2, Some(&cursor)).await.unwrap();
+
+        for photo in &second_page {
+            assert!(first_page.iter().all(|first_page_photo| first_page_photo.id != photo.id));
+            assert!((photo.created_at, photo.id) < (cursor.created_at(), cursor.id()));
+        }
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_filter_photos_by_tags_category_and_owner() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let owner_user_id = Uuid::new_v4();
+        let image_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let tag = Uuid::new_v4().to_string();
+        let create_photo = CreatePhoto::new(
+            "title",
+            "description",
+            "landscape",
+            &vec![tag.clone(), "sunset".to_string()],
+            &owner_user_id,
+            &image_id,
+            &None,
+            &Visibility::Public,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        );
+        let created_photo = pg.create_photo(&create_photo).await.unwrap();
+
+        let matching_query = PhotoQuery::new(
+            vec![tag.clone(), "sunset".to_string()],
+            Some("landscape".to_string()),
+            None,
+            Some(Visibility::Public),
+            Some(created_photo.created_at - chrono::Duration::minutes(1)),
+            None,
+            Some(owner_user_id),
+        );
+        let photos = pg.find_all_photos(&matching_query, 30, None).await.unwrap();
+        assert_eq!(photos.len(), 1);
+        assert_eq!(photos[0].id, created_photo.id);
+
+        let other_category_query = PhotoQuery::new(vec![tag.clone()], Some("portrait".to_string()), None, None, None, None, None);
+        assert!(pg.find_all_photos(&other_category_query, 30, None).await.unwrap().is_empty());
+
+        let missing_tag_query = PhotoQuery::new(vec![tag, "sunrise".to_string()], None, None, None, None, None, None);
+        assert!(pg.find_all_photos(&missing_tag_query, 30, None).await.unwrap().is_empty());
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_select_every_column_of_the_built_photo_query() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let owner_user_id = Uuid::new_v4();
+        let image_id = Uuid::new_v4();
+        let album_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let tag = Uuid::new_v4().to_string();
+        let image_properties = ImageProperties::new([7; 32]).with_layout(640, 480, ColorType::Rgba, 8);
+        let create_photo = CreatePhoto::new(
+            "title",
+            "description",
+            "landscape",
+            &vec![tag.clone()],
+            &owner_user_id,
+            &image_id,
+            &Some(album_id),
+            &Visibility::Public,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        ).with_image_properties(image_properties);
+        let created_photo = pg.create_photo(&create_photo).await.unwrap();
+
+        let photo_query = PhotoQuery::new(
+            vec![tag],
+            Some("landscape".to_string()),
+            Some(album_id),
+            Some(Visibility::Public),
+            Some(created_photo.created_at - chrono::Duration::minutes(1)),
+            Some(created_photo.created_at + chrono::Duration::minutes(1)),
+            Some(owner_user_id),
+        );
+        let cursor = Cursor::new(created_photo.created_at + chrono::Duration::minutes(1), Uuid::nil());
+        let photos = pg.find_all_photos(&photo_query, 30, Some(&cursor)).await.unwrap();
+
+        assert_eq!(photos, vec![created_photo.clone()]);
+        assert_eq!(photos[0].image.id, image_id);
+        assert_eq!(photos[0].image.width, Some(640));
+        assert_eq!(photos[0].image.height, Some(480));
+        assert_eq!(photos[0].image.color_type, Some(ColorTypeEntity::Rgba));
+        assert_eq!(photos[0].image.bit_depth, Some(8));
+        assert_eq!(photos[0].image.sha256, Some(vec![7; 32]));
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_rank_and_highlight_searched_photos() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let owner_user_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let word = format!("zq{}", Uuid::new_v4().simple());
+        let create_photo = |title: String, description: String| CreatePhoto::new(
+            &title,
+            &description,
+            "category",
+            &vec![],
+            &owner_user_id,
+            &Uuid::new_v4(),
+            &None,
+            &Visibility::Public,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        );
+        let in_description = pg.create_photo(&create_photo("Holidays".to_string(), format!("A walk with {}", word))).await.unwrap();
+        let in_title = pg.create_photo(&create_photo(format!("{} at dawn", word), "Morning".to_string())).await.unwrap();
+
+        let first_page = pg.search_photos(&word, 1, None).await.unwrap();
+        assert_eq!(first_page.len(), 1);
+        assert_eq!(first_page[0].photo.id, in_title.id);
+        assert!(first_page[0].headline.contains(&format!("<mark>{}</mark>", word)));
+
+        let last_result = &first_page[0];
+        let cursor = Cursor::ranked(last_result.rank, last_result.photo.created_at, last_result.photo.id);
+        let second_page = pg.search_photos(&word, 10, Some(&cursor)).await.unwrap();
+        assert_eq!(second_page.len(), 1);
+        assert_eq!(second_page[0].photo.id, in_description.id);
+        assert!(second_page[0].rank < last_result.rank);
+    }
+
     #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
     async fn find_photo_by_id() {
         let env: &'static str = env!("DATABASE_URL");
@@ -290,13 +597,64 @@ mod tests {
         let new_title = "new_title".to_string();
         let update_photo = UpdatePhoto::new(
             &created_photo.id,
-            Some(&new_title),
+            Some(new_title.clone()),
+            None,
             None,
+            Some(vec![]),
+            Some(None),
             None,
         );
-        let updated_photo = pg.update_photo(&update_photo).await.unwrap();
+        let expected_version = ExpectedVersion::OneOf(vec![created_photo.version]);
+        let updated_photo = pg.update_photo(&update_photo, &expected_version).await.unwrap().unwrap();
         assert_eq!(&updated_photo.id, &created_photo.id);
         assert_eq!(updated_photo.title, new_title);
+        assert_eq!(updated_photo.description, "description");
+        assert!(updated_photo.tags.is_empty());
+        assert_eq!(updated_photo.album_id, None);
+        assert_eq!(updated_photo.version, created_photo.version + 1);
+
+        assert!(pg.update_photo(&update_photo, &expected_version).await.unwrap().is_none());
+        assert!(!pg.delete_photo(&created_photo.id, &expected_version).await.unwrap());
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn soft_delete_and_restore_photo() {
+        let env: &'static str = env!("DATABASE_URL");
+        let pg = PostgresDatabase::connect(env).await.unwrap();
+
+        let owner_user_id = Uuid::new_v4();
+        let image_id = Uuid::new_v4();
+        let image_url = Url::parse("http://localhost:8080/").unwrap();
+        let create_photo = CreatePhoto::new(
+            "title",
+            "description",
+            "category",
+            &vec!["tag1".to_string()],
+            &owner_user_id,
+            &image_id,
+            &None,
+            &Visibility::Private,
+            &image_url,
+            &image_url,
+            1024,
+            &ImageFormat::Png,
+        );
+
+        let created_photo = pg.create_photo(&create_photo).await.unwrap();
+
+        assert!(pg.delete_photo(&created_photo.id, &ExpectedVersion::Any).await.unwrap());
+        assert!(!pg.delete_photo(&created_photo.id, &ExpectedVersion::Any).await.unwrap());
+        assert!(pg.find_photo_by_id(&created_photo.id).await.unwrap().is_none());
+        assert!(pg.find_deleted_photo_by_id(&created_photo.id).await.unwrap().is_some());
+
+        let trash = pg.find_all_deleted_photos(&owner_user_id, 30, None).await.unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, created_photo.id);
+
+        let restored_photo = pg.restore_photo(&created_photo.id).await.unwrap();
+        assert_eq!(restored_photo.id, created_photo.id);
+        assert!(!restored_photo.is_deleted);
+        assert!(pg.find_photo_by_id(&created_photo.id).await.unwrap().is_some());
     }
     
 }
\ No newline at end of file
@@ -6,6 +6,8 @@ pub mod health_check;
 pub mod photo;
 pub mod album;
 pub mod image;
+pub mod metrics;
+pub mod upload;
 
 
 #[get("/")]
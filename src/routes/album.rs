@@ -1,76 +1,124 @@
 use actix_multipart::form::MultipartForm;
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpResponse, web};
+use actix_web::http::header::{ETag, IfMatch};
 use uuid::Uuid;
 
-use crate::models::api::album::{AlbumApi, CreateAlbumApi, PatchAlbumApi};
-use crate::models::api::photo::{PatchPhotoApi, PhotoApi};
-use crate::models::service::album::{CreateAlbumWithCover, UpdateAlbum};
-use crate::models::service::photo::UpdatePhoto;
+use crate::models::api::album::{AlbumApi, CreateAlbumApi, DeleteAlbumQueryApi, PatchAlbumApi};
+use crate::models::api::{version_entity_tag, PageQueryApi};
+use crate::models::api::photo::PhotoApi;
+use crate::models::service::album::{CreateAlbumWithCover, DeleteAlbum, UpdateAlbum};
+use crate::models::service::ExpectedVersion;
+use crate::models::service::pagination::PageRequest;
 use crate::security::auth::user::AuthenticatedUser;
-use crate::service::AlbumService;
-use crate::setup::{AlbumRoutesState, PhotoRoutesState};
+use crate::service::{AlbumService, ServiceError};
+use crate::setup::AlbumRoutesState;
 
 pub const ALBUMS_ROUTE: &'static str = "/albums";
 pub const ALBUM_BY_ID_ROUTE: &'static str = "/albums/{id}";
+pub const ALBUM_PHOTOS_ROUTE: &str = "/albums/{id}/photos";
 
 pub async fn post_albums<AS: AlbumService>(
     authenticated_user: AuthenticatedUser,
     MultipartForm(create_album_api): MultipartForm<CreateAlbumApi>,
     app_state: web::Data<AlbumRoutesState<AS>>,
-) -> impl Responder {
-    let create_album_with_cover = CreateAlbumWithCover::try_from(create_album_api).unwrap();
+) -> Result<HttpResponse, ServiceError> {
+    let create_album_with_cover = CreateAlbumWithCover::try_from(create_album_api)?;
 
-    let album_api = AlbumApi::from(app_state
+    let album = app_state
         .get_ref()
         .album_service()
         .create_album(&authenticated_user, &create_album_with_cover)
-        .await
-        .unwrap());  // TODO: error handling
+        .await?;
     
-    HttpResponse::Created().json(album_api)
+    Ok(HttpResponse::Created().json(AlbumApi::from(album)))
 }
 
 pub async fn get_album_by_id<AS: AlbumService>(
     authenticated_user: AuthenticatedUser,
     id: web::Path<Uuid>,
     app_state: web::Data<AlbumRoutesState<AS>>,
-) -> impl Responder {
-    app_state
+) -> Result<HttpResponse, ServiceError> {
+    let album = app_state
         .get_ref()
         .album_service()
         .get_album_by_id(&authenticated_user, &id.into_inner())
-        .await
-        .unwrap() // TODO: error handling
-        .map(|album| HttpResponse::Ok().json(AlbumApi::from(album)))
-        .unwrap_or(HttpResponse::NotFound().finish())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(version_entity_tag(album.version())))
+        .json(AlbumApi::from(album)))
+}
+
+pub async fn get_album_photos<AS: AlbumService>(
+    authenticated_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    page_query_api: web::Query<PageQueryApi>,
+    app_state: web::Data<AlbumRoutesState<AS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let page_request = PageRequest::try_from(page_query_api.into_inner())?;
+
+    let photos = app_state
+        .get_ref()
+        .album_service()
+        .get_album_photos(&authenticated_user, &id.into_inner(), &page_request)
+        .await?
+        .map::<PhotoApi>();
+
+    Ok(HttpResponse::Ok().json(photos))
 }
 
 pub async fn get_albums<AS: AlbumService>(
     authenticated_user: AuthenticatedUser,
+    page_query_api: web::Query<PageQueryApi>,
     app_state: web::Data<AlbumRoutesState<AS>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ServiceError> {
+    let page_request = PageRequest::try_from(page_query_api.into_inner())?;
+
     let albums = app_state
         .get_ref()
         .album_service()
-        .get_all_albums(&authenticated_user)
-        .await
-        .unwrap()
+        .get_all_albums(&authenticated_user, &page_request)
+        .await?
         .map::<AlbumApi>();
     
-    HttpResponse::Ok().json(albums)
+    Ok(HttpResponse::Ok().json(albums))
 }
 
 pub async fn patch_album<AS: AlbumService>(
     authenticated_user: AuthenticatedUser,
     album_id: web::Path<Uuid>,
     patch_album_api: web::Json<PatchAlbumApi>,
+    if_match: Option<web::Header<IfMatch>>,
     app_state: web::Data<AlbumRoutesState<AS>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ServiceError> {
+    let expected_version = ExpectedVersion::try_from(if_match)?;
+    let update_album = UpdateAlbum::try_from(album_id.into_inner(), patch_album_api.into_inner())?;
+
+    let album = app_state
+        .get_ref()
+        .album_service()
+        .update_album(&authenticated_user, &update_album, &expected_version)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(version_entity_tag(album.version())))
+        .json(AlbumApi::from(album)))
+}
+
+pub async fn delete_album<AS: AlbumService>(
+    authenticated_user: AuthenticatedUser,
+    album_id: web::Path<Uuid>,
+    delete_album_query_api: web::Query<DeleteAlbumQueryApi>,
+    if_match: Option<web::Header<IfMatch>>,
+    app_state: web::Data<AlbumRoutesState<AS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let expected_version = ExpectedVersion::try_from(if_match)?;
+
     app_state
         .get_ref()
         .album_service()
-        .update_album(&authenticated_user, &UpdateAlbum::from(album_id.into_inner(), patch_album_api.into_inner()))
-        .await
-        .map(|album| HttpResponse::Ok().json(AlbumApi::from(album)))
-        .unwrap_or(HttpResponse::NotFound().finish()) // TODO: error handling
-}
\ No newline at end of file
+        .delete_album(&authenticated_user, &DeleteAlbum::from(album_id.into_inner(), delete_album_query_api.into_inner()), &expected_version)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,35 +1,89 @@
-use actix_web::{HttpResponse, Responder, web};
-use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use std::time::SystemTime;
+
+use actix_web::{HttpResponse, HttpResponseBuilder, web};
+use actix_web::body::SizedStream;
+use actix_web::http::header::{self, Accept, CacheControl, CacheDirective, ContentDisposition, ContentRange, ContentRangeSpec, DispositionParam, DispositionType, ETag, EntityTag, LastModified, Range};
+use image::ImageFormat;
 use uuid::Uuid;
-use crate::models::api::ImageTransformOptionsApi;
+use crate::models::api::image::ImageTransformOptionsApi;
 
-use crate::models::service::image::ImageTransformOptions;
+use crate::models::service::image::{ByteRange, DownloadCondition, ImageDownload, ImageStream, ImageTransformOptions, ImageValidators};
+use crate::models::service::Visibility;
 use crate::security::auth::user::AuthenticatedUser;
-use crate::service::ImageService;
+use crate::service::{ImageService, ServiceError};
 use crate::setup::ImageRoutesState;
 
 pub const IMAGE_BY_ID_ROUTE: &'static str = "/images/{id}";
+/// In seconds, short enough for a public image turned private to leave the caches soon.
+const PUBLIC_IMAGE_MAX_AGE: u32 = 3600;
 
 pub async fn get_image_by_id<IS: ImageService>(
     authenticated_user: AuthenticatedUser,
     id: web::Path<Uuid>,
     convert_options: web::Query<ImageTransformOptionsApi>,
+    accept: Option<web::Header<Accept>>,
+    download_condition: DownloadCondition,
+    range: Option<web::Header<Range>>,
     app_state: web::Data<ImageRoutesState<IS>>,
-) -> impl Responder {
-    let convert_options = convert_options.into_inner();
-    app_state
+) -> Result<HttpResponse, ServiceError> {
+    let image_transform_options = ImageTransformOptions::try_from(convert_options.into_inner(), accept)?;
+    let image_download = app_state
         .get_ref()
         .image_service()
-        .get_image(&authenticated_user, &id.into_inner(), &ImageTransformOptions::from(convert_options))
-        .await
-        .unwrap() // TODO: error handling
-        .map(|image| HttpResponse::Ok()
-            .content_type(image.format().to_mime_type())
-            .insert_header(ContentDisposition {
-                disposition: DispositionType::Inline,
-                parameters: vec![DispositionParam::Filename(image.filename().to_string())],
-            })
-            .body(image.take_bytes())
-        )
-        .unwrap_or(HttpResponse::NotFound().finish())
-}
\ No newline at end of file
+        .get_image(&authenticated_user, &id.into_inner(), &image_transform_options, &download_condition, ByteRange::from(range))
+        .await?;
+
+    match image_download {
+        ImageDownload::NotModified(image_validators) => {
+            Ok(with_validators(HttpResponse::NotModified(), &image_validators).finish())
+        },
+        ImageDownload::Modified(image, image_validators) => {
+            let mut response = with_validators(HttpResponse::Ok(), &image_validators);
+            with_content(&mut response, image.format(), image.filename_with_extension());
+            Ok(response.body(image.take_bytes()))
+        },
+        ImageDownload::Original(image_stream, image_validators) => {
+            let mut response = with_validators(HttpResponse::Ok(), &image_validators);
+            response.insert_header((header::ACCEPT_RANGES, "bytes"));
+            Ok(stream_image(response, image_stream))
+        },
+        ImageDownload::PartialContent(image_stream, content_range, image_validators) => {
+            let mut response = with_validators(HttpResponse::PartialContent(), &image_validators);
+            response.insert_header(ContentRange(ContentRangeSpec::Bytes {
+                range: Some((content_range.first(), content_range.last())),
+                instance_length: Some(content_range.size()),
+            }));
+            Ok(stream_image(response, image_stream))
+        },
+    }
+}
+
+/// Sends the image as it is read from the storage, with its length known upfront.
+fn stream_image(mut response: HttpResponseBuilder, image_stream: ImageStream) -> HttpResponse {
+    with_content(&mut response, image_stream.format(), image_stream.filename_with_extension());
+    response.body(SizedStream::new(image_stream.content_length(), image_stream.into_bytes()))
+}
+
+fn with_content(response: &mut HttpResponseBuilder, image_format: ImageFormat, filename: String) {
+    response
+        .content_type(image_format.to_mime_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Inline,
+            parameters: vec![DispositionParam::Filename(filename)],
+        });
+}
+
+/// Public images may be stored by shared caches, private ones by no cache at all.
+fn with_validators(mut response: HttpResponseBuilder, image_validators: &ImageValidators) -> HttpResponseBuilder {
+    let cache_control = match image_validators.visibility() {
+        Visibility::Public => CacheControl(vec![CacheDirective::Public, CacheDirective::MaxAge(PUBLIC_IMAGE_MAX_AGE)]),
+        Visibility::Private => CacheControl(vec![CacheDirective::Private, CacheDirective::NoStore]),
+    };
+
+    response
+        .insert_header((header::VARY, "Accept"))
+        .insert_header(ETag(EntityTag::new_strong(image_validators.entity_tag().to_string())))
+        .insert_header(LastModified(SystemTime::from(image_validators.last_modified()).into()))
+        .insert_header(cache_control);
+    response
+}
@@ -0,0 +1,64 @@
+use std::fmt::Write;
+
+use actix_web::{HttpResponse, web};
+
+use crate::security::auth::user::AuthenticatedUser;
+use crate::security::authz::MetricsPolicyEnforcer;
+use crate::service::ServiceError;
+use crate::service::transform_executor::TransformExecutorMetrics;
+use crate::setup::MetricsRoutesState;
+
+pub const METRICS_ROUTE: &str = "/metrics";
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// The load of the image transformations, in the Prometheus text format.
+pub async fn get_metrics<MP: MetricsPolicyEnforcer>(
+    authenticated_user: AuthenticatedUser,
+    app_state: web::Data<MetricsRoutesState<MP>>,
+) -> Result<HttpResponse, ServiceError> {
+    let can_read_metrics = app_state
+        .get_ref()
+        .metrics_policy_enforcer()
+        .can_read_metrics(&authenticated_user)
+        .await
+        .map_err(ServiceError::UpstreamUnavailable)?;
+    if !can_read_metrics {
+        return Err(ServiceError::Forbidden("Unauthorized to read the metrics".to_string()));
+    }
+    let metrics = app_state.get_ref().transform_executor().metrics();
+
+    Ok(HttpResponse::Ok()
+        .content_type(PROMETHEUS_CONTENT_TYPE)
+        .body(render_metrics(&metrics)))
+}
+
+fn render_metrics(metrics: &TransformExecutorMetrics) -> String {
+    let samples = [
+        ("image_transform_concurrency", "gauge", "Image transformations allowed to run at once", metrics.concurrency as u64),
+        ("image_transform_queue_depth", "gauge", "Image transformations allowed to wait for their turn", metrics.queue_depth as u64),
+        ("image_transform_running", "gauge", "Image transformations running", metrics.running as u64),
+        ("image_transform_queued", "gauge", "Image transformations waiting for their turn", metrics.queued as u64),
+        ("image_transform_completed_total", "counter", "Image transformations completed", metrics.completed),
+        ("image_transform_rejected_total", "counter", "Image transformations refused while the queue was full", metrics.rejected),
+    ];
+
+    samples.iter().fold(String::new(), |mut body, (name, metric_type, help, value)| {
+        let _ = writeln!(body, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, metric_type, name, value);
+        body
+    })
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_render_the_metrics_in_the_prometheus_format() {
+        let metrics = TransformExecutorMetrics { concurrency: 4, queue_depth: 64, running: 2, queued: 1, completed: 10, rejected: 3 };
+
+        let body = render_metrics(&metrics);
+
+        assert!(body.contains("# TYPE image_transform_queued gauge\nimage_transform_queued 1\n"));
+        assert!(body.contains("# TYPE image_transform_rejected_total counter\nimage_transform_rejected_total 3\n"));
+    }
+}
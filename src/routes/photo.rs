@@ -1,79 +1,158 @@
 use actix_multipart::form::MultipartForm;
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpResponse, web};
+use actix_web::http::header::{ETag, IfMatch};
 use uuid::Uuid;
 
-use crate::models::api::photo::{PatchPhotoApi, PhotoApi};
+use crate::models::api::photo::{PatchPhotoApi, PhotoApi, PhotoQueryApi, PhotoSearchQueryApi, PhotoSearchResultApi};
 use crate::models::api::photo::UploadPhotoApi;
-use crate::models::service::photo::{UpdatePhoto, UploadPhoto};
-use crate::service::PhotoService;
+use crate::models::api::{version_entity_tag, PageQueryApi};
+use crate::models::service::ExpectedVersion;
+use crate::models::service::pagination::PageRequest;
+use crate::models::service::photo::{PhotoQuery, UpdatePhoto, UploadPhoto};
+use crate::service::{PhotoService, ServiceError};
 use crate::security::auth::user::AuthenticatedUser;
 use crate::setup::PhotoRoutesState;
 
 pub const PHOTOS_ROUTE: &'static str = "/photos";
 pub const PHOTO_BY_ID_ROUTE: &'static str = "/photos/{id}";
+pub const PHOTOS_SEARCH_ROUTE: &str = "/photos/search";
+pub const PHOTOS_TRASH_ROUTE: &str = "/photos/trash";
+pub const RESTORE_PHOTO_ROUTE: &str = "/photos/{id}/restore";
 
 pub async fn post_photos<PS: PhotoService>(
     authenticated_user: AuthenticatedUser,
     MultipartForm(upload_photo_api): MultipartForm<UploadPhotoApi>,
     app_state: web::Data<PhotoRoutesState<PS>>,
-) -> impl Responder {
-    let upload_photo = UploadPhoto::try_from(upload_photo_api).unwrap(); // TODO: error handling
+) -> Result<HttpResponse, ServiceError> {
+    let upload_photo = UploadPhoto::try_from(upload_photo_api)?;
 
-    app_state
+    let photo = app_state
         .get_ref()
         .photo_service()
         .create_photo(&authenticated_user, &upload_photo)
-        .await
-        .map(PhotoApi::from)
-        .map(|photo| HttpResponse::Created().json(photo))
-        .unwrap_or_else(|err| {
-            eprintln!("{}", err.to_string());
-            HttpResponse::InternalServerError().finish()
-        }) // TODO: error handling
+        .await?;
+
+    Ok(HttpResponse::Created().json(PhotoApi::from(photo)))
 }
 
 pub async fn get_photos<PS: PhotoService>(
     authenticated_user: AuthenticatedUser,
+    photo_query_api: web::Query<PhotoQueryApi>,
+    page_query_api: web::Query<PageQueryApi>,
     app_state: web::Data<PhotoRoutesState<PS>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ServiceError> {
+    let photo_query = PhotoQuery::try_from(photo_query_api.into_inner(), authenticated_user.id())?;
+    let page_request = PageRequest::try_from(page_query_api.into_inner())?;
 
     let photos = app_state
         .get_ref()
         .photo_service()
-        .get_all_photos(&authenticated_user)
-        .await
-        .unwrap() // TODO: error handling
+        .get_all_photos(&authenticated_user, &photo_query, &page_request)
+        .await?
         .map::<PhotoApi>();
     
-    HttpResponse::Ok().json(photos)
+    Ok(HttpResponse::Ok().json(photos))
+}
+
+pub async fn search_photos<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    photo_search_query_api: web::Query<PhotoSearchQueryApi>,
+    page_query_api: web::Query<PageQueryApi>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let page_request = PageRequest::try_from(page_query_api.into_inner())?;
+
+    let search_results = app_state
+        .get_ref()
+        .photo_service()
+        .search_photos(&authenticated_user, &photo_search_query_api.q, &page_request)
+        .await?
+        .map::<PhotoSearchResultApi>();
+
+    Ok(HttpResponse::Ok().json(search_results))
 }
 
 pub async fn get_photo_by_id<PS: PhotoService>(
     authenticated_user: AuthenticatedUser,
     id: web::Path<Uuid>,
     app_state: web::Data<PhotoRoutesState<PS>>,
-) -> impl Responder {
-    app_state
+) -> Result<HttpResponse, ServiceError> {
+    let photo = app_state
         .get_ref()
         .photo_service()
         .get_photo_by_id(&authenticated_user, &id.into_inner())
-        .await
-        .unwrap() // TODO: error handling
-        .map(|photo| HttpResponse::Ok().json(PhotoApi::from(photo)))
-        .unwrap_or(HttpResponse::NotFound().finish())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(version_entity_tag(photo.version())))
+        .json(PhotoApi::from(photo)))
 }
 
 pub async fn patch_photo<PS: PhotoService>(
     authenticated_user: AuthenticatedUser,
     photo_id: web::Path<Uuid>,
     patch_photo_api: web::Json<PatchPhotoApi>,
+    if_match: Option<web::Header<IfMatch>>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let expected_version = ExpectedVersion::try_from(if_match)?;
+    let update_photo = UpdatePhoto::try_from(photo_id.into_inner(), patch_photo_api.into_inner())?;
+
+    let photo = app_state
+        .get_ref()
+        .photo_service()
+        .update_photo(&authenticated_user, &update_photo, &expected_version)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ETag(version_entity_tag(photo.version())))
+        .json(PhotoApi::from(photo)))
+}
+
+pub async fn delete_photo<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    photo_id: web::Path<Uuid>,
+    if_match: Option<web::Header<IfMatch>>,
     app_state: web::Data<PhotoRoutesState<PS>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ServiceError> {
+    let expected_version = ExpectedVersion::try_from(if_match)?;
+
     app_state
         .get_ref()
         .photo_service()
-        .update_photo(&authenticated_user, &UpdatePhoto::from(photo_id.into_inner(), patch_photo_api.into_inner()))
-        .await
-        .map(|photo| HttpResponse::Ok().json(PhotoApi::from(photo)))
-        .unwrap_or(HttpResponse::NotFound().finish()) // TODO: error handling
-}
\ No newline at end of file
+        .delete_photo(&authenticated_user, &photo_id.into_inner(), &expected_version)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_deleted_photos<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    page_query_api: web::Query<PageQueryApi>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let page_request = PageRequest::try_from(page_query_api.into_inner())?;
+
+    let photos = app_state
+        .get_ref()
+        .photo_service()
+        .get_deleted_photos(&authenticated_user, &page_request)
+        .await?
+        .map::<PhotoApi>();
+
+    Ok(HttpResponse::Ok().json(photos))
+}
+
+pub async fn restore_photo<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    photo_id: web::Path<Uuid>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let photo = app_state
+        .get_ref()
+        .photo_service()
+        .restore_photo(&authenticated_user, &photo_id.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(PhotoApi::from(photo)))
+}
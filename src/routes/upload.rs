@@ -0,0 +1,42 @@
+use actix_web::{HttpResponse, web};
+use uuid::Uuid;
+
+use crate::models::api::photo::PhotoApi;
+use crate::models::api::upload::{CreatePendingUploadApi, PendingUploadApi};
+use crate::models::service::upload::CreatePendingUpload;
+use crate::security::auth::user::AuthenticatedUser;
+use crate::service::{PhotoService, ServiceError};
+use crate::setup::PhotoRoutesState;
+
+pub const UPLOADS_ROUTE: &str = "/uploads";
+pub const COMPLETE_UPLOAD_ROUTE: &str = "/uploads/{id}/complete";
+
+pub async fn post_uploads<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    create_pending_upload_api: web::Json<CreatePendingUploadApi>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let create_pending_upload = CreatePendingUpload::try_from(create_pending_upload_api.into_inner())?;
+
+    let presigned_upload = app_state
+        .get_ref()
+        .photo_service()
+        .create_pending_upload(&authenticated_user, &create_pending_upload)
+        .await?;
+
+    Ok(HttpResponse::Created().json(PendingUploadApi::from(presigned_upload)))
+}
+
+pub async fn complete_upload<PS: PhotoService>(
+    authenticated_user: AuthenticatedUser,
+    id: web::Path<Uuid>,
+    app_state: web::Data<PhotoRoutesState<PS>>,
+) -> Result<HttpResponse, ServiceError> {
+    let photo = app_state
+        .get_ref()
+        .photo_service()
+        .complete_pending_upload(&authenticated_user, &id.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Created().json(PhotoApi::from(photo)))
+}
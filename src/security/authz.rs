@@ -8,11 +8,13 @@ mod kc_authz_service;
 mod album;
 mod claims;
 mod image;
+mod metrics;
 
 pub use kc_authz_service::{AuthorizationScope, KcAuthzService};
 pub use photo::{PhotoPolicyEnforcerKc};
 pub use album::{AlbumPolicyEnforcerKc};
 pub use image::{ImagePolicyEnforcerKc};
+pub use metrics::{MetricsPolicyEnforcerKc};
 use crate::models::service::image::{ImageReference};
 
 #[async_trait()]
@@ -20,6 +22,7 @@ pub trait PhotoPolicyEnforcer: Send + Sync + 'static + Clone {
     async fn can_view_photo(&self, authenticated_user: &AuthenticatedUser, photo: &Photo) -> anyhow::Result<bool>;
     async fn can_create_photo(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool>;
     async fn can_edit_photo(&self, authenticated_user: &AuthenticatedUser, photo: &Photo, update_photo: &UpdatePhoto) -> anyhow::Result<bool>;
+    async fn can_delete_photo(&self, authenticated_user: &AuthenticatedUser, photo: &Photo) -> anyhow::Result<bool>;
     async fn filter_photos_by_view_permission<'a>(
         &self,
         authenticated_user: &AuthenticatedUser,
@@ -32,6 +35,7 @@ pub trait AlbumPolicyEnforcer: Send + Sync + 'static + Clone {
     async fn can_view_album(&self, authenticated_user: &AuthenticatedUser, album: &Album) -> anyhow::Result<bool>;
     async fn can_create_album(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool>;
     async fn can_edit_album(&self, authenticated_user: &AuthenticatedUser, album: &Album, update_album: &UpdateAlbum) -> anyhow::Result<bool>;
+    async fn can_delete_album(&self, authenticated_user: &AuthenticatedUser, album: &Album) -> anyhow::Result<bool>;
     async fn filter_albums_by_view_permission(
         &self,
         authenticated_user: &AuthenticatedUser,
@@ -46,4 +50,9 @@ pub trait ImagePolicyEnforcer: Send + Sync + 'static + Clone {
     async fn can_download_then_transform(&self, authenticated_user: &AuthenticatedUser, image_reference: &ImageReference) -> anyhow::Result<bool>;
     async fn can_create(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool>;
     async fn can_view(&self, authenticated_user: &AuthenticatedUser, image_reference: &ImageReference) -> anyhow::Result<bool>;
-}
\ No newline at end of file
+}
+
+#[async_trait()]
+pub trait MetricsPolicyEnforcer: Send + Sync + 'static + Clone {
+    async fn can_read_metrics(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool>;
+}
@@ -63,6 +63,20 @@ impl AlbumPolicyEnforcer for AlbumPolicyEnforcerKc {
         permission_request.decision_response_mode_send().await
     }
 
+    async fn can_delete_album(&self, authenticated_user: &AuthenticatedUser, album: &Album) -> anyhow::Result<bool> {
+        let resource_id = self.kc_authz_service.get_resource_id(routes::album::ALBUM_BY_ID_ROUTE).await?;
+
+        let album_claims = CommonClaims::resource_owner(&album.owner_user_id());
+        let permission_request = self.kc_authz_service.permission_request(
+            authenticated_user,
+            album_claims,
+            &resource_id,
+            &[AuthorizationScope::Delete],
+        );
+
+        permission_request.decision_response_mode_send().await
+    }
+
     async fn filter_albums_by_view_permission(
         &self,
         authenticated_user: &AuthenticatedUser,
@@ -113,6 +127,14 @@ impl AlbumPolicyEnforcerKc {
             authorization_scopes.push(AuthorizationScope::EditTitle);
         }
 
+        if update_album.description().is_some() {
+            authorization_scopes.push(AuthorizationScope::EditDescription);
+        }
+
+        if update_album.cover_photo_id().is_some() {
+            authorization_scopes.push(AuthorizationScope::ChangeCover);
+        }
+
         return authorization_scopes;
     }
     
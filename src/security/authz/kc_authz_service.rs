@@ -26,7 +26,13 @@ pub enum AuthorizationScope {
     Download,
     ChangeAlbum,
     ChangeVisibility,
+    ChangeCover,
     EditTitle,
+    EditDescription,
+    EditCategory,
+    EditTags,
+    Delete,
+    ReadMetrics,
 }
 
 impl Display for AuthorizationScope {
@@ -40,7 +46,13 @@ impl Display for AuthorizationScope {
             AuthorizationScope::Download => f.write_str("Download"),
             AuthorizationScope::ChangeAlbum => f.write_str("ChangeAlbum"),
             AuthorizationScope::ChangeVisibility => f.write_str("ChangeVisibility"),
+            AuthorizationScope::ChangeCover => f.write_str("ChangeCover"),
             AuthorizationScope::EditTitle => f.write_str("EditTitle"),
+            AuthorizationScope::EditDescription => f.write_str("EditDescription"),
+            AuthorizationScope::EditCategory => f.write_str("EditCategory"),
+            AuthorizationScope::EditTags => f.write_str("EditTags"),
+            AuthorizationScope::Delete => f.write_str("Delete"),
+            AuthorizationScope::ReadMetrics => f.write_str("ReadMetrics"),
         }
     }
 }
@@ -0,0 +1,32 @@
+use std::sync::Arc;
+use async_trait::async_trait;
+use crate::routes;
+use crate::security::auth::user::AuthenticatedUser;
+use crate::security::authz::{AuthorizationScope, KcAuthzService, MetricsPolicyEnforcer};
+
+#[derive(Clone)]
+pub struct MetricsPolicyEnforcerKc {
+    kc_authz_service: Arc<KcAuthzService>,
+}
+
+impl MetricsPolicyEnforcerKc {
+    pub fn new(kc_authz_service: Arc<KcAuthzService>) -> Self {
+        Self { kc_authz_service }
+    }
+}
+
+#[async_trait()]
+impl MetricsPolicyEnforcer for MetricsPolicyEnforcerKc {
+    async fn can_read_metrics(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool> {
+        let resource_id = self.kc_authz_service.get_resource_id(routes::metrics::METRICS_ROUTE).await?;
+
+        let permission_request = self.kc_authz_service.permission_request(
+            authenticated_user,
+            (),
+            &resource_id,
+            &[AuthorizationScope::ReadMetrics],
+        );
+
+        permission_request.decision_response_mode_send().await
+    }
+}
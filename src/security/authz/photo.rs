@@ -64,6 +64,20 @@ impl PhotoPolicyEnforcer for PhotoPolicyEnforcerKc {
         permission_request.decision_response_mode_send().await
     }
 
+    async fn can_delete_photo(&self, authenticated_user: &AuthenticatedUser, photo: &Photo) -> anyhow::Result<bool> {
+        let resource_id = self.kc_authz_service.get_resource_id(routes::photo::PHOTO_BY_ID_ROUTE).await?;
+
+        let photo_claims = CommonClaims::resource_owner(photo.owner_user_id());
+        let permission_request = self.kc_authz_service.permission_request(
+            authenticated_user,
+            photo_claims,
+            &resource_id,
+            &[AuthorizationScope::Delete],
+        );
+
+        permission_request.decision_response_mode_send().await
+    }
+
     async fn filter_photos_by_view_permission<'a>(
         &self,
         authenticated_user: &AuthenticatedUser,
@@ -118,6 +132,18 @@ impl PhotoPolicyEnforcerKc {
             authorization_scopes.push(AuthorizationScope::EditTitle);
         }
 
+        if update_photo.description().is_some() {
+            authorization_scopes.push(AuthorizationScope::EditDescription);
+        }
+
+        if update_photo.category().is_some() {
+            authorization_scopes.push(AuthorizationScope::EditCategory);
+        }
+
+        if update_photo.tags().is_some() {
+            authorization_scopes.push(AuthorizationScope::EditTags);
+        }
+
         return authorization_scopes;
     }
     
@@ -1,29 +1,48 @@
 use uuid::Uuid;
-use crate::models::service::album::{Album, CreateAlbumWithCover, UpdateAlbum};
-use crate::models::service::image::{ImageTransformOptions, Image};
-use crate::models::service::pagination::Page;
-use crate::models::service::photo::{Photo, UpdatePhoto, UploadPhoto};
+use crate::models::service::ExpectedVersion;
+use crate::models::service::album::{Album, CreateAlbumWithCover, DeleteAlbum, UpdateAlbum};
+use crate::models::service::image::{ByteRange, DownloadCondition, ImageDownload, ImageTransformOptions};
+use crate::models::service::pagination::{Page, PageRequest};
+use crate::models::service::photo::{Photo, PhotoQuery, PhotoSearchResult, UpdatePhoto, UploadPhoto};
+use crate::models::service::upload::{CreatePendingUpload, PresignedUpload};
 use crate::security::auth::user::AuthenticatedUser;
 
+pub use error::ServiceError;
+
 pub mod photo;
 pub mod album;
 pub(crate) mod image_storage;
 pub mod image;
+pub mod image_pipeline;
+pub mod rendition_cache;
+pub mod transform_executor;
+pub mod error;
+mod pagination;
 
 #[async_trait::async_trait]
 pub trait PhotoService: Clone + Send + Sync + 'static {
-    async fn get_all_photos(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<Page<Photo>>;
-    async fn get_photo_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> anyhow::Result<Option<Photo>>;
-    async fn create_photo(&self, authenticated_user: &AuthenticatedUser, upload_photo: &UploadPhoto) -> anyhow::Result<Photo>;
-    async fn update_photo(&self, authenticated_user: &AuthenticatedUser, update_photo: &UpdatePhoto) -> anyhow::Result<Photo>;
+    async fn get_all_photos(&self, authenticated_user: &AuthenticatedUser, photo_query: &PhotoQuery, page_request: &PageRequest) -> Result<Page<Photo>, ServiceError>;
+    async fn search_photos(&self, authenticated_user: &AuthenticatedUser, query: &str, page_request: &PageRequest) -> Result<Page<PhotoSearchResult>, ServiceError>;
+    async fn get_photo_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Photo, ServiceError>;
+    async fn create_photo(&self, authenticated_user: &AuthenticatedUser, upload_photo: &UploadPhoto) -> Result<Photo, ServiceError>;
+    async fn update_photo(&self, authenticated_user: &AuthenticatedUser, update_photo: &UpdatePhoto, expected_version: &ExpectedVersion) -> Result<Photo, ServiceError>;
+    async fn delete_photo(&self, authenticated_user: &AuthenticatedUser, id: &Uuid, expected_version: &ExpectedVersion) -> Result<(), ServiceError>;
+    async fn get_deleted_photos(&self, authenticated_user: &AuthenticatedUser, page_request: &PageRequest) -> Result<Page<Photo>, ServiceError>;
+    async fn restore_photo(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Photo, ServiceError>;
+    /// Records the photo and returns the URL its image is uploaded to, straight to the storage.
+    async fn create_pending_upload(&self, authenticated_user: &AuthenticatedUser, create_pending_upload: &CreatePendingUpload) -> Result<PresignedUpload, ServiceError>;
+    /// Creates the photo of a pending upload once its image has been uploaded.
+    async fn complete_pending_upload(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Photo, ServiceError>;
 }
 
 #[async_trait::async_trait]
 pub trait AlbumService: Clone + Send + Sync + 'static {
-    async fn get_all_albums(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<Page<Album>>;
-    async fn get_album_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> anyhow::Result<Option<Album>>;
-    async fn create_album(&self, authenticated_user: &AuthenticatedUser, create_album: &CreateAlbumWithCover) -> anyhow::Result<Album>; 
-    async fn update_album(&self, authenticated_user: &AuthenticatedUser, update_album: &UpdateAlbum) -> anyhow::Result<Album>;
+    async fn get_all_albums(&self, authenticated_user: &AuthenticatedUser, page_request: &PageRequest) -> Result<Page<Album>, ServiceError>;
+    async fn get_album_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Album, ServiceError>;
+    async fn get_album_photos(&self, authenticated_user: &AuthenticatedUser, id: &Uuid, page_request: &PageRequest) -> Result<Page<Photo>, ServiceError>;
+    async fn create_album(&self, authenticated_user: &AuthenticatedUser, create_album: &CreateAlbumWithCover) -> Result<Album, ServiceError>;
+    async fn update_album(&self, authenticated_user: &AuthenticatedUser, update_album: &UpdateAlbum, expected_version: &ExpectedVersion) -> Result<Album, ServiceError>;
+    async fn delete_album(&self, authenticated_user: &AuthenticatedUser, delete_album: &DeleteAlbum, expected_version: &ExpectedVersion) -> Result<(), ServiceError>;
 }
 
 #[async_trait::async_trait]
@@ -33,5 +52,7 @@ pub trait ImageService: Clone + Send + Sync + 'static {
         authenticated_user: &AuthenticatedUser,
         id: &Uuid,
         convert_options: &ImageTransformOptions,
-    ) -> anyhow::Result<Option<Image>>;
+        download_condition: &DownloadCondition,
+        byte_range: Option<ByteRange>,
+    ) -> Result<ImageDownload, ServiceError>;
 }
\ No newline at end of file
@@ -1,33 +1,98 @@
 use std::sync::Arc;
 use uuid::Uuid;
-use crate::models::service::album::{Album, CreateAlbum, CreateAlbumWithCover, UpdateAlbum};
-use crate::models::service::pagination::Page;
+use crate::models::service::album::{Album, CreateAlbum, CreateAlbumWithCover, DeleteAlbum, UpdateAlbum};
+use crate::models::service::ExpectedVersion;
+use crate::models::service::pagination::{Page, PageRequest};
+use crate::models::service::photo::{Photo, PhotoQuery};
 use crate::repository::album_repository::AlbumRepository;
+use crate::repository::photo_repository::PhotoRepository;
 use crate::security::auth::user::AuthenticatedUser;
-use crate::security::authz::AlbumPolicyEnforcer;
-use crate::service::AlbumService;
+use crate::security::authz::{AlbumPolicyEnforcer, PhotoPolicyEnforcer};
+use crate::service::{AlbumService, ServiceError};
 use crate::service::image::ImageReferenceUrlBuilder;
+use crate::service::image_pipeline::ImagePipeline;
 use crate::service::image_storage::ImageStorage;
+use crate::service::pagination::fetch_filtered_page;
+use crate::service::rendition_cache::RenditionCache;
 
 #[derive(Debug, Clone)]
-pub struct AlbumServiceImpl<R, I, P>
+pub struct AlbumServiceImpl<R, I, P, PR, PP, C>
     where
         R: AlbumRepository,
         I: ImageStorage,
         P: AlbumPolicyEnforcer,
+        PR: PhotoRepository,
+        PP: PhotoPolicyEnforcer,
+        C: RenditionCache,
 {
     album_repository: Arc<R>,
     image_repository: Arc<I>,
     album_policy_enforcer: Arc<P>,
     image_reference_url_builder: Arc<ImageReferenceUrlBuilder>,
+    photo_repository: Arc<PR>,
+    photo_policy_enforcer: Arc<PP>,
+    image_pipeline: Arc<ImagePipeline<C>>,
 }
 
-impl<R, I, P> AlbumServiceImpl<R, I, P> 
+impl<R, I, P, PR, PP, C> AlbumServiceImpl<R, I, P, PR, PP, C>
     where
         R: AlbumRepository,
         I: ImageStorage,
         P: AlbumPolicyEnforcer,
+        PR: PhotoRepository,
+        PP: PhotoPolicyEnforcer,
+        C: RenditionCache,
 {
+    fn check_version(album: &Album, expected_version: &ExpectedVersion) -> Result<(), ServiceError> {
+        if !expected_version.matches(album.version()) {
+            return Err(ServiceError::PreconditionFailed(format!("Album with id {} has been modified", album.id())));
+        }
+        Ok(())
+    }
+
+    /// A cover photo must be a photo of the album the user is allowed to see.
+    async fn check_cover_photo(&self, authenticated_user: &AuthenticatedUser, album: &Album, cover_photo_id: &Uuid) -> Result<(), ServiceError> {
+        let cover_photo = self.photo_repository
+            .find_photo_by_id(cover_photo_id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .map(Photo::from)
+            .filter(|photo| photo.album_id() == &Some(album.id()))
+            .ok_or_else(|| ServiceError::Validation(format!("Photo with id {} is not in the album", cover_photo_id)))?;
+
+        let can_view_photo = self.photo_policy_enforcer
+            .can_view_photo(authenticated_user, &cover_photo)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_view_photo {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to view photo with id {}", cover_photo_id)));
+        }
+
+        Ok(())
+    }
+
+    /// The renditions of the cover image of an album carry its visibility, they are dropped
+    /// once it changes or the cover image is replaced.
+    async fn invalidate_cover_renditions(&self, album: &Album) {
+        let cover_image_id = album.cover_image_id();
+        if let Err(err) = self.image_pipeline.rendition_cache().invalidate(&cover_image_id).await {
+            log::warn!("Album {} cover image {} renditions were not invalidated: {:#}", album.id(), cover_image_id, err);
+        }
+    }
+
+    /// Removes the former cover image of an album, unless it is shared with one of its photos.
+    async fn delete_cover_image(&self, album: &Album) {
+        let cover_image_id = album.cover_image_id();
+        self.invalidate_cover_renditions(album).await;
+        match self.album_repository.delete_cover_image_if_unused(&cover_image_id).await {
+            Ok(false) => {}
+            Ok(true) => if let Err(err) = self.image_repository.delete_image(&cover_image_id).await {
+                log::warn!("Album {} cover image {} is still in storage: {}", album.id(), cover_image_id, err);
+            },
+            Err(err) => log::warn!("Album {} cover image {} was not deleted: {}", album.id(), cover_image_id, err),
+        }
+    }
+
     pub fn album_repository(&self) -> Arc<R> {
         self.album_repository.clone()
     }
@@ -36,62 +101,131 @@ impl<R, I, P> AlbumServiceImpl<R, I, P>
         image_repository: Arc<I>, 
         album_policy_enforcer: Arc<P>,
         image_reference_url_builder: Arc<ImageReferenceUrlBuilder>,
+        photo_repository: Arc<PR>,
+        photo_policy_enforcer: Arc<PP>,
+        image_pipeline: Arc<ImagePipeline<C>>,
     ) -> Self {
-        Self { album_repository, image_repository, album_policy_enforcer, image_reference_url_builder }
+        Self {
+            album_repository,
+            image_repository,
+            album_policy_enforcer,
+            image_reference_url_builder,
+            photo_repository,
+            photo_policy_enforcer,
+            image_pipeline,
+        }
+    }
+
+    async fn find_album_by_id(&self, id: &Uuid) -> Result<Album, ServiceError> {
+        self.album_repository
+            .find_album_by_id(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .map(Album::from)
+            .ok_or_else(|| ServiceError::NotFound(format!("Album with id {} not found", id)))
     }
 }
 
 #[async_trait::async_trait]
-impl<R, I, P> AlbumService for AlbumServiceImpl<R, I, P>
+impl<R, I, P, PR, PP, C> AlbumService for AlbumServiceImpl<R, I, P, PR, PP, C>
     where
         R: AlbumRepository,
         I: ImageStorage,
         P: AlbumPolicyEnforcer,
+        PR: PhotoRepository,
+        PP: PhotoPolicyEnforcer,
+        C: RenditionCache,
 {
-    async fn get_all_albums(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<Page<Album>> {
-        let albums = self.album_repository()
-            .find_all_albums()
-            .await?
-            .into_iter()
-            .map(Album::from)
-            .collect::<Vec<_>>();
-
-        let albums = self.album_policy_enforcer.filter_albums_by_view_permission(authenticated_user, albums).await?;
-
-        let tot_albums = albums.len();
-        Ok(Page::new(albums, 0, tot_albums as u32))
+    async fn get_all_albums(&self, authenticated_user: &AuthenticatedUser, page_request: &PageRequest) -> Result<Page<Album>, ServiceError> {
+        fetch_filtered_page(
+            page_request,
+            |limit, cursor| async move {
+                Ok(self.album_repository
+                    .find_all_albums(limit, cursor.as_ref())
+                    .await
+                    .map_err(ServiceError::Storage)?
+                    .into_iter()
+                    .map(Album::from)
+                    .collect())
+            },
+            |albums| async move {
+                self.album_policy_enforcer
+                    .filter_albums_by_view_permission(authenticated_user, albums)
+                    .await
+                    .map_err(ServiceError::UpstreamUnavailable)
+            },
+        ).await
     }
 
-    async fn get_album_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> anyhow::Result<Option<Album>> {
-        let album_option = self
-            .album_repository
-            .find_album_by_id(id)
-            .await?
-            .map(Album::from);
+    async fn get_album_by_id(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Album, ServiceError> {
+        let album = self.find_album_by_id(id).await?;
 
-        if let Some(photo) = &album_option {
-            let can_view_album = self.album_policy_enforcer.can_view_album(authenticated_user, photo).await?;
-            if !can_view_album {
-                return Err(anyhow::anyhow!("Unauthorized to view album with id: {}", id).into()); // TODO: Error Handling
-            }
+        let can_view_album = self.album_policy_enforcer
+            .can_view_album(authenticated_user, &album)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_view_album {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to view album with id {}", id)));
         }
 
-        Ok(album_option)
+        Ok(album)
+    }
+
+    async fn get_album_photos(
+        &self,
+        authenticated_user: &AuthenticatedUser,
+        id: &Uuid,
+        page_request: &PageRequest
+    ) -> Result<Page<Photo>, ServiceError> {
+        let album = self.get_album_by_id(authenticated_user, id).await?;
+        let album_photo_query = PhotoQuery::for_album(&album.id());
+
+        fetch_filtered_page(
+            page_request,
+            |limit, cursor| {
+                let album_photo_query = &album_photo_query;
+                async move {
+                    Ok(self.photo_repository
+                        .find_all_photos(album_photo_query, limit, cursor.as_ref())
+                        .await
+                        .map_err(ServiceError::Storage)?
+                        .into_iter()
+                        .map(Photo::from)
+                        .collect())
+                }
+            },
+            |photos| async move {
+                self.photo_policy_enforcer
+                    .filter_photos_by_view_permission(authenticated_user, photos)
+                    .await
+                    .map_err(ServiceError::UpstreamUnavailable)
+            },
+        ).await
     }
 
     async fn create_album(
         &self,
         authenticated_user: &AuthenticatedUser,
         create_album_with_cover: &CreateAlbumWithCover
-    ) -> anyhow::Result<Album> {
-        let can_create_album = self.album_policy_enforcer.can_create_album(authenticated_user).await?;
+    ) -> Result<Album, ServiceError> {
+        let can_create_album = self.album_policy_enforcer
+            .can_create_album(authenticated_user)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
         if !can_create_album {
-            return Err(anyhow::anyhow!("Unauthorized to create album")); // TODO: Error Handling
+            return Err(ServiceError::Forbidden("Unauthorized to create an album".to_string()));
         }
         
         let upload_cover_image = create_album_with_cover.upload_image();
-        let (created_cover_image_id, created_cover_image_url) = self.image_repository.upload_image(upload_cover_image).await?;
+        let (dyn_image, cover_image_properties) = self.image_pipeline.validate(upload_cover_image).await?;
+        let (created_cover_image_id, created_cover_image_url) = self.image_repository
+            .upload_image(upload_cover_image, cover_image_properties.sha256())
+            .await
+            .map_err(ServiceError::Storage)?;
         let cover_image_reference_url = self.image_reference_url_builder.build(&created_cover_image_id);
+        let cover_image_renditions = self.image_pipeline
+            .upload_renditions(self.image_repository.as_ref(), &created_cover_image_id, upload_cover_image.filename(), upload_cover_image.visibility(), dyn_image)
+            .await;
         
         let create_album = CreateAlbum::new(
             create_album_with_cover.title().to_string(),
@@ -103,34 +237,102 @@ impl<R, I, P> AlbumService for AlbumServiceImpl<R, I, P>
             cover_image_reference_url,
             upload_cover_image.size() as u64,
             upload_cover_image.format(),
-        );
+        ).with_cover_image_renditions(cover_image_renditions)
+            .with_cover_image_properties(cover_image_properties);
         
         self.album_repository()
             .create_album(&create_album)
             .await
             .map(Album::from)
+            .map_err(ServiceError::Storage)
     }
 
     async fn update_album(
         &self, 
         authenticated_user: &AuthenticatedUser, 
-        update_album: &UpdateAlbum
-    ) -> anyhow::Result<Album> {
+        update_album: &UpdateAlbum,
+        expected_version: &ExpectedVersion,
+    ) -> Result<Album, ServiceError> {
         let album_id = update_album.id();
-        let album = self.album_repository
-            .find_album_by_id(album_id)
-            .await?
-            .map(Album::from)
-            .ok_or(anyhow::anyhow!("Not found"))?;
+        if update_album.is_empty() {
+            let album = self.get_album_by_id(authenticated_user, album_id).await?;
+            Self::check_version(&album, expected_version)?;
+            return Ok(album);
+        }
+        let album = self.find_album_by_id(album_id).await?;
         
-        let can_edit_album = self.album_policy_enforcer.can_edit_album(authenticated_user, &album, update_album).await?;
+        let can_edit_album = self.album_policy_enforcer
+            .can_edit_album(authenticated_user, &album, update_album)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
         if !can_edit_album {
-            return Err(anyhow::anyhow!("Unauthorized to edit album with id {}", update_album.id()).into()); // TODO: Error Handling
+            return Err(ServiceError::Forbidden(format!("Unauthorized to edit album with id {}", album_id)));
+        }
+        Self::check_version(&album, expected_version)?;
+
+        if let Some(cover_photo_id) = update_album.cover_photo_id() {
+            self.check_cover_photo(authenticated_user, &album, cover_photo_id).await?;
         }
         
-        self.album_repository
-            .update_album(update_album)
+        let updated_album = self.album_repository
+            .update_album(update_album, expected_version)
             .await
-            .map(Album::from)
+            .map_err(ServiceError::Storage)?
+            .map(Album::from);
+        let Some(updated_album) = updated_album else {
+            // The conditional update matched no row, the album may have changed in the meantime
+            let current_album = self.find_album_by_id(album_id).await?;
+            Self::check_version(&current_album, expected_version)?;
+            return Err(match update_album.cover_photo_id() {
+                Some(cover_photo_id) => ServiceError::Validation(format!("Photo with id {} is not in the album", cover_photo_id)),
+                None => ServiceError::NotFound(format!("Album with id {} not found", album_id)),
+            });
+        };
+
+        if updated_album.cover_image_id() != album.cover_image_id() {
+            self.delete_cover_image(&album).await;
+        } else if update_album.visibility().is_some() {
+            self.invalidate_cover_renditions(&album).await;
+        }
+
+        Ok(updated_album)
+    }
+
+    async fn delete_album(
+        &self,
+        authenticated_user: &AuthenticatedUser,
+        delete_album: &DeleteAlbum,
+        expected_version: &ExpectedVersion,
+    ) -> Result<(), ServiceError> {
+        let album_id = delete_album.id();
+        let album = self.find_album_by_id(album_id).await?;
+
+        let can_delete_album = self.album_policy_enforcer
+            .can_delete_album(authenticated_user, &album)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_delete_album {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to delete album with id {}", album_id)));
+        }
+        Self::check_version(&album, expected_version)?;
+
+        let Some(deleted_album) = self.album_repository
+            .delete_album(delete_album, expected_version)
+            .await
+            .map_err(ServiceError::Storage)? else {
+            let current_album = self.find_album_by_id(album_id).await?;
+            Self::check_version(&current_album, expected_version)?;
+            return Err(ServiceError::NotFound(format!("Album with id {} not found", album_id)));
+        };
+
+        // The photos moved to the trash are no longer served, neither are their renditions
+        for image_id in &deleted_album.deleted_photo_image_ids {
+            if let Err(err) = self.image_pipeline.rendition_cache().invalidate(image_id).await {
+                log::warn!("Album {} photo image {} renditions were not invalidated: {:#}", album_id, image_id, err);
+            }
+        }
+        self.delete_cover_image(&album).await;
+
+        Ok(())
     }
 }
@@ -0,0 +1,279 @@
+use std::fmt;
+
+use actix_multipart::MultipartError;
+use actix_web::error::PayloadError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+
+use crate::models::api::{MissingIfMatchError, NotNullableFieldError};
+use crate::models::service::image::{InvalidImageTransformationError, UploadImageError};
+use crate::models::service::pagination::InvalidCursorError;
+use crate::models::service::photo::InvalidPhotoQueryError;
+
+#[derive(Debug)]
+pub enum ServiceError {
+    NotFound(String),
+    Forbidden(String),
+    Validation(String),
+    Conflict(String),
+    PayloadTooLarge(String),
+    Unprocessable(String),
+    PreconditionFailed(String),
+    PreconditionRequired(String),
+    /// Holds the size of the image, reported in the `Content-Range` of the response.
+    RangeNotSatisfiable(u64),
+    /// Holds the seconds after which the request can be retried, sent in the `Retry-After` of
+    /// the response.
+    Overloaded(u64),
+    UpstreamUnavailable(anyhow::Error),
+    /// The stored image no longer matches the digest recorded at upload.
+    IntegrityViolation(String),
+    Storage(anyhow::Error),
+}
+
+impl ServiceError {
+    const PROBLEM_JSON_CONTENT_TYPE: &'static str = "application/problem+json";
+    const PROBLEM_TYPE: &'static str = "about:blank";
+
+    fn detail(&self) -> String {
+        match self {
+            ServiceError::NotFound(detail)
+            | ServiceError::Forbidden(detail)
+            | ServiceError::Validation(detail)
+            | ServiceError::Conflict(detail)
+            | ServiceError::PayloadTooLarge(detail)
+            | ServiceError::Unprocessable(detail)
+            | ServiceError::PreconditionFailed(detail)
+            | ServiceError::PreconditionRequired(detail) => detail.clone(),
+            ServiceError::RangeNotSatisfiable(size) => format!("The range is outside of the {} bytes of the image", size),
+            ServiceError::Overloaded(_) => "The server is too busy to process the image, retry later".to_string(),
+            ServiceError::UpstreamUnavailable(_) => "A service required to fulfill the request is unavailable".to_string(),
+            ServiceError::IntegrityViolation(_) => "The stored image does not match the uploaded image".to_string(),
+            ServiceError::Storage(_) => "Unable to access the storage".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::NotFound(detail) => write!(f, "Not found: {}", detail),
+            ServiceError::Forbidden(detail) => write!(f, "Forbidden: {}", detail),
+            ServiceError::Validation(detail) => write!(f, "Validation failed: {}", detail),
+            ServiceError::Conflict(detail) => write!(f, "Conflict: {}", detail),
+            ServiceError::PayloadTooLarge(detail) => write!(f, "Payload too large: {}", detail),
+            ServiceError::Unprocessable(detail) => write!(f, "Unprocessable: {}", detail),
+            ServiceError::PreconditionFailed(detail) => write!(f, "Precondition failed: {}", detail),
+            ServiceError::PreconditionRequired(detail) => write!(f, "Precondition required: {}", detail),
+            ServiceError::RangeNotSatisfiable(size) => write!(f, "Range not satisfiable: image of {} bytes", size),
+            ServiceError::Overloaded(retry_after) => write!(f, "Overloaded: retry after {} seconds", retry_after),
+            ServiceError::UpstreamUnavailable(err) => write!(f, "Upstream unavailable: {:#}", err),
+            ServiceError::IntegrityViolation(detail) => write!(f, "Integrity violation: {}", detail),
+            ServiceError::Storage(err) => write!(f, "Storage error: {:#}", err),
+        }
+    }
+}
+
+impl std::error::Error for ServiceError {}
+
+impl ResponseError for ServiceError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ServiceError::NotFound(_) => StatusCode::NOT_FOUND,
+            ServiceError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ServiceError::Validation(_) => StatusCode::BAD_REQUEST,
+            ServiceError::Conflict(_) => StatusCode::CONFLICT,
+            ServiceError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ServiceError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ServiceError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            ServiceError::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            ServiceError::RangeNotSatisfiable(_) => StatusCode::RANGE_NOT_SATISFIABLE,
+            ServiceError::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::UpstreamUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ServiceError::IntegrityViolation(_) => StatusCode::BAD_GATEWAY,
+            ServiceError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status_code = self.status_code();
+        if status_code.is_server_error() {
+            log::error!("{}", self);
+        }
+
+        let problem_details = ProblemDetails {
+            problem_type: Self::PROBLEM_TYPE,
+            title: status_code.canonical_reason().unwrap_or_default(),
+            status: status_code.as_u16(),
+            detail: self.detail(),
+        };
+
+        let mut response = HttpResponse::build(status_code);
+        match self {
+            ServiceError::RangeNotSatisfiable(size) => {
+                response.insert_header((header::CONTENT_RANGE, format!("bytes */{}", size)));
+            },
+            ServiceError::Overloaded(retry_after) => {
+                response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+            },
+            _ => {}
+        }
+
+        response
+            .content_type(Self::PROBLEM_JSON_CONTENT_TYPE)
+            .json(problem_details)
+    }
+}
+
+/// Problem Details for HTTP APIs (RFC 7807)
+#[derive(Debug, Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: String,
+}
+
+impl From<UploadImageError> for ServiceError {
+    fn from(upload_image_error: UploadImageError) -> Self {
+        let detail = match upload_image_error {
+            UploadImageError::MissingContentType => "The uploaded file has no content type",
+            UploadImageError::BadContentType => "The uploaded file is not an image",
+            UploadImageError::UnsupportedMimeType => "The uploaded image format is not supported",
+            UploadImageError::MismatchedFormat => "The uploaded image does not match its content type",
+            UploadImageError::CorruptedImage => "The uploaded image is corrupted",
+            UploadImageError::InvalidAlbum => "The album id is not a valid UUID",
+        };
+
+        ServiceError::Validation(detail.to_string())
+    }
+}
+
+/// The forms exceeding the size limit of their fields overflow their payload, any other
+/// malformed form is invalid.
+impl From<MultipartError> for ServiceError {
+    fn from(multipart_error: MultipartError) -> Self {
+        match multipart_error {
+            MultipartError::Payload(PayloadError::Overflow) => ServiceError::PayloadTooLarge("The uploaded form exceeds the size limit".to_string()),
+            MultipartError::Field { name, source } if source.as_response_error().status_code() == StatusCode::PAYLOAD_TOO_LARGE => {
+                ServiceError::PayloadTooLarge(format!("The field '{}' exceeds the size limit", name))
+            },
+            multipart_error => ServiceError::Validation(multipart_error.to_string()),
+        }
+    }
+}
+
+impl From<InvalidCursorError> for ServiceError {
+    fn from(_: InvalidCursorError) -> Self {
+        ServiceError::Validation("The cursor is not valid".to_string())
+    }
+}
+
+impl From<InvalidPhotoQueryError> for ServiceError {
+    fn from(invalid_photo_query_error: InvalidPhotoQueryError) -> Self {
+        let detail = match invalid_photo_query_error {
+            InvalidPhotoQueryError::InvalidAlbum => "The album_id filter is not a valid UUID",
+            InvalidPhotoQueryError::InvalidVisibility => "The visibility filter must be either public or private",
+            InvalidPhotoQueryError::InvalidCreatedAfter => "The created_after filter is not a valid date",
+            InvalidPhotoQueryError::InvalidCreatedBefore => "The created_before filter is not a valid date",
+            InvalidPhotoQueryError::InvalidDateRange => "The created_after filter must precede created_before",
+            InvalidPhotoQueryError::InvalidOwner => "The owner filter only supports 'me'",
+        };
+
+        ServiceError::Validation(detail.to_string())
+    }
+}
+
+impl From<NotNullableFieldError> for ServiceError {
+    fn from(NotNullableFieldError(field): NotNullableFieldError) -> Self {
+        ServiceError::Validation(format!("The field '{}' cannot be null", field))
+    }
+}
+
+impl From<MissingIfMatchError> for ServiceError {
+    fn from(_: MissingIfMatchError) -> Self {
+        ServiceError::PreconditionRequired("The If-Match header with the ETag of the resource is required".to_string())
+    }
+}
+
+impl From<InvalidImageTransformationError> for ServiceError {
+    fn from(invalid_image_transformation_error: InvalidImageTransformationError) -> Self {
+        let detail = match invalid_image_transformation_error {
+            InvalidImageTransformationError::TooManyOperations(max) => format!("At most {} operations can be applied", max),
+            InvalidImageTransformationError::UnknownOperation(name) => format!("The operation '{}' is not supported", name),
+            InvalidImageTransformationError::InvalidParameters(usage) => format!("Invalid parameters, expected {}", usage),
+            InvalidImageTransformationError::CropOutOfBounds => "The crop area exceeds the bounds of the image".to_string(),
+            InvalidImageTransformationError::RenditionSizeWithOptions => "The size of a rendition cannot be combined with other options".to_string(),
+            InvalidImageTransformationError::UnsupportedEncoding(setting) => format!("The {} is not supported", setting),
+        };
+
+        ServiceError::Validation(detail)
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use actix_web::body::to_bytes;
+    use actix_web::http::header;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn should_render_problem_json() {
+        let response = ServiceError::Forbidden("Unauthorized to view photo".to_string()).error_response();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "application/problem+json");
+
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let problem_details: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem_details["type"], "about:blank");
+        assert_eq!(problem_details["title"], "Forbidden");
+        assert_eq!(problem_details["status"], 403);
+        assert_eq!(problem_details["detail"], "Unauthorized to view photo");
+    }
+
+    #[actix_web::test]
+    async fn should_not_leak_upstream_errors() {
+        let response = ServiceError::UpstreamUnavailable(anyhow::anyhow!("connection refused: keycloak:8080")).error_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = to_bytes(response.into_body()).await.unwrap();
+        let problem_details: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(!problem_details["detail"].as_str().unwrap().contains("keycloak"));
+    }
+
+    #[actix_web::test]
+    async fn should_report_the_size_of_an_unsatisfiable_range() {
+        let response = ServiceError::RangeNotSatisfiable(1024).error_response();
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers().get(header::CONTENT_RANGE).unwrap(), "bytes */1024");
+    }
+
+    #[actix_web::test]
+    async fn should_tell_when_to_retry_once_overloaded() {
+        let response = ServiceError::Overloaded(5).error_response();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "5");
+    }
+
+    #[actix_web::test]
+    async fn should_answer_payload_too_large_for_an_oversized_form() {
+        let response = ServiceError::from(MultipartError::Payload(PayloadError::Overflow)).error_response();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(ServiceError::from(MultipartError::Incomplete).status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn should_answer_bad_gateway_for_an_altered_image() {
+        let response = ServiceError::IntegrityViolation("The image does not match its digest".to_string()).error_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -1,32 +1,53 @@
-use std::io::Cursor;
+use std::fs::File;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek};
 use std::sync::Arc;
-use image::{ImageFormat, ImageReader};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{self, CompressionType, PngEncoder};
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::error::{LimitError, LimitErrorKind};
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageEncoder, ImageError, ImageFormat, ImageReader, ImageResult, Limits};
+use sha2::{Digest, Sha256};
 use url::Url;
 use uuid::Uuid;
-use crate::models::service::image::{ImageTransformOptions, Image, ImageTransformation, ImageReference};
+use crate::models::service::image::{ByteRange, ColorType, ContentHash, DownloadCondition, Flip, ImageEncodingOptions, ImageTransformOptions, Image, ImageTransformation, ImageReference, ImageRendition, ImageDownload, ImageProperties, ImageStream, ImageValidators, InvalidImageTransformationError, RenditionSize, ResizeFit, Rotation, UploadImage, UploadImageError};
+use crate::models::service::Visibility;
 use crate::repository::image_reference_repository::ImageReferenceRepository;
 use crate::security::auth::user::AuthenticatedUser;
 use crate::security::authz::ImagePolicyEnforcer;
 use crate::service::image_storage::ImageStorage;
-use crate::service::ImageService;
+use crate::service::rendition_cache::RenditionCache;
+use crate::service::transform_executor::TransformExecutor;
+use crate::service::{ImageService, ServiceError};
 
 #[derive(Debug, Clone)]
-pub struct ImageServiceImpl<IR, IU, IP>
+pub struct ImageServiceImpl<IR, IU, IP, RC>
     where
         IR: ImageReferenceRepository,
         IU: ImageStorage,
         IP: ImagePolicyEnforcer,
+        RC: RenditionCache,
 {
     image_reference_repository: Arc<IR>,
     image_uploader: Arc<IU>,
     image_policy_enforcer: Arc<IP>,
+    rendition_cache: Arc<RC>,
+    rendition_generator: Arc<RenditionGenerator>,
+    decode_limits: Arc<DecodeLimits>,
+    transform_executor: Arc<TransformExecutor>,
 }
 
-impl<IR, IU, IP> ImageServiceImpl<IR, IU, IP>
+impl<IR, IU, IP, RC> ImageServiceImpl<IR, IU, IP, RC>
     where
         IR: ImageReferenceRepository,
         IU: ImageStorage,
         IP: ImagePolicyEnforcer,
+        RC: RenditionCache,
 {
     pub fn image_reference_repository(&self) -> Arc<IR> {
         self.image_reference_repository.clone()
@@ -36,69 +57,746 @@ impl<IR, IU, IP> ImageServiceImpl<IR, IU, IP>
         self.image_uploader.clone()
     }
 
-    pub fn new(image_reference_repository: Arc<IR>, image_uploader: Arc<IU>, image_policy_enforcer: Arc<IP>) -> Self {
-        Self { image_reference_repository, image_uploader, image_policy_enforcer }
+    pub fn new(
+        image_reference_repository: Arc<IR>,
+        image_uploader: Arc<IU>,
+        image_policy_enforcer: Arc<IP>,
+        rendition_cache: Arc<RC>,
+        rendition_generator: Arc<RenditionGenerator>,
+        decode_limits: Arc<DecodeLimits>,
+        transform_executor: Arc<TransformExecutor>,
+    ) -> Self {
+        Self {
+            image_reference_repository,
+            image_uploader,
+            image_policy_enforcer,
+            rendition_cache,
+            rendition_generator,
+            decode_limits,
+            transform_executor,
+        }
+    }
+
+    // A missing rendition, e.g. of an image uploaded before the renditions, is rendered and stored
+    async fn get_rendition(&self, image_reference: &ImageReference, rendition_size: RenditionSize) -> Result<Image, ServiceError> {
+        let id = image_reference.id();
+        if self.rendition_generator.long_edge(rendition_size).is_none() {
+            return Err(ServiceError::NotFound(format!("Rendition {} of image with id {} is not available", rendition_size.as_str(), id)));
+        }
+
+        let image_rendition = self.image_reference_repository
+            .find_image_rendition(id, rendition_size)
+            .await
+            .map_err(ServiceError::Storage)?;
+        if image_rendition.is_some() {
+            let rendition = self.image_uploader
+                .download_rendition(id, rendition_size)
+                .await
+                .map_err(ServiceError::Storage)?;
+            match rendition {
+                Some(rendition) => return Ok(rendition),
+                None => log::warn!("Rendition {} of image {} not found in storage", rendition_size.as_str(), id),
+            }
+        }
+
+        let image = self.image_uploader
+            .download_image(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found in storage", id)))?;
+        let rendition_generator = Arc::clone(&self.rendition_generator);
+        let decode_limits = Arc::clone(&self.decode_limits);
+        let expected_sha256 = image_reference.properties().sha256().copied();
+        let (image_rendition, rendition) = self.transform_executor
+            .execute(move || {
+                verify_integrity(&image.id(), expected_sha256.as_ref(), &Sha256::digest(image.bytes()).into())?;
+                rendition_generator.render(&image, rendition_size, &decode_limits)
+            })
+            .await?
+            .ok_or_else(|| ServiceError::NotFound(format!("Rendition {} of image with id {} is not available", rendition_size.as_str(), id)))?;
+        self.store_rendition(id, &image_rendition, &rendition).await;
+
+        Ok(rendition)
+    }
+
+    async fn store_rendition(&self, image_id: &Uuid, image_rendition: &ImageRendition, rendition: &Image) {
+        let stored_rendition = self.image_uploader
+            .upload_rendition(image_id, image_rendition.size(), rendition)
+            .await;
+        let saved_rendition = match stored_rendition {
+            Ok(()) => self.image_reference_repository.save_image_rendition(image_id, image_rendition).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = saved_rendition {
+            log::warn!("Rendition {} of image {} was not stored: {:#}", image_rendition.size().as_str(), image_id, err);
+        }
     }
     
-    fn transform_image(image: Image, image_transform_options: &ImageTransformOptions) -> anyhow::Result<Image> {
-        let mut dyn_image = ImageReader::new(Cursor::new(image.bytes()))
-            .with_guessed_format()?
-            .decode()?;
-        let mut image_format = image.format(); // TODO: add convert to transformation
+    const ORIGINAL_RENDITION_KEY: &'static str = "original";
+
+    // From 1 (slowest) to 10 (fastest)
+    const AVIF_ENCODER_SPEED: u8 = 4;
+    const DEFAULT_AVIF_QUALITY: u8 = 80;
+
+    fn transform_image(image: Image, image_transform_options: &ImageTransformOptions, decode_limits: &DecodeLimits) -> Result<Image, ServiceError> {
+        let image_format = image_transform_options.output_format(image.format());
+        let encoding_options = image_transform_options.encoding();
+        if image_format == ImageFormat::WebP && encoding_options.quality().is_some() {
+            return Err(InvalidImageTransformationError::UnsupportedEncoding("quality of the lossless WebP encoder").into());
+        }
+        if !image_transform_options.modifies(image.format()) {
+            return Ok(image);
+        }
+
+        let (mut dyn_image, metadata) = Self::decode(image.bytes(), encoding_options.strip_metadata(), decode_limits)
+            .map_err(|err| decode_limits.stored_image_error(err))?;
+        let transformations = image_transform_options.transformations();
+        Self::check_transformed_dimensions(&dyn_image, &transformations, decode_limits)?;
         
-        image_transform_options.transformations()
-            .into_iter()
-            .for_each(|transformation| match transformation {
-                ImageTransformation::HueRotate(huerotate) => dyn_image = dyn_image.huerotate(huerotate),
-                ImageTransformation::Thumbnail(nwidth, nheigth) => dyn_image = dyn_image.thumbnail(nwidth, nheigth),
-                ImageTransformation::None => {}
-            });
+        for transformation in transformations {
+            dyn_image = Self::apply_transformation(dyn_image, transformation)?;
+        }
+        let dyn_image = to_encodable_color(dyn_image, image_format);
         
         let mut image_bytes = Vec::with_capacity(image.bytes().len());
+        Self::encode(&dyn_image, &mut image_bytes, image_format, encoding_options, metadata)
+            .map_err(|err| ServiceError::Storage(err.into()))?;
         let image_size = image_bytes.len();
-        dyn_image.write_to(&mut Cursor::new(&mut image_bytes), ImageFormat::Png)?;
         
         Ok(Image::new(&image.id(), image.filename(), &image_format, &image.visibility(), image_bytes, image_size as u32))
     }
+
+    fn decode(bytes: &[u8], strip_metadata: bool, decode_limits: &DecodeLimits) -> ImageResult<(DynamicImage, ImageMetadata)> {
+        let image_reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+        let mut decoder = decode_limits.decoder(image_reader)?;
+        let metadata = if strip_metadata {
+            ImageMetadata::default()
+        } else {
+            ImageMetadata { icc_profile: decoder.icc_profile()?, exif: decoder.exif_metadata()? }
+        };
+
+        Ok((DynamicImage::from_decoder(decoder)?, metadata))
+    }
+
+    // The WebP encoder is lossless, `transform_image` refuses a quality
+    fn encode(
+        dyn_image: &DynamicImage,
+        image_bytes: &mut Vec<u8>,
+        image_format: ImageFormat,
+        encoding_options: &ImageEncodingOptions,
+        metadata: ImageMetadata
+    ) -> ImageResult<()> {
+        match image_format {
+            ImageFormat::Jpeg => {
+                let encoder = match encoding_options.quality() {
+                    Some(quality) => JpegEncoder::new_with_quality(image_bytes, quality),
+                    None => JpegEncoder::new(image_bytes),
+                };
+                Self::write_with_encoder(dyn_image, encoder, metadata)
+            },
+            ImageFormat::Png => {
+                let compression = encoding_options.compression()
+                    .map(CompressionType::Level)
+                    .unwrap_or_default();
+                let encoder = PngEncoder::new_with_quality(image_bytes, compression, png::FilterType::Adaptive);
+                Self::write_with_encoder(dyn_image, encoder, metadata)
+            },
+            ImageFormat::WebP => Self::write_with_encoder(dyn_image, WebPEncoder::new_lossless(image_bytes), metadata),
+            ImageFormat::Avif => {
+                let quality = encoding_options.quality().unwrap_or(Self::DEFAULT_AVIF_QUALITY);
+                let encoder = AvifEncoder::new_with_speed_quality(image_bytes, Self::AVIF_ENCODER_SPEED, quality);
+                Self::write_with_encoder(dyn_image, encoder, metadata)
+            },
+            _ => dyn_image.write_to(&mut Cursor::new(image_bytes), image_format),
+        }
+    }
+
+    // Metadata an encoder cannot embed is dropped rather than failing the download
+    fn write_with_encoder<E: ImageEncoder>(dyn_image: &DynamicImage, mut encoder: E, metadata: ImageMetadata) -> ImageResult<()> {
+        if let Some(icc_profile) = metadata.icc_profile {
+            let _ = encoder.set_icc_profile(icc_profile);
+        }
+        if let Some(exif) = metadata.exif {
+            let _ = encoder.set_exif_metadata(exif);
+        }
+
+        dyn_image.write_with_encoder(encoder)
+    }
+
+    // A thumbnail or a resize can enlarge the image far beyond the decode limits
+    fn check_transformed_dimensions(dyn_image: &DynamicImage, transformations: &[ImageTransformation], decode_limits: &DecodeLimits) -> Result<(), ServiceError> {
+        let bytes_per_pixel = u64::from(dyn_image.color().bytes_per_pixel());
+        let mut dimensions = dyn_image.dimensions();
+        for transformation in transformations {
+            let (largest_dimensions, transformed_dimensions) = transformed_dimensions(*transformation, dimensions);
+            if !decode_limits.admits(largest_dimensions, bytes_per_pixel) {
+                return Err(ServiceError::Validation(format!("The transformed image exceeds the limits of {}", decode_limits)));
+            }
+            dimensions = transformed_dimensions;
+        }
+        Ok(())
+    }
+
+    fn apply_transformation(dyn_image: DynamicImage, transformation: ImageTransformation) -> Result<DynamicImage, InvalidImageTransformationError> {
+        let dyn_image = match transformation {
+            ImageTransformation::HueRotate(huerotate) => dyn_image.huerotate(huerotate),
+            ImageTransformation::Thumbnail(nwidth, nheigth) => dyn_image.thumbnail(nwidth, nheigth),
+            ImageTransformation::Crop { x, y, width, height } => {
+                let (image_width, image_height) = dyn_image.dimensions();
+                let fits = |offset: u32, length: u32, bound: u32| offset.checked_add(length).is_some_and(|end| end <= bound);
+                if !fits(x, width, image_width) || !fits(y, height, image_height) {
+                    return Err(InvalidImageTransformationError::CropOutOfBounds);
+                }
+                dyn_image.crop_imm(x, y, width, height)
+            },
+            ImageTransformation::Rotate(Rotation::Rotate90) => dyn_image.rotate90(),
+            ImageTransformation::Rotate(Rotation::Rotate180) => dyn_image.rotate180(),
+            ImageTransformation::Rotate(Rotation::Rotate270) => dyn_image.rotate270(),
+            ImageTransformation::Flip(Flip::Horizontal) => dyn_image.fliph(),
+            ImageTransformation::Flip(Flip::Vertical) => dyn_image.flipv(),
+            ImageTransformation::Resize { width, height, fit: ResizeFit::Contain } => dyn_image.resize(width, height, FilterType::Lanczos3),
+            ImageTransformation::Resize { width, height, fit: ResizeFit::Cover } => dyn_image.resize_to_fill(width, height, FilterType::Lanczos3),
+            ImageTransformation::Resize { width, height, fit: ResizeFit::Fill } => dyn_image.resize_exact(width, height, FilterType::Lanczos3),
+            ImageTransformation::Blur(sigma) => dyn_image.blur(sigma),
+            ImageTransformation::Grayscale => dyn_image.grayscale(),
+            ImageTransformation::Brightness(brightness) => dyn_image.brighten(brightness),
+            ImageTransformation::Contrast(contrast) => dyn_image.adjust_contrast(contrast),
+        };
+
+        Ok(dyn_image)
+    }
+
 }
 
 #[async_trait::async_trait]
-impl<IR, IU, IP> ImageService for ImageServiceImpl<IR, IU, IP>
+impl<IR, IU, IP, RC> ImageService for ImageServiceImpl<IR, IU, IP, RC>
     where
         IR: ImageReferenceRepository,
         IU: ImageStorage,
         IP: ImagePolicyEnforcer,
+        RC: RenditionCache,
 {
     async fn get_image(
         &self,
         authenticated_user: &AuthenticatedUser,
         id: &Uuid,
-        image_transform_options: &ImageTransformOptions
-    ) -> anyhow::Result<Option<Image>> {
+        image_transform_options: &ImageTransformOptions,
+        download_condition: &DownloadCondition,
+        byte_range: Option<ByteRange>,
+    ) -> Result<ImageDownload, ServiceError> {
         let image_reference_entity = self
             .image_reference_repository()
             .find_image_reference_by_id(id)
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Image reference not found"))?;
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found", id)))?;
 
+        let last_modified = image_reference_entity.created_at;
         let image_reference = ImageReference::from(image_reference_entity);
         let is_authorized = if image_transform_options.contains_transformations() {
-            self.image_policy_enforcer.can_download_then_transform(authenticated_user, &image_reference).await?
+            self.image_policy_enforcer.can_download_then_transform(authenticated_user, &image_reference).await
         } else {
-            self.image_policy_enforcer.can_download(authenticated_user, &image_reference).await?
-        };
+            self.image_policy_enforcer.can_download(authenticated_user, &image_reference).await
+        }.map_err(ServiceError::UpstreamUnavailable)?;
         if !is_authorized {
-            return Err(anyhow::anyhow!("Unauthorized to download image")); // TODO: Error Handling
+            return Err(ServiceError::Forbidden(format!("Unauthorized to download image with id {}", id)));
+        }
+
+        // The images that cannot be decoded are served as they are, whatever format is negotiated
+        let original_options = ImageTransformOptions::new(None, None, Vec::new(), None, ImageEncodingOptions::default());
+        let image_transform_options = if can_decode(image_reference.format()) {
+            image_transform_options
+        } else if image_transform_options.rendition_size().is_none()
+            && !image_transform_options.contains_transformations()
+            && !image_transform_options.encoding().is_customized() {
+            &original_options
+        } else {
+            return Err(ServiceError::Validation(format!("The {} images cannot be transformed", image_reference.format().extensions_str()[0])));
+        };
+
+        // The digest recorded at upload identifies the content, the images uploaded before it was
+        // recorded are identified by the ETag of the storage
+        let entity_tag = match image_reference.properties().sha256() {
+            Some(sha256) => hex::encode(sha256),
+            None => self.stored_content_hash(&image_reference).await?.entity_tag().to_string(),
+        };
+        let image_validators = self.image_validators(&image_reference, &entity_tag, last_modified, image_transform_options);
+        if download_condition.is_not_modified(&image_validators) {
+            return Ok(ImageDownload::NotModified(image_validators));
+        }
+
+        let is_original = image_transform_options.rendition_size().is_none()
+            && !image_transform_options.modifies(image_reference.format());
+        if is_original {
+            return self.download_original(&image_reference, byte_range, image_validators).await;
+        }
+
+        let image = self.produce_image(&image_reference, image_transform_options).await?;
+
+        Ok(ImageDownload::Modified(image, image_validators))
+    }
+}
+
+impl<IR, IU, IP, RC> ImageServiceImpl<IR, IU, IP, RC>
+    where
+        IR: ImageReferenceRepository,
+        IU: ImageStorage,
+        IP: ImagePolicyEnforcer,
+        RC: RenditionCache,
+{
+    // Without a checksum in the storage, the image is hashed in full before any byte is sent
+    // and its ranges are not served
+    async fn download_original(
+        &self,
+        image_reference: &ImageReference,
+        byte_range: Option<ByteRange>,
+        image_validators: ImageValidators,
+    ) -> Result<ImageDownload, ServiceError> {
+        let id = image_reference.id();
+        let expected_sha256 = image_reference.properties().sha256();
+        if let Some(expected_sha256) = expected_sha256 {
+            let content_hash = self.stored_content_hash(image_reference).await?;
+            if content_hash.sha256().is_none() {
+                let image = self.image_uploader
+                    .download_image(id)
+                    .await
+                    .map_err(ServiceError::Storage)?
+                    .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found in storage", id)))?;
+                verify_integrity(id, Some(expected_sha256), &Sha256::digest(image.bytes()).into())?;
+                return Ok(ImageDownload::Modified(image, image_validators));
+            }
+        }
+
+        // The ranges only address the bytes of the original image
+        let content_range = byte_range
+            .map(|byte_range| byte_range
+                .resolve(image_reference.size())
+                .ok_or(ServiceError::RangeNotSatisfiable(image_reference.size())))
+            .transpose()?;
+        let image_stream = self
+            .image_uploader
+            .download_image_stream(id, content_range.as_ref())
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found in storage", id)))?;
+        // The bytes of a range cannot be hashed on their own, only the whole image is
+        let image_stream = match (content_range, expected_sha256) {
+            (None, Some(sha256)) => verify_stream(id, image_stream, *sha256),
+            _ => image_stream,
+        };
+
+        Ok(match content_range {
+            Some(content_range) => ImageDownload::PartialContent(image_stream, content_range, image_validators),
+            None => ImageDownload::Original(image_stream, image_validators),
+        })
+    }
+
+    async fn stored_content_hash(&self, image_reference: &ImageReference) -> Result<ContentHash, ServiceError> {
+        let id = image_reference.id();
+        let content_hash = self.image_uploader
+            .content_hash(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found in storage", id)))?;
+        if let Some(stored_sha256) = content_hash.sha256() {
+            verify_integrity(id, image_reference.properties().sha256(), stored_sha256)?;
+        }
+
+        Ok(content_hash)
+    }
+
+    fn image_validators(
+        &self,
+        image_reference: &ImageReference,
+        entity_tag: &str,
+        last_modified: DateTime<Utc>,
+        image_transform_options: &ImageTransformOptions
+    ) -> ImageValidators {
+        let original_format = image_reference.format();
+        let rendition_key = match image_transform_options.rendition_size() {
+            Some(rendition_size) => self.rendition_generator.rendition_key(rendition_size),
+            None if image_transform_options.modifies(original_format) => image_transform_options.rendition_key(original_format),
+            None => Self::ORIGINAL_RENDITION_KEY.to_string(),
+        };
+
+        ImageValidators::new(entity_tag, &rendition_key, last_modified, image_reference.visibility())
+    }
+
+    async fn produce_image(&self, image_reference: &ImageReference, image_transform_options: &ImageTransformOptions) -> Result<Image, ServiceError> {
+        let id = image_reference.id();
+        if let Some(rendition_size) = image_transform_options.rendition_size() {
+            return self.get_rendition(image_reference, rendition_size).await;
+        }
+
+        // A rendition that cannot be read or written is produced again from the original image
+        let rendition_key = image_transform_options.rendition_key(image_reference.format());
+        match self.rendition_cache.get_rendition(id, &rendition_key).await {
+            Ok(Some(rendition)) => return Ok(rendition),
+            Ok(None) => {}
+            Err(err) => log::warn!("Rendition {} of image {} is unavailable: {:#}", rendition_key, id, err),
         }
         
         let image = self
             .image_uploader
             .download_image(image_reference.id())
-            .await?
-            .ok_or_else(|| anyhow::anyhow!("Image not found"))?;
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::NotFound(format!("Image with id {} not found in storage", id)))?;
         
-        Ok(Some(Self::transform_image(image, image_transform_options)?))
+        let transform_options = image_transform_options.clone();
+        let decode_limits = Arc::clone(&self.decode_limits);
+        let expected_sha256 = image_reference.properties().sha256().copied();
+        let image = self.transform_executor
+            .execute(move || {
+                verify_integrity(&image.id(), expected_sha256.as_ref(), &Sha256::digest(image.bytes()).into())?;
+                Self::transform_image(image, &transform_options, &decode_limits)
+            })
+            .await?;
+        if let Err(err) = self.rendition_cache.put_rendition(id, &rendition_key, &image).await {
+            log::warn!("Rendition {} of image {} was not cached: {:#}", rendition_key, id, err);
+        }
+
+        Ok(image)
+    }
+}
+
+fn verify_integrity(image_id: &Uuid, expected_sha256: Option<&[u8; 32]>, sha256: &[u8; 32]) -> Result<(), ServiceError> {
+    match expected_sha256 {
+        Some(expected_sha256) if expected_sha256 != sha256 => {
+            log::error!(
+                "Integrity incident: image {} has the SHA-256 digest {} in storage instead of {}",
+                image_id, hex::encode(sha256), hex::encode(expected_sha256)
+            );
+            Err(ServiceError::IntegrityViolation(format!("The image {} does not match its digest", image_id)))
+        },
+        _ => Ok(()),
+    }
+}
+
+// The headers are already sent once the last byte is read, a mismatch cuts the response short
+fn verify_stream(image_id: &Uuid, image_stream: ImageStream, expected_sha256: [u8; 32]) -> ImageStream {
+    let image_id = *image_id;
+    image_stream.map_bytes(|bytes| futures::stream::unfold(Some((bytes, Sha256::new())), move |state| async move {
+        let (mut bytes, mut hasher) = state?;
+        match bytes.next().await {
+            Some(Ok(chunk)) => {
+                hasher.update(&chunk);
+                Some((Ok(chunk), Some((bytes, hasher))))
+            },
+            Some(Err(err)) => Some((Err(err), None)),
+            None => verify_integrity(&image_id, Some(&expected_sha256), &hasher.finalize().into())
+                .err()
+                .map(|err| (Err(anyhow::anyhow!(err)), None)),
+        }
+    }).boxed())
+}
+
+// JPEG has no alpha channel, and the WebP, AVIF, BMP and QOI encoders only support 8-bit colors
+fn to_encodable_color(dyn_image: DynamicImage, image_format: ImageFormat) -> DynamicImage {
+    match image_format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(dyn_image.to_rgb8()),
+        ImageFormat::WebP | ImageFormat::Avif | ImageFormat::Bmp | ImageFormat::Qoi if dyn_image.color().has_alpha() => DynamicImage::ImageRgba8(dyn_image.to_rgba8()),
+        ImageFormat::WebP | ImageFormat::Avif | ImageFormat::Bmp | ImageFormat::Qoi => DynamicImage::ImageRgb8(dyn_image.to_rgb8()),
+        _ => dyn_image,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenditionGenerator {
+    format: ImageFormat,
+    long_edges: Vec<(RenditionSize, u32)>,
+}
+
+impl RenditionGenerator {
+    pub fn new(format: ImageFormat, long_edges: Vec<(RenditionSize, u32)>) -> Self {
+        Self { format, long_edges }
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn rendition_key(&self, rendition_size: RenditionSize) -> String {
+        format!("{}:{:?}:{:?}", rendition_size.as_str(), self.long_edge(rendition_size), self.format)
+    }
+
+    pub fn long_edge(&self, rendition_size: RenditionSize) -> Option<u32> {
+        self.long_edges
+            .iter()
+            .find(|(size, _)| *size == rendition_size)
+            .map(|(_, long_edge)| *long_edge)
+    }
+
+    pub async fn upload_renditions<IU: ImageStorage>(
+        &self,
+        image_storage: &IU,
+        transform_executor: &TransformExecutor,
+        image_id: &Uuid,
+        filename: &str,
+        visibility: Visibility,
+        dyn_image: Option<DynamicImage>
+    ) -> Vec<ImageRendition> {
+        let Some(dyn_image) = dyn_image else {
+            log::warn!("Renditions of image {} were not generated: its format cannot be decoded", image_id);
+            return Vec::new();
+        };
+        let rendition_generator = self.clone();
+        let (rendered_image_id, filename) = (*image_id, filename.to_string());
+        let renditions = transform_executor
+            .execute(move || rendition_generator
+                .render_all(&rendered_image_id, &filename, visibility, &dyn_image)
+                .map_err(ServiceError::Storage))
+            .await;
+        let renditions = match renditions {
+            Ok(renditions) => renditions,
+            Err(err) => {
+                log::warn!("Renditions of image {} were not generated: {}", image_id, err);
+                return Vec::new();
+            }
+        };
+
+        let mut image_renditions = Vec::with_capacity(renditions.len());
+        for (image_rendition, rendition) in renditions {
+            match image_storage.upload_rendition(image_id, image_rendition.size(), &rendition).await {
+                Ok(()) => image_renditions.push(image_rendition),
+                Err(err) => log::warn!("Rendition {} of image {} was not uploaded: {:#}", image_rendition.size().as_str(), image_id, err),
+            }
+        }
+        image_renditions
+    }
+
+    pub fn render(&self, image: &Image, rendition_size: RenditionSize, decode_limits: &DecodeLimits) -> Result<Option<(ImageRendition, Image)>, ServiceError> {
+        let Some(long_edge) = self.long_edge(rendition_size) else {
+            return Ok(None);
+        };
+        let dyn_image = ImageReader::new(Cursor::new(image.bytes()))
+            .with_guessed_format()
+            .map_err(ImageError::IoError)
+            .and_then(|image_reader| decode_limits.decode(image_reader))
+            .map_err(|err| decode_limits.stored_image_error(err))?;
+        self.render_decoded(&dyn_image, &image.id(), image.filename(), image.visibility(), rendition_size, long_edge)
+            .map(Some)
+            .map_err(ServiceError::Storage)
+    }
+
+    fn render_all(&self, image_id: &Uuid, filename: &str, visibility: Visibility, dyn_image: &DynamicImage) -> anyhow::Result<Vec<(ImageRendition, Image)>> {
+        self.long_edges
+            .iter()
+            .map(|(rendition_size, long_edge)| self.render_decoded(
+                dyn_image,
+                image_id,
+                filename,
+                visibility,
+                *rendition_size,
+                *long_edge
+            ))
+            .collect()
+    }
+
+    // Images smaller than the long edge are never upscaled
+    fn render_decoded(
+        &self,
+        dyn_image: &DynamicImage,
+        image_id: &Uuid,
+        filename: &str,
+        visibility: Visibility,
+        rendition_size: RenditionSize,
+        long_edge: u32
+    ) -> anyhow::Result<(ImageRendition, Image)> {
+        let (width, height) = dyn_image.dimensions();
+        let resized_image = if width.max(height) > long_edge {
+            dyn_image.thumbnail(long_edge, long_edge)
+        } else {
+            dyn_image.clone()
+        };
+        let resized_image = to_encodable_color(resized_image, self.format);
+
+        let mut rendition_bytes = Vec::new();
+        resized_image.write_to(&mut Cursor::new(&mut rendition_bytes), self.format)?;
+        let rendition_size_bytes = rendition_bytes.len();
+
+        let image_rendition = ImageRendition::new(
+            rendition_size,
+            resized_image.width(),
+            resized_image.height(),
+            self.format,
+            rendition_size_bytes as u64
+        );
+        let rendition = Image::new(image_id, filename, &self.format, &visibility, rendition_bytes, rendition_size_bytes as u32);
+
+        Ok((image_rendition, rendition))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadValidator {
+    accepted_formats: Vec<ImageFormat>,
+    decode_limits: Arc<DecodeLimits>,
+}
+
+impl UploadValidator {
+    // Enough bytes for the signature of any image format
+    pub const SNIFFED_LENGTH: u64 = 64;
+
+    pub fn new(accepted_formats: Vec<ImageFormat>, decode_limits: Arc<DecodeLimits>) -> Self {
+        Self { accepted_formats, decode_limits }
+    }
+
+    pub fn recognize(&self, head: &[u8]) -> Result<ImageFormat, UploadImageError> {
+        let format = image::guess_format(head).map_err(|_| UploadImageError::BadContentType)?;
+        if !self.accepted_formats.contains(&format) {
+            return Err(UploadImageError::UnsupportedMimeType);
+        }
+        Ok(format)
+    }
+
+    // The decoded image is `None` when this build cannot decode its format
+    pub fn validate(&self, upload_image: &UploadImage) -> Result<(Option<DynamicImage>, ImageProperties), ServiceError> {
+        let file = File::open(upload_image.path()).map_err(|err| ServiceError::Storage(err.into()))?;
+        self.validate_content(file, upload_image.format())
+    }
+
+    pub fn validate_image(&self, image: &Image) -> Result<(Option<DynamicImage>, ImageProperties), ServiceError> {
+        self.validate_content(Cursor::new(image.bytes()), image.format())
+    }
+
+    fn validate_content<R: Read + Seek>(&self, mut content: R, declared_format: ImageFormat) -> Result<(Option<DynamicImage>, ImageProperties), ServiceError> {
+        let mut head = Vec::with_capacity(Self::SNIFFED_LENGTH as usize);
+        (&mut content).take(Self::SNIFFED_LENGTH)
+            .read_to_end(&mut head)
+            .and_then(|_| content.rewind())
+            .map_err(|err| ServiceError::Storage(err.into()))?;
+
+        let format = self.recognize(&head)?;
+        if format != declared_format {
+            return Err(UploadImageError::MismatchedFormat.into());
+        }
+        let mut hasher = Sha256::new();
+        io::copy(&mut content, &mut hasher)
+            .and_then(|_| content.rewind())
+            .map_err(|err| ServiceError::Storage(err.into()))?;
+        let properties = ImageProperties::new(hasher.finalize().into());
+        if !can_decode(format) {
+            return Ok((None, properties));
+        }
+
+        let decoder = self.decode_limits.decoder(ImageReader::with_format(BufReader::new(content), format));
+        let (dyn_image, bit_depth) = decoder
+            .and_then(|decoder| {
+                let original_color_type = decoder.original_color_type();
+                let bit_depth = original_color_type.bits_per_pixel() / u16::from(original_color_type.channel_count().max(1));
+                DynamicImage::from_decoder(decoder).map(|dyn_image| (dyn_image, bit_depth))
+            })
+            .map_err(|err| match err {
+                ImageError::Limits(_) => ServiceError::PayloadTooLarge(format!("The uploaded image exceeds the limits of {}", self.decode_limits)),
+                _ => UploadImageError::CorruptedImage.into(),
+            })?;
+        let properties = properties.with_layout(
+            dyn_image.width(),
+            dyn_image.height(),
+            ColorType::from(dyn_image.color()),
+            bit_depth as u8,
+        );
+        Ok((Some(dyn_image), properties))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodeLimits {
+    max_width: u32,
+    max_height: u32,
+    max_pixels: u64,
+    max_alloc: u64,
+}
+
+impl DecodeLimits {
+    pub fn new(max_width: u32, max_height: u32, max_pixels: u64, max_alloc: u64) -> Self {
+        Self { max_width, max_height, max_pixels, max_alloc }
+    }
+
+    fn limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_width);
+        limits.max_image_height = Some(self.max_height);
+        limits.max_alloc = Some(self.max_alloc);
+        limits
+    }
+
+    pub fn decoder<'a, R: BufRead + Seek + 'a>(&self, mut image_reader: ImageReader<R>) -> ImageResult<impl ImageDecoder + 'a> {
+        image_reader.limits(self.limits());
+        let decoder = image_reader.into_decoder()?;
+        // Unlike `ImageReader::decode`, `into_decoder` does not reserve the buffer of the image
+        self.limits().reserve(decoder.total_bytes())?;
+        // The pixel count is the one limit `image::Limits` does not have
+        let (width, height) = decoder.dimensions();
+        if u64::from(width) * u64::from(height) > self.max_pixels {
+            return Err(ImageError::Limits(LimitError::from_kind(LimitErrorKind::DimensionError)));
+        }
+        Ok(decoder)
     }
+
+    pub fn admits(&self, (width, height): (u32, u32), bytes_per_pixel: u64) -> bool {
+        let pixels = u64::from(width) * u64::from(height);
+        let mut limits = self.limits();
+        pixels <= self.max_pixels
+            && limits.check_dimensions(width, height).is_ok()
+            && limits.reserve(pixels.saturating_mul(bytes_per_pixel)).is_ok()
+    }
+
+    pub fn decode<R: BufRead + Seek>(&self, image_reader: ImageReader<R>) -> ImageResult<DynamicImage> {
+        DynamicImage::from_decoder(self.decoder(image_reader)?)
+    }
+
+    // An image stored before the limits were lowered cannot be processed, while it is not invalid
+    fn stored_image_error(&self, err: ImageError) -> ServiceError {
+        match err {
+            ImageError::Limits(_) => ServiceError::Unprocessable(format!("The image exceeds the limits of {}", self)),
+            err => ServiceError::Storage(err.into()),
+        }
+    }
+}
+
+impl fmt::Display for DecodeLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{} pixels, {} pixels in total and {} bytes of memory", self.max_width, self.max_height, self.max_pixels, self.max_alloc)
+    }
+}
+
+// The largest dimensions a transformation allocates and the transformed ones, they differ when
+// a resize fills its area before cropping it
+fn transformed_dimensions(transformation: ImageTransformation, (width, height): (u32, u32)) -> ((u32, u32), (u32, u32)) {
+    let transformed_dimensions = match transformation {
+        ImageTransformation::Thumbnail(nwidth, nheight) => resize_dimensions((width, height), (nwidth, nheight), false),
+        ImageTransformation::Crop { width, height, .. } => (width, height),
+        ImageTransformation::Rotate(Rotation::Rotate90 | Rotation::Rotate270) => (height, width),
+        ImageTransformation::Resize { width: nwidth, height: nheight, fit: ResizeFit::Contain } => resize_dimensions((width, height), (nwidth, nheight), false),
+        ImageTransformation::Resize { width: nwidth, height: nheight, fit: ResizeFit::Cover } => {
+            return (resize_dimensions((width, height), (nwidth, nheight), true), (nwidth, nheight));
+        },
+        ImageTransformation::Resize { width, height, fit: ResizeFit::Fill } => (width, height),
+        _ => (width, height),
+    };
+    (transformed_dimensions, transformed_dimensions)
+}
+
+fn resize_dimensions((width, height): (u32, u32), (nwidth, nheight): (u32, u32), fill: bool) -> (u32, u32) {
+    let width_ratio = f64::from(nwidth) / f64::from(width);
+    let height_ratio = f64::from(nheight) / f64::from(height);
+    let use_width = if fill { width_ratio > height_ratio } else { width_ratio <= height_ratio };
+    let resized_length = if use_width { f64::from(height) * width_ratio } else { f64::from(width) * height_ratio };
+    let resized_length = resized_length.round().clamp(1.0, f64::from(u32::MAX)) as u32;
+    if use_width { (nwidth, resized_length) } else { (resized_length, nheight) }
+}
+
+// AVIF only decodes with the `avif-native` feature, which links libdav1d and is not enabled
+pub fn can_decode(format: ImageFormat) -> bool {
+    format.reading_enabled() && format != ImageFormat::Avif
+}
+
+#[derive(Debug, Default)]
+struct ImageMetadata {
+    icc_profile: Option<Vec<u8>>,
+    exif: Option<Vec<u8>>,
 }
 
 #[derive(Debug, Clone)]
@@ -117,4 +815,384 @@ impl ImageReferenceUrlBuilder {
             .join(&image_reference_id.to_string())
             .unwrap()
     } 
-}
\ No newline at end of file
+}
+#[allow(unused_imports, dead_code)]
+mod tests {
+    use std::io::Write;
+
+    use bytes::Bytes;
+    use image::{DynamicImage, ImageBuffer, ImageFormat, RgbaImage};
+    use tempfile::NamedTempFile;
+
+    use crate::models::entity::{ImageReferenceEntity, ImageRenditionEntity};
+    use crate::models::service::image::ContentRange;
+    use crate::models::service::upload::{UploadedImage, UploadedObject};
+    use crate::models::service::Visibility;
+    use crate::repository::PostgresDatabase;
+    use crate::security::authz::ImagePolicyEnforcerKc;
+    use crate::service::image_storage::AwsS3Client;
+
+    use super::*;
+
+    #[test]
+    fn should_convert_the_format_of_an_image() {
+        let image = png_image(4, 4);
+
+        let image_transform_options = ImageTransformOptions::new(None, None, Vec::new(), Some(ImageFormat::Jpeg), ImageEncodingOptions::default());
+        let converted_image = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits()).unwrap();
+
+        assert_eq!(converted_image.format(), ImageFormat::Jpeg);
+        assert_eq!(converted_image.filename_with_extension(), "photo.jpg");
+        assert_eq!(converted_image.size() as usize, converted_image.bytes().len());
+        assert_eq!(image::guess_format(converted_image.bytes()).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn should_apply_the_operations_in_order() {
+        let image = png_image(40, 20);
+
+        let operations = vec![
+            ImageTransformation::Crop { x: 10, y: 0, width: 30, height: 20 },
+            ImageTransformation::Rotate(Rotation::Rotate90),
+            ImageTransformation::Resize { width: 10, height: 10, fit: ResizeFit::Cover },
+            ImageTransformation::Grayscale,
+        ];
+        let image_transform_options = ImageTransformOptions::new(None, None, operations, None, ImageEncodingOptions::default());
+        let transformed_image = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits()).unwrap();
+
+        let dyn_image = image::load_from_memory(transformed_image.bytes()).unwrap();
+        assert_eq!(dyn_image.dimensions(), (10, 10));
+        assert!(!dyn_image.color().has_color());
+    }
+
+    #[test]
+    fn should_reject_a_crop_out_of_the_image() {
+        let image = png_image(4, 4);
+
+        let operations = vec![ImageTransformation::Crop { x: 2, y: 2, width: 4, height: 4 }];
+        let image_transform_options = ImageTransformOptions::new(None, None, operations, None, ImageEncodingOptions::default());
+        let result = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits());
+
+        assert!(matches!(result, Err(ServiceError::Validation(_))));
+    }
+
+    #[test]
+    fn should_refuse_the_quality_of_a_webp_image() {
+        let webp_bytes = encode_image(DynamicImage::ImageRgba8(RgbaImage::new(8, 8)), ImageFormat::WebP);
+        let webp_size = webp_bytes.len() as u32;
+        let image = Image::new(&Uuid::new_v4(), "photo.webp", &ImageFormat::WebP, &Visibility::Public, webp_bytes, webp_size);
+
+        let image_transform_options = ImageTransformOptions::new(None, None, Vec::new(), None, ImageEncodingOptions::new(Some(50), None, true));
+        let result = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits());
+
+        assert!(matches!(result, Err(ServiceError::Validation(_))));
+    }
+
+    #[test]
+    fn should_re_encode_with_the_requested_quality() {
+        let gradient = image::RgbImage::from_fn(64, 64, |x, y| image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8]));
+        let jpeg_bytes = encode_image(DynamicImage::ImageRgb8(gradient), ImageFormat::Jpeg);
+        let jpeg_size = jpeg_bytes.len() as u32;
+        let image = Image::new(&Uuid::new_v4(), "photo.jpg", &ImageFormat::Jpeg, &Visibility::Public, jpeg_bytes, jpeg_size);
+
+        let image_transform_options = ImageTransformOptions::new(None, None, Vec::new(), None, ImageEncodingOptions::new(Some(10), None, true));
+        let re_encoded_image = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits()).unwrap();
+
+        assert_eq!(re_encoded_image.format(), ImageFormat::Jpeg);
+        assert!(re_encoded_image.size() < jpeg_size);
+    }
+
+    #[test]
+    fn should_downscale_the_renditions_to_their_long_edge() {
+        let upload_image = png_upload(400, 200);
+        let dyn_image = UploadValidator::new(vec![ImageFormat::Png], Arc::new(decode_limits())).validate(&upload_image).unwrap().0.unwrap();
+
+        let rendition_generator = RenditionGenerator::new(ImageFormat::WebP, vec![(RenditionSize::Small, 160), (RenditionSize::Large, 1280)]);
+        let renditions = rendition_generator.render_all(&Uuid::new_v4(), upload_image.filename(), upload_image.visibility(), &dyn_image).unwrap();
+
+        let (small_rendition, small_image) = &renditions[0];
+        assert_eq!((small_rendition.width(), small_rendition.height()), (160, 80));
+        assert_eq!(small_image.format(), ImageFormat::WebP);
+        assert_eq!(small_rendition.file_size(), small_image.bytes().len() as u64);
+        let (large_rendition, _) = &renditions[1];
+        assert_eq!((large_rendition.width(), large_rendition.height()), (400, 200));
+    }
+
+    #[test]
+    fn should_record_the_properties_of_an_uploaded_image() {
+        let png_bytes = encode_image(DynamicImage::ImageLumaA16(ImageBuffer::new(30, 20)), ImageFormat::Png);
+        let upload_image = upload_image("photo.png", ImageFormat::Png, &png_bytes);
+
+        let (_, properties) = UploadValidator::new(vec![ImageFormat::Png], Arc::new(decode_limits())).validate(&upload_image).unwrap();
+
+        assert_eq!((properties.width(), properties.height()), (Some(30), Some(20)));
+        assert_eq!(properties.color_type(), Some(ColorType::GrayAlpha));
+        assert_eq!(properties.bit_depth(), Some(16));
+        assert_eq!(properties.sha256(), Some(&Sha256::digest(&png_bytes).into()));
+    }
+
+    #[test]
+    fn should_reject_an_image_not_matching_its_content_type() {
+        let png_bytes = encode_image(DynamicImage::ImageRgba8(RgbaImage::new(4, 4)), ImageFormat::Png);
+        let upload_image = upload_image("photo.jpg", ImageFormat::Jpeg, &png_bytes);
+
+        let upload_validator = UploadValidator::new(vec![ImageFormat::Jpeg, ImageFormat::Png], Arc::new(decode_limits()));
+        let result = upload_validator.validate(&upload_image);
+
+        assert!(matches!(result, Err(ServiceError::Validation(_))));
+        assert_eq!(upload_validator.recognize(&png_bytes), Ok(ImageFormat::Png));
+        assert_eq!(UploadValidator::new(vec![ImageFormat::Jpeg], Arc::new(decode_limits())).recognize(&png_bytes), Err(UploadImageError::UnsupportedMimeType));
+    }
+
+    #[test]
+    fn should_reject_a_truncated_image() {
+        let qoi_bytes = encode_image(DynamicImage::ImageRgb8(image::RgbImage::from_fn(32, 32, |x, y| image::Rgb([x as u8, y as u8, 0]))), ImageFormat::Qoi);
+        let upload_image = upload_image("photo.qoi", ImageFormat::Qoi, &qoi_bytes[..qoi_bytes.len() / 2]);
+
+        let result = UploadValidator::new(vec![ImageFormat::Qoi], Arc::new(decode_limits())).validate(&upload_image);
+
+        assert!(matches!(result, Err(ServiceError::Validation(_))));
+    }
+
+    #[test]
+    fn should_decode_an_image_uploaded_straight_to_the_storage() {
+        let image = png_image(64, 4);
+        let truncated_bytes = image.bytes()[..image.bytes().len() / 2].to_vec();
+        let truncated_image = Image::new(&Uuid::new_v4(), "photo.png", &ImageFormat::Png, &Visibility::Public, truncated_bytes, image.size() / 2);
+
+        let upload_validator = UploadValidator::new(vec![ImageFormat::Png], Arc::new(decode_limits()));
+        assert_eq!(upload_validator.validate_image(&image).unwrap().1.width(), Some(64));
+        assert!(matches!(upload_validator.validate_image(&truncated_image), Err(ServiceError::Validation(_))));
+
+        let narrow_upload_validator = UploadValidator::new(vec![ImageFormat::Png], Arc::new(DecodeLimits::new(32, 32, 1024, 64 * 1024 * 1024)));
+        assert!(matches!(narrow_upload_validator.validate_image(&image), Err(ServiceError::PayloadTooLarge(_))));
+    }
+
+    #[test]
+    fn should_refuse_an_oversized_thumbnail_before_transforming_the_image() {
+        let image = png_image(40, 20);
+
+        let image_transform_options = ImageTransformOptions::new(None, Some((8192, 8192)), Vec::new(), None, ImageEncodingOptions::default());
+        let result = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits());
+
+        assert!(matches!(result, Err(ServiceError::Validation(_))));
+        assert_eq!(transformed_dimensions(ImageTransformation::Thumbnail(8192, 8192), (40, 20)), ((8192, 4096), (8192, 4096)));
+        assert_eq!(
+            transformed_dimensions(ImageTransformation::Resize { width: 100, height: 100, fit: ResizeFit::Cover }, (40, 20)),
+            ((200, 100), (100, 100))
+        );
+    }
+
+    #[test]
+    fn should_refuse_to_transform_an_image_beyond_the_limits() {
+        let image = png_image(40, 20);
+
+        let image_transform_options = ImageTransformOptions::new(None, None, vec![ImageTransformation::Grayscale], None, ImageEncodingOptions::default());
+        let decode_limits = DecodeLimits::new(40, 40, 400, 64 * 1024 * 1024);
+        let result = ImageServiceImpl::<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, AwsS3Client>::transform_image(image, &image_transform_options, &decode_limits);
+
+        assert!(matches!(result, Err(ServiceError::Unprocessable(_))));
+    }
+
+    #[actix_web::test]
+    async fn should_fail_the_stream_of_an_image_altered_in_the_storage() {
+        let stream = |bytes: &'static [u8]| {
+            let chunks = futures::stream::iter(bytes.chunks(4).map(|chunk| Ok(Bytes::copy_from_slice(chunk))));
+            ImageStream::new("photo.png", ImageFormat::Png, bytes.len() as u64, chunks.boxed())
+        };
+        let uploaded_sha256: [u8; 32] = Sha256::digest(b"uploaded image bytes").into();
+
+        let verified: Vec<_> = verify_stream(&Uuid::new_v4(), stream(b"uploaded image bytes"), uploaded_sha256).into_bytes().collect().await;
+        let altered: Vec<_> = verify_stream(&Uuid::new_v4(), stream(b"altered image bytes!"), uploaded_sha256).into_bytes().collect().await;
+
+        assert!(verified.iter().all(Result::is_ok));
+        assert!(altered.last().unwrap().is_err());
+        assert!(altered[..altered.len() - 1].iter().all(Result::is_ok));
+    }
+
+    #[actix_web::test]
+    async fn should_verify_an_original_image_before_sending_it() {
+        let uploaded_bytes = b"uploaded image bytes".to_vec();
+        let uploaded_sha256: [u8; 32] = Sha256::digest(&uploaded_bytes).into();
+        let image_reference = ImageReference::new(
+            &Uuid::new_v4(),
+            &Uuid::new_v4(),
+            &Url::parse("http://localhost:8080/").unwrap(),
+            uploaded_bytes.len() as u64,
+            &ImageFormat::Png,
+            &Visibility::Public,
+            &ImageProperties::new(uploaded_sha256),
+        );
+        let image_service = |bytes: &[u8], stored_sha256: Option<[u8; 32]>| {
+            let mock_image_storage = Arc::new(MockImageStorage { bytes: bytes.to_vec(), stored_sha256 });
+            ImageServiceImpl::new(
+                Arc::clone(&mock_image_storage),
+                Arc::clone(&mock_image_storage),
+                Arc::clone(&mock_image_storage),
+                Arc::clone(&mock_image_storage),
+                Arc::new(RenditionGenerator::new(ImageFormat::WebP, Vec::new())),
+                Arc::new(decode_limits()),
+                Arc::new(TransformExecutor::new(1, 1, std::time::Duration::from_secs(1))),
+            )
+        };
+        let download_original = |image_service: ImageServiceImpl<MockImageStorage, MockImageStorage, MockImageStorage, MockImageStorage>, byte_range| {
+            let image_reference = image_reference.clone();
+            async move {
+                let image_validators = ImageValidators::new("etag", "original", Utc::now(), Visibility::Public);
+                image_service.download_original(&image_reference, byte_range, image_validators).await
+            }
+        };
+        let altered_sha256: [u8; 32] = Sha256::digest(b"altered image bytes!").into();
+
+        let replaced = download_original(image_service(b"altered image bytes!", Some(altered_sha256)), Some(ByteRange::From(4))).await;
+        assert!(matches!(replaced, Err(ServiceError::IntegrityViolation(_))));
+
+        let unchecked_altered = download_original(image_service(b"altered image bytes!", None), None).await;
+        assert!(matches!(unchecked_altered, Err(ServiceError::IntegrityViolation(_))));
+
+        let unchecked = download_original(image_service(&uploaded_bytes, None), Some(ByteRange::From(4))).await;
+        assert!(matches!(unchecked, Ok(ImageDownload::Modified(image, _)) if image.bytes() == &uploaded_bytes));
+
+        let checked = download_original(image_service(&uploaded_bytes, Some(uploaded_sha256)), Some(ByteRange::From(4))).await;
+        assert!(matches!(checked, Ok(ImageDownload::PartialContent(_, _, _))));
+    }
+
+    fn decode_limits() -> DecodeLimits {
+        DecodeLimits::new(4096, 4096, 4096 * 4096, 512 * 1024 * 1024)
+    }
+
+    fn encode_image(dyn_image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        dyn_image.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+        bytes
+    }
+
+    fn png_image(width: u32, height: u32) -> Image {
+        let png_bytes = encode_image(DynamicImage::ImageRgba8(RgbaImage::new(width, height)), ImageFormat::Png);
+        let png_size = png_bytes.len() as u32;
+        Image::new(&Uuid::new_v4(), "photo.png", &ImageFormat::Png, &Visibility::Public, png_bytes, png_size)
+    }
+
+    fn upload_image(filename: &str, format: ImageFormat, bytes: &[u8]) -> UploadImage {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(bytes).unwrap();
+        UploadImage::new(filename, file, format, Visibility::Public, bytes.len())
+    }
+
+    fn png_upload(width: u32, height: u32) -> UploadImage {
+        let png_bytes = encode_image(DynamicImage::ImageRgba8(RgbaImage::new(width, height)), ImageFormat::Png);
+        upload_image("photo.png", ImageFormat::Png, &png_bytes)
+    }
+
+    #[derive(Clone)]
+    struct MockImageStorage {
+        bytes: Vec<u8>,
+        stored_sha256: Option<[u8; 32]>,
+    }
+
+    #[async_trait::async_trait]
+    impl ImageStorage for MockImageStorage {
+        async fn upload_image(&self, _upload_image: &UploadImage, _sha256: Option<&[u8; 32]>) -> anyhow::Result<(Uuid, Url)> {
+            Ok((Uuid::new_v4(), Url::parse("https://localhost:8080/").unwrap()))
+        }
+
+        async fn download_image(&self, id: &Uuid) -> anyhow::Result<Option<Image>> {
+            Ok(Some(Image::new(id, "photo.png", &ImageFormat::Png, &Visibility::Public, self.bytes.clone(), self.bytes.len() as u32)))
+        }
+
+        async fn download_image_stream(&self, _id: &Uuid, content_range: Option<&ContentRange>) -> anyhow::Result<Option<ImageStream>> {
+            let bytes = match content_range {
+                Some(content_range) => self.bytes[content_range.first() as usize..=content_range.last() as usize].to_vec(),
+                None => self.bytes.clone(),
+            };
+            let content_length = bytes.len() as u64;
+            let chunks = futures::stream::once(async move { Ok(Bytes::from(bytes)) });
+            Ok(Some(ImageStream::new("photo.png", ImageFormat::Png, content_length, chunks.boxed())))
+        }
+
+        async fn content_hash(&self, _id: &Uuid) -> anyhow::Result<Option<ContentHash>> {
+            Ok(Some(ContentHash::new("etag", self.stored_sha256)))
+        }
+
+        async fn delete_image(&self, _id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn upload_rendition(&self, _image_id: &Uuid, _rendition_size: RenditionSize, _rendition: &Image) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn download_rendition(&self, _image_id: &Uuid, _rendition_size: RenditionSize) -> anyhow::Result<Option<Image>> {
+            Ok(None)
+        }
+
+        async fn presign_upload(&self, upload_id: &Uuid) -> anyhow::Result<(Url, DateTime<Utc>)> {
+            Ok((Url::parse(&format!("https://localhost:8080/uploads/{}", upload_id)).unwrap(), Utc::now()))
+        }
+
+        async fn inspect_upload(&self, _upload_id: &Uuid, _head_length: u64) -> anyhow::Result<Option<UploadedObject>> {
+            Ok(None)
+        }
+
+        async fn finalize_upload(&self, _uploaded_image: &UploadedImage) -> anyhow::Result<Option<(Uuid, Url)>> {
+            Ok(None)
+        }
+
+        async fn delete_upload(&self, _upload_id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ImageReferenceRepository for MockImageStorage {
+        async fn find_image_reference_by_id(&self, _id: &Uuid) -> anyhow::Result<Option<ImageReferenceEntity>> {
+            Ok(None)
+        }
+
+        async fn find_image_rendition(&self, _image_id: &Uuid, _rendition_size: RenditionSize) -> anyhow::Result<Option<ImageRenditionEntity>> {
+            Ok(None)
+        }
+
+        async fn save_image_rendition(&self, _image_id: &Uuid, _image_rendition: &ImageRendition) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ImagePolicyEnforcer for MockImageStorage {
+        async fn can_download(&self, _authenticated_user: &AuthenticatedUser, _image_reference: &ImageReference) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn can_transform(&self, _authenticated_user: &AuthenticatedUser, _image_reference: &ImageReference) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn can_download_then_transform(&self, _authenticated_user: &AuthenticatedUser, _image_reference: &ImageReference) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn can_create(&self, _authenticated_user: &AuthenticatedUser) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        async fn can_view(&self, _authenticated_user: &AuthenticatedUser, _image_reference: &ImageReference) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RenditionCache for MockImageStorage {
+        async fn get_rendition(&self, _image_id: &Uuid, _rendition_key: &str) -> anyhow::Result<Option<Image>> {
+            Ok(None)
+        }
+
+        async fn put_rendition(&self, _image_id: &Uuid, _rendition_key: &str, _rendition: &Image) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn invalidate(&self, _image_id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+}
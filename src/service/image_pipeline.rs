@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use image::DynamicImage;
+use uuid::Uuid;
+
+use crate::models::service::image::{Image, ImageProperties, ImageRendition, UploadImage};
+use crate::models::service::Visibility;
+use crate::service::image::{DecodeLimits, RenditionGenerator, UploadValidator};
+use crate::service::image_storage::ImageStorage;
+use crate::service::rendition_cache::RenditionCache;
+use crate::service::transform_executor::TransformExecutor;
+use crate::service::ServiceError;
+
+/// The image processing shared by the services storing uploaded images: validating them and
+/// rendering their renditions on the transform executor, and dropping their cached renditions.
+#[derive(Debug)]
+pub struct ImagePipeline<C: RenditionCache> {
+    rendition_cache: Arc<C>,
+    rendition_generator: Arc<RenditionGenerator>,
+    transform_executor: Arc<TransformExecutor>,
+    upload_validator: Arc<UploadValidator>,
+    decode_limits: Arc<DecodeLimits>,
+}
+
+impl<C: RenditionCache> ImagePipeline<C> {
+    pub fn new(
+        rendition_cache: Arc<C>,
+        rendition_generator: Arc<RenditionGenerator>,
+        transform_executor: Arc<TransformExecutor>,
+        upload_validator: Arc<UploadValidator>,
+        decode_limits: Arc<DecodeLimits>,
+    ) -> Self {
+        Self {
+            rendition_cache,
+            rendition_generator,
+            transform_executor,
+            upload_validator,
+            decode_limits,
+        }
+    }
+
+    pub fn rendition_cache(&self) -> &C {
+        &self.rendition_cache
+    }
+    pub fn rendition_generator(&self) -> &RenditionGenerator {
+        &self.rendition_generator
+    }
+    pub fn transform_executor(&self) -> &TransformExecutor {
+        &self.transform_executor
+    }
+    pub fn upload_validator(&self) -> &UploadValidator {
+        &self.upload_validator
+    }
+    pub fn decode_limits(&self) -> &DecodeLimits {
+        &self.decode_limits
+    }
+
+    /// Validates an uploaded image on the transform executor.
+    pub async fn validate(&self, upload_image: &UploadImage) -> Result<(Option<DynamicImage>, ImageProperties), ServiceError> {
+        let (upload_validator, validated_image) = (Arc::clone(&self.upload_validator), upload_image.clone());
+        self.transform_executor
+            .execute(move || upload_validator.validate(&validated_image))
+            .await
+    }
+
+    /// Validates an image uploaded straight to the storage on the transform executor.
+    pub async fn validate_image(&self, image: Image) -> Result<(Option<DynamicImage>, ImageProperties), ServiceError> {
+        let upload_validator = Arc::clone(&self.upload_validator);
+        self.transform_executor
+            .execute(move || upload_validator.validate_image(&image))
+            .await
+    }
+
+    /// Renders and stores the renditions of a validated image, see `RenditionGenerator::upload_renditions`.
+    pub async fn upload_renditions<IU: ImageStorage>(
+        &self,
+        image_storage: &IU,
+        image_id: &Uuid,
+        filename: &str,
+        visibility: Visibility,
+        dyn_image: Option<DynamicImage>
+    ) -> Vec<ImageRendition> {
+        self.rendition_generator
+            .upload_renditions(image_storage, &self.transform_executor, image_id, filename, visibility, dyn_image)
+            .await
+    }
+}
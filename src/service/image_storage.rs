@@ -1,73 +1,278 @@
-use anyhow::Context;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use uuid::Uuid;
-use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::operation::get_object::GetObjectOutput;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::{ByteStream, Length};
+use aws_sdk_s3::types::{ChecksumAlgorithm, ChecksumMode, CompletedMultipartUpload, CompletedPart, Delete, MetadataDirective, ObjectIdentifier};
 use aws_sdk_s3::Client;
 use image::ImageFormat;
 use serde::{Deserialize, Serialize};
-use crate::models::service::image::{Image, UploadImage};
+use crate::models::service::image::{ContentHash, ContentRange, Image, ImageStream, RenditionSize, UploadImage};
+use crate::models::service::upload::{UploadedImage, UploadedObject};
 use crate::models::service::Visibility;
-use crate::setup::AwsS3Config;
+use crate::service::rendition_cache::RenditionCache;
+use crate::setup::{AwsS3Config, UploadsConfig};
 
 #[async_trait::async_trait]
 pub trait ImageStorage: Clone + Send + Sync + 'static {
-    async fn upload_image(&self, upload_image: &UploadImage) -> anyhow::Result<(Uuid, url::Url)>;
+    // Refused by the storage when the bytes do not match their SHA-256 digest, if known
+    async fn upload_image(&self, upload_image: &UploadImage, sha256: Option<&[u8; 32]>) -> anyhow::Result<(Uuid, url::Url)>;
     async fn download_image(&self, id: &Uuid) -> anyhow::Result<Option<Image>>;
+    async fn download_image_stream(&self, id: &Uuid, content_range: Option<&ContentRange>) -> anyhow::Result<Option<ImageStream>>;
+    async fn content_hash(&self, id: &Uuid) -> anyhow::Result<Option<ContentHash>>;
+    async fn delete_image(&self, id: &Uuid) -> anyhow::Result<()>;
+    async fn upload_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize, rendition: &Image) -> anyhow::Result<()>;
+    async fn download_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize) -> anyhow::Result<Option<Image>>;
+    async fn presign_upload(&self, upload_id: &Uuid) -> anyhow::Result<(url::Url, DateTime<Utc>)>;
+    // `None` when nothing has been uploaded yet
+    async fn inspect_upload(&self, upload_id: &Uuid, head_length: u64) -> anyhow::Result<Option<UploadedObject>>;
+    // `None` when the uploaded object was replaced or removed since it was inspected
+    async fn finalize_upload(&self, uploaded_image: &UploadedImage) -> anyhow::Result<Option<(Uuid, url::Url)>>;
+    async fn delete_upload(&self, upload_id: &Uuid) -> anyhow::Result<()>;
 }
 
 #[derive(Clone, Debug)]
 pub struct AwsS3Client {
     aws_sdk_s3: aws_sdk_s3::Client,
     bucket_name: String,
+    multipart_threshold: u64,
+    multipart_part_size: u64,
+    presigned_url_expiry: Duration,
     endpoint_url: String,
 }
 
 #[async_trait::async_trait]
 impl ImageStorage for AwsS3Client {
-    async fn upload_image(&self, upload_image: &UploadImage) -> anyhow::Result<(Uuid, url::Url)> {
-        let image_id = self.put_object_image(upload_image).await?;
+    async fn upload_image(&self, upload_image: &UploadImage, sha256: Option<&[u8; 32]>) -> anyhow::Result<(Uuid, url::Url)> {
+        let image_id = self.put_object_image(upload_image, sha256).await?;
         let resource_url = self.build_resource_url(&image_id);
 
         Ok((image_id, resource_url))
     }
 
     async fn download_image(&self, id: &Uuid) -> anyhow::Result<Option<Image>> {
-        let object = self.aws_sdk_s3
-            .get_object()
+        self.get_object_image(id, id.to_string())
+            .await
+            .context("Failed to download image from S3")
+    }
+
+    async fn download_image_stream(&self, id: &Uuid, content_range: Option<&ContentRange>) -> anyhow::Result<Option<ImageStream>> {
+        let range = content_range.map(|content_range| format!("bytes={}-{}", content_range.first(), content_range.last()));
+        let Some((image_metadata, object)) = self.get_object(id.to_string(), range)
+            .await
+            .context("Failed to download image from S3")? else {
+            return Ok(None);
+        };
+
+        let content_length = object.content_length
+            .and_then(|content_length| u64::try_from(content_length).ok())
+            .context("Image content length not found in S3")?;
+        let bytes = futures::stream::unfold(object.body, |mut body| async move {
+            body.next().await.map(|chunk| (chunk.context("Failed to stream image from S3"), body))
+        });
+
+        Ok(Some(ImageStream::new(&image_metadata.filename, image_metadata.format, content_length, bytes.boxed())))
+    }
+
+    // The objects uploaded in parts are copied onto themselves to get a full SHA-256 checksum
+    async fn content_hash(&self, id: &Uuid) -> anyhow::Result<Option<ContentHash>> {
+        match self.aws_sdk_s3
+            .head_object()
             .bucket(&self.bucket_name)
             .key(id.to_string())
+            .checksum_mode(ChecksumMode::Enabled)
             .send()
             .await
-            .context("Failed to download image from S3")?; // TODO: error handling
+        {
+            Ok(object) => {
+                let entity_tag = object.e_tag
+                    .map(|e_tag| e_tag.trim_matches('"').to_string())
+                    .context("Image ETag not found in S3")?;
+                let sha256 = object.checksum_sha256
+                    .and_then(|checksum| BASE64_STANDARD.decode(checksum).ok())
+                    .and_then(|checksum| <[u8; 32]>::try_from(checksum).ok());
+                Ok(Some(ContentHash::new(&entity_tag, sha256)))
+            },
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_not_found()) => Ok(None),
+            Err(err) => Err(err).context("Failed to read image metadata from S3"),
+        }
+    }
 
-        let metadata = object.metadata
-            .as_ref()
-            .and_then(|metadata| metadata.get(Self::IMAGE_METADATA_KEY))
-            .ok_or_else(|| anyhow::anyhow!("Image metadata not found for key: {}", Self::IMAGE_METADATA_KEY))?;  // TODO: error handling
+    async fn delete_image(&self, id: &Uuid) -> anyhow::Result<()> {
+        self.aws_sdk_s3
+            .delete_object()
+            .bucket(&self.bucket_name)
+            .key(id.to_string())
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!(
+                "Failed to delete image '{}' from bucket '{}': {:?}",
+                id, self.bucket_name, e
+            ))?;
 
-        let image_metadata: ImageMetadata = serde_json::from_str(metadata)
-            .context("Failed to deserialize image metadata")?; // TODO: error handling
+        self.delete_renditions(id).await
+    }
 
-        let bytes = object.body.collect().await?.into_bytes().to_vec();
+    async fn upload_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize, rendition: &Image) -> anyhow::Result<()> {
+        let key = Self::rendition_size_object_key(image_id, rendition_size);
+        let image_metadata = serde_json::to_string(&ImageMetadata::from(rendition))?;
 
-        Ok(Some(Image::new(
-            id,
-            &image_metadata.filename,
-            &image_metadata.format,
-            &image_metadata.visibility,
-            bytes,
-            image_metadata.size as u32,
-        )))
+        self.aws_sdk_s3
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(&key)
+            .body(ByteStream::from(rendition.bytes().to_vec()))
+            .metadata(Self::IMAGE_METADATA_KEY, image_metadata)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!(
+                "Failed to upload object with key '{}' to bucket '{}': {:?}",
+                key, self.bucket_name, e
+            ))
+    }
+
+    async fn download_rendition(&self, image_id: &Uuid, rendition_size: RenditionSize) -> anyhow::Result<Option<Image>> {
+        self.get_object_image(image_id, Self::rendition_size_object_key(image_id, rendition_size))
+            .await
+            .context("Failed to download rendition from S3")
+    }
+
+    async fn presign_upload(&self, upload_id: &Uuid) -> anyhow::Result<(url::Url, DateTime<Utc>)> {
+        let expires_at = Utc::now() + self.presigned_url_expiry;
+        let presigned_request = self.aws_sdk_s3
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(Self::upload_object_key(upload_id))
+            .presigned(PresigningConfig::expires_in(self.presigned_url_expiry)?)
+            .await
+            .context("Failed to presign upload to S3")?;
+
+        let upload_url = url::Url::parse(presigned_request.uri())
+            .context("Invalid presigned upload URL")?;
+
+        Ok((upload_url, expires_at))
+    }
+
+    async fn inspect_upload(&self, upload_id: &Uuid, head_length: u64) -> anyhow::Result<Option<UploadedObject>> {
+        let object = match self.aws_sdk_s3
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(Self::upload_object_key(upload_id))
+            .range(format!("bytes=0-{}", head_length.saturating_sub(1)))
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_no_such_key()) => return Ok(None),
+            Err(err) => return Err(err).context("Failed to read uploaded object from S3"),
+        };
+
+        // The size of the whole object follows the range, e.g. `bytes 0-63/1048576`
+        let size = object.content_range()
+            .and_then(|content_range| content_range.rsplit_once('/'))
+            .and_then(|(_, size)| size.parse().ok())
+            .or_else(|| object.content_length().and_then(|content_length| u64::try_from(content_length).ok()))
+            .context("Uploaded object size not found in S3")?;
+        let entity_tag = object.e_tag()
+            .context("Uploaded object ETag not found in S3")?
+            .to_string();
+        let head = object.body.collect().await?.into_bytes().to_vec();
+
+        Ok(Some(UploadedObject::new(size, &entity_tag, head)))
+    }
+
+    async fn finalize_upload(&self, uploaded_image: &UploadedImage) -> anyhow::Result<Option<(Uuid, url::Url)>> {
+        let image_id = Uuid::new_v4();
+        let upload_key = Self::upload_object_key(uploaded_image.upload_id());
+        let image_metadata = serde_json::to_string(&ImageMetadata::from(uploaded_image))?;
+
+        // The presigned URL still accepts another object until it expires
+        match self.aws_sdk_s3
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .copy_source(format!("{}/{}", self.bucket_name, upload_key))
+            .copy_source_if_match(uploaded_image.entity_tag())
+            .key(image_id.to_string())
+            .checksum_algorithm(ChecksumAlgorithm::Sha256)
+            .metadata_directive(MetadataDirective::Replace)
+            .metadata(Self::IMAGE_METADATA_KEY, image_metadata)
+            .send()
+            .await
+        {
+            Ok(_) => {},
+            Err(err) if err.as_service_error().is_some_and(|err| matches!(err.code(), Some("PreconditionFailed" | "NoSuchKey"))) => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("Failed to move uploaded object with key '{}'", upload_key)),
+        }
+
+        if let Err(err) = self.delete_upload(uploaded_image.upload_id()).await {
+            log::warn!("Uploaded object with key '{}' was not deleted: {:#}", upload_key, err);
+        }
+
+        Ok(Some((image_id, self.build_resource_url(&image_id))))
+    }
+
+    async fn delete_upload(&self, upload_id: &Uuid) -> anyhow::Result<()> {
+        let upload_key = Self::upload_object_key(upload_id);
+        self.aws_sdk_s3
+            .delete_object()
+            .bucket(&self.bucket_name)
+            .key(&upload_key)
+            .send()
+            .await
+            .map(|_| ())
+            .with_context(|| format!("Failed to delete uploaded object with key '{}'", upload_key))
+    }
+}
+
+#[async_trait::async_trait]
+impl RenditionCache for AwsS3Client {
+    async fn get_rendition(&self, image_id: &Uuid, rendition_key: &str) -> anyhow::Result<Option<Image>> {
+        self.get_object_image(image_id, Self::rendition_object_key(image_id, rendition_key))
+            .await
+            .context("Failed to download rendition from S3")
+    }
+
+    async fn put_rendition(&self, image_id: &Uuid, rendition_key: &str, rendition: &Image) -> anyhow::Result<()> {
+        let image_metadata = serde_json::to_string(&ImageMetadata::from(rendition))?;
+
+        self.aws_sdk_s3
+            .put_object()
+            .bucket(&self.bucket_name)
+            .key(Self::rendition_object_key(image_id, rendition_key))
+            .body(ByteStream::from(rendition.bytes().to_vec()))
+            .metadata(Self::IMAGE_METADATA_KEY, image_metadata)
+            .send()
+            .await
+            .map(|_| ())
+            .context("Failed to upload rendition to S3")
+    }
+
+    async fn invalidate(&self, image_id: &Uuid) -> anyhow::Result<()> {
+        self.delete_renditions(image_id).await
     }
 }
 
 impl AwsS3Client {
     const IMAGE_METADATA_KEY: &'static str = "image_metadata";
+    const RENDITIONS_PREFIX: &'static str = "renditions";
+    const UPLOADS_PREFIX: &'static str = "uploads";
 
-    pub fn new(aws_s3config: &AwsS3Config) -> Self {
+    pub fn new(aws_s3config: &AwsS3Config, uploads_config: &UploadsConfig) -> Self {
         let endpoint_url = Self::strip_https_scheme_prefix(aws_s3config);
         Self {
             aws_sdk_s3: Client::new(&aws_s3config.sdk_config),
             bucket_name: aws_s3config.bucket_name.clone(),
+            multipart_threshold: uploads_config.multipart_threshold(),
+            multipart_part_size: uploads_config.multipart_part_size(),
+            presigned_url_expiry: uploads_config.presigned_url_expiry(),
             endpoint_url
         }
     }
@@ -83,16 +288,25 @@ impl AwsS3Client {
             .to_string()
     }
 
-    async fn put_object_image(&self, upload_image: &UploadImage) -> anyhow::Result<Uuid> {
+    async fn put_object_image(&self, upload_image: &UploadImage, sha256: Option<&[u8; 32]>) -> anyhow::Result<Uuid> {
         let image_id = Uuid::new_v4();
         let key = image_id.to_string();
         let image_metadata = serde_json::to_string(&ImageMetadata::from(upload_image))?;
 
+        if upload_image.size() as u64 > self.multipart_threshold {
+            self.put_multipart_object_image(upload_image, &key, image_metadata, sha256).await?;
+            return Ok(image_id);
+        }
+
+        let body = ByteStream::from_path(upload_image.path())
+            .await
+            .context("Failed to read uploaded image")?;
         self.aws_sdk_s3
             .put_object()
             .bucket(&self.bucket_name)
             .key(&key)
-            .body(ByteStream::from(upload_image.bytes().to_vec()))
+            .body(body)
+            .set_checksum_sha256(sha256.map(|sha256| BASE64_STANDARD.encode(sha256)))
             .metadata(Self::IMAGE_METADATA_KEY, image_metadata)
             .send()
             .await
@@ -105,6 +319,211 @@ impl AwsS3Client {
         Ok(image_id)
     }
 
+    async fn put_multipart_object_image(&self, upload_image: &UploadImage, key: &str, image_metadata: String, sha256: Option<&[u8; 32]>) -> anyhow::Result<()> {
+        let multipart_upload = self.aws_sdk_s3
+            .create_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .checksum_algorithm(ChecksumAlgorithm::Sha256)
+            .metadata(Self::IMAGE_METADATA_KEY, &image_metadata)
+            .send()
+            .await
+            .with_context(|| format!("Failed to start multipart upload of object with key '{}'", key))?;
+        let upload_id = multipart_upload.upload_id()
+            .context("Multipart upload id not found in S3")?;
+
+        let upload_result = self.put_parts(upload_image, key, upload_id).await;
+        if let Err(err) = upload_result {
+            if let Err(abort_err) = self.aws_sdk_s3
+                .abort_multipart_upload()
+                .bucket(&self.bucket_name)
+                .key(key)
+                .upload_id(upload_id)
+                .send()
+                .await
+            {
+                log::warn!("Multipart upload {} of object with key '{}' was not aborted: {:?}", upload_id, key, abort_err);
+            }
+            return Err(err);
+        }
+
+        if let Err(err) = self.checksum_object_image(key, image_metadata, sha256).await {
+            if let Err(delete_err) = self.aws_sdk_s3
+                .delete_object()
+                .bucket(&self.bucket_name)
+                .key(key)
+                .send()
+                .await
+            {
+                log::warn!("Object with key '{}' was not deleted: {:?}", key, delete_err);
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // An object uploaded in parts only has a checksum of the checksums of its parts, copying it
+    // onto itself hashes it in full
+    async fn checksum_object_image(&self, key: &str, image_metadata: String, sha256: Option<&[u8; 32]>) -> anyhow::Result<()> {
+        let copy_object = self.aws_sdk_s3
+            .copy_object()
+            .bucket(&self.bucket_name)
+            .copy_source(format!("{}/{}", self.bucket_name, key))
+            .key(key)
+            .checksum_algorithm(ChecksumAlgorithm::Sha256)
+            .metadata_directive(MetadataDirective::Replace)
+            .metadata(Self::IMAGE_METADATA_KEY, image_metadata)
+            .send()
+            .await
+            .with_context(|| format!("Failed to copy object with key '{}' onto itself", key))?;
+
+        if let Some(sha256) = sha256 {
+            let checksum_sha256 = copy_object.copy_object_result()
+                .and_then(|copy_object_result| copy_object_result.checksum_sha256());
+            ensure!(
+                checksum_sha256 == Some(BASE64_STANDARD.encode(sha256).as_str()),
+                "SHA-256 checksum of object with key '{}' does not match the uploaded image", key
+            );
+        }
+        Ok(())
+    }
+
+    async fn put_parts(&self, upload_image: &UploadImage, key: &str, upload_id: &str) -> anyhow::Result<()> {
+        let size = upload_image.size() as u64;
+        let mut completed_parts = Vec::new();
+        let mut offset = 0;
+        // S3 numbers the parts from 1
+        let mut part_number = 1;
+        while offset < size {
+            let part_size = self.multipart_part_size.min(size - offset);
+            let body = ByteStream::read_from()
+                .path(upload_image.path())
+                .offset(offset)
+                .length(Length::Exact(part_size))
+                .build()
+                .await
+                .context("Failed to read uploaded image")?;
+
+            let part = self.aws_sdk_s3
+                .upload_part()
+                .bucket(&self.bucket_name)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .checksum_algorithm(ChecksumAlgorithm::Sha256)
+                .body(body)
+                .send()
+                .await
+                .with_context(|| format!("Failed to upload part {} of object with key '{}'", part_number, key))?;
+            completed_parts.push(CompletedPart::builder()
+                .set_e_tag(part.e_tag)
+                .set_checksum_sha256(part.checksum_sha256)
+                .part_number(part_number)
+                .build());
+            offset += part_size;
+            part_number += 1;
+        }
+
+        self.aws_sdk_s3
+            .complete_multipart_upload()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(completed_parts)).build())
+            .send()
+            .await
+            .with_context(|| format!("Failed to complete multipart upload of object with key '{}'", key))?;
+
+        Ok(())
+    }
+
+    // The pre-generated renditions share the prefix of the cached ones
+    async fn delete_renditions(&self, image_id: &Uuid) -> anyhow::Result<()> {
+        let prefix = Self::rendition_prefix(image_id);
+        let mut continuation_token = None;
+        loop {
+            let objects = self.aws_sdk_s3
+                .list_objects_v2()
+                .bucket(&self.bucket_name)
+                .prefix(&prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .context("Failed to list renditions in S3")?;
+
+            let object_identifiers = objects.contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .map(|key| ObjectIdentifier::builder().key(key).build())
+                .collect::<Result<Vec<_>, _>>()?;
+            if !object_identifiers.is_empty() {
+                self.aws_sdk_s3
+                    .delete_objects()
+                    .bucket(&self.bucket_name)
+                    .delete(Delete::builder().set_objects(Some(object_identifiers)).quiet(true).build()?)
+                    .send()
+                    .await
+                    .context("Failed to delete renditions from S3")?;
+            }
+
+            continuation_token = objects.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn get_object_image(&self, id: &Uuid, key: String) -> anyhow::Result<Option<Image>> {
+        let Some((image_metadata, object)) = self.get_object(key, None).await? else {
+            return Ok(None);
+        };
+
+        let bytes = object.body.collect().await?.into_bytes().to_vec();
+
+        Ok(Some(image_metadata.into_image(id, bytes)))
+    }
+
+    async fn get_object(&self, key: String, range: Option<String>) -> anyhow::Result<Option<(ImageMetadata, GetObjectOutput)>> {
+        let object = match self.aws_sdk_s3
+            .get_object()
+            .bucket(&self.bucket_name)
+            .key(key)
+            .set_range(range)
+            .send()
+            .await
+        {
+            Ok(object) => object,
+            Err(err) if err.as_service_error().is_some_and(|err| err.is_no_such_key()) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let metadata = object.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(Self::IMAGE_METADATA_KEY))
+            .ok_or_else(|| anyhow::anyhow!("Image metadata not found for key: {}", Self::IMAGE_METADATA_KEY))?;
+
+        let image_metadata: ImageMetadata = serde_json::from_str(metadata)
+            .context("Failed to deserialize image metadata")?;
+
+        Ok(Some((image_metadata, object)))
+    }
+
+    fn rendition_size_object_key(image_id: &Uuid, rendition_size: RenditionSize) -> String {
+        Self::rendition_object_key(image_id, rendition_size.as_str())
+    }
+
+    fn upload_object_key(upload_id: &Uuid) -> String {
+        format!("{}/{}", Self::UPLOADS_PREFIX, upload_id)
+    }
+
+    fn rendition_prefix(image_id: &Uuid) -> String {
+        format!("{}/{}/", Self::RENDITIONS_PREFIX, image_id)
+    }
+
+    fn rendition_object_key(image_id: &Uuid, rendition_key: &str) -> String {
+        format!("{}{}", Self::rendition_prefix(image_id), rendition_key)
+    }
+
     fn build_resource_url(&self, key: &Uuid) -> url::Url {
         url::Url::parse(&format!("https://{}.{}/{}", self.bucket_name, self.endpoint_url, key)).unwrap()
     }
@@ -127,4 +546,32 @@ impl From<&UploadImage> for ImageMetadata {
             size: upload_image.size(),
         }
     }
-}
\ No newline at end of file
+}
+
+impl From<&Image> for ImageMetadata {
+    fn from(image: &Image) -> Self {
+        Self {
+            filename: image.filename().to_string(),
+            format: image.format(),
+            visibility: image.visibility(),
+            size: image.size() as usize,
+        }
+    }
+}
+
+impl From<&UploadedImage> for ImageMetadata {
+    fn from(uploaded_image: &UploadedImage) -> Self {
+        Self {
+            filename: uploaded_image.filename().to_string(),
+            format: uploaded_image.format(),
+            visibility: uploaded_image.visibility(),
+            size: uploaded_image.size() as usize,
+        }
+    }
+}
+
+impl ImageMetadata {
+    pub fn into_image(self, id: &Uuid, bytes: Vec<u8>) -> Image {
+        Image::new(id, &self.filename, &self.format, &self.visibility, bytes, self.size as u32)
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+use std::future::Future;
+
+use crate::models::service::pagination::{Cursor, Page, PageRequest, Paginated};
+
+/// Upper bound on the batches scanned to fill a single page. When it is reached the
+/// page is returned as it is, with a cursor pointing right after the last scanned item.
+const MAX_SCAN_ROUNDS: usize = 10;
+
+/// Builds a page of items visible to the caller on top of a keyset-paginated source.
+///
+/// `fetch` reads at most `limit` items after the given cursor, `filter` drops the items
+/// the caller is not allowed to see (preserving their order). Batches are fetched until
+/// the page is full, so filtered out items never produce short pages or skipped items.
+pub(crate) async fn fetch_filtered_page<T, E, Fetch, FetchFuture, Filter, FilterFuture>(
+    page_request: &PageRequest,
+    mut fetch: Fetch,
+    mut filter: Filter,
+) -> Result<Page<T>, E>
+    where
+        T: Paginated + Debug + Clone,
+        Fetch: FnMut(u32, Option<Cursor>) -> FetchFuture,
+        FetchFuture: Future<Output = Result<Vec<T>, E>>,
+        Filter: FnMut(Vec<T>) -> FilterFuture,
+        FilterFuture: Future<Output = Result<Vec<T>, E>>,
+{
+    let limit = page_request.limit() as usize;
+    let batch_size = page_request.limit() + 1;
+    let mut scan_cursor = page_request.cursor().copied();
+    let mut items = Vec::with_capacity(limit);
+
+    for _ in 0..MAX_SCAN_ROUNDS {
+        let batch = fetch(batch_size, scan_cursor).await?;
+        let is_source_exhausted = batch.len() < batch_size as usize;
+        scan_cursor = batch.last().map(Paginated::cursor).or(scan_cursor);
+
+        for item in filter(batch).await? {
+            if items.len() == limit {
+                let next_cursor = items.last().map(Paginated::cursor);
+                return Ok(Page::new(items, next_cursor));
+            }
+            items.push(item);
+        }
+
+        if is_source_exhausted {
+            return Ok(Page::new(items, None));
+        }
+    }
+
+    Ok(Page::new(items, scan_cursor))
+}
+
+#[allow(unused_imports, dead_code)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Item {
+        created_at: DateTime<Utc>,
+        id: Uuid,
+        visible: bool,
+    }
+
+    impl Paginated for Item {
+        fn cursor(&self) -> Cursor {
+            Cursor::new(self.created_at, self.id)
+        }
+    }
+
+    fn items(visibility: &[bool]) -> Vec<Item> {
+        let now = Utc.timestamp_opt(1_736_000_000, 0).unwrap();
+        visibility
+            .iter()
+            .enumerate()
+            .map(|(i, visible)| Item { created_at: now - Duration::seconds(i as i64), id: Uuid::new_v4(), visible: *visible })
+            .collect()
+    }
+
+    async fn fetch(source: &[Item], limit: u32, cursor: Option<Cursor>) -> anyhow::Result<Vec<Item>> {
+        Ok(source
+            .iter()
+            .filter(|item| cursor.is_none_or(|cursor| (item.created_at, item.id) < (cursor.created_at(), cursor.id())))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn walk(source: &[Item], limit: u32) -> Vec<Page<Item>> {
+        let mut pages = vec![];
+        let mut cursor = None;
+        loop {
+            let page = fetch_filtered_page(
+                &PageRequest::new(Some(limit), cursor),
+                |limit, cursor| fetch(source, limit, cursor),
+                |items: Vec<Item>| async move { anyhow::Ok(items.into_iter().filter(|item| item.visible).collect()) },
+            ).await.unwrap();
+            cursor = page.next_cursor().map(|cursor| Cursor::decode(cursor).unwrap());
+            pages.push(page);
+            if cursor.is_none() {
+                return pages;
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn should_fill_pages_with_visible_items_only() {
+        let source = items(&[true, false, false, true, false, true, true, false, true]);
+
+        let pages = walk(&source, 2).await;
+
+        let page_sizes: Vec<_> = pages.iter().map(|page| page.data().len()).collect();
+        assert_eq!(page_sizes, vec![2, 2, 1]);
+        assert!(pages[0].has_more() && pages[1].has_more() && !pages[2].has_more());
+
+        let walked: Vec<_> = pages.iter().flat_map(|page| page.data().clone()).collect();
+        let expected: Vec<_> = source.into_iter().filter(|item| item.visible).collect();
+        assert_eq!(walked, expected);
+    }
+
+    #[actix_web::test]
+    async fn should_not_report_more_items_when_the_rest_is_not_visible() {
+        let source = items(&[true, true, false, false, false]);
+
+        let pages = walk(&source, 2).await;
+
+        assert_eq!(pages.len(), 1);
+        assert_eq!(pages[0].data().len(), 2);
+        assert!(!pages[0].has_more());
+    }
+}
@@ -1,104 +1,323 @@
+use std::collections::HashSet;
 use std::sync::Arc;
+use futures::TryStreamExt;
 use url::Url;
 use uuid::Uuid;
-use crate::models::service::pagination::Page;
-use crate::models::service::photo::{CreatePhoto, Photo, UpdatePhoto, UploadPhoto};
-use crate::service::PhotoService;
+use crate::models::service::ExpectedVersion;
+use crate::models::service::image::{Image, UploadImage};
+use crate::models::service::pagination::{Page, PageRequest};
+use crate::models::service::photo::{CreatePhoto, Photo, PhotoQuery, PhotoSearchResult, UpdatePhoto, UploadPhoto};
+use crate::models::service::upload::{CreatePendingUpload, PendingUpload, PresignedUpload, UploadedImage, UploadedObject};
+use crate::service::{PhotoService, ServiceError};
 use crate::service::image_storage::ImageStorage;
+use crate::repository::pending_upload_repository::PendingUploadRepository;
 use crate::repository::photo_repository::PhotoRepository;
 use crate::security::auth::user::AuthenticatedUser;
 use crate::security::authz::PhotoPolicyEnforcer;
-use crate::service::image::ImageReferenceUrlBuilder;
+use crate::service::image::{ImageReferenceUrlBuilder, UploadValidator};
+use crate::service::image_pipeline::ImagePipeline;
+use crate::service::pagination::fetch_filtered_page;
+use crate::service::rendition_cache::RenditionCache;
 
 #[derive(Debug, Clone)]
-pub struct PhotoServiceImpl<R, I, P>
+pub struct PhotoServiceImpl<R, I, P, C, U>
     where
         R: PhotoRepository,
         I: ImageStorage,
         P: PhotoPolicyEnforcer,
+        C: RenditionCache,
+        U: PendingUploadRepository,
 {
     photo_repository: Arc<R>,
     image_repository: Arc<I>,
     photo_policy_enforcer: Arc<P>,
     image_reference_url_builder: Arc<ImageReferenceUrlBuilder>,
+    image_pipeline: Arc<ImagePipeline<C>>,
+    pending_upload_repository: Arc<U>,
 }
 
-impl<R, I, P> PhotoServiceImpl<R, I, P>
+impl<R, I, P, C, U> PhotoServiceImpl<R, I, P, C, U>
     where
         R: PhotoRepository,
         I: ImageStorage,
         P: PhotoPolicyEnforcer,
+        C: RenditionCache,
+        U: PendingUploadRepository,
 {
     pub fn new(
         photo_repository: Arc<R>, 
         image_repository: Arc<I>, 
         photo_policy_enforcer: Arc<P>,
-        image_reference_url_builder: Arc<ImageReferenceUrlBuilder>
+        image_reference_url_builder: Arc<ImageReferenceUrlBuilder>,
+        image_pipeline: Arc<ImagePipeline<C>>,
+        pending_upload_repository: Arc<U>,
     ) -> Self {
         Self {
             photo_repository,
             image_repository,
             photo_policy_enforcer,
-            image_reference_url_builder
+            image_reference_url_builder,
+            image_pipeline,
+            pending_upload_repository,
+        }
+    }
+
+    const MAX_SEARCH_QUERY_LENGTH: usize = 256;
+    /// The expired pending uploads purged along with the creation of a pending upload.
+    const PURGED_PENDING_UPLOADS: u32 = 100;
+
+    async fn check_can_create_photo(&self, authenticated_user: &AuthenticatedUser) -> Result<(), ServiceError> {
+        let can_create_photo = self.photo_policy_enforcer
+            .can_create_photo(authenticated_user)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_create_photo {
+            return Err(ServiceError::Forbidden("Unauthorized to create a photo".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Only the owner of a pending upload sees it.
+    async fn find_pending_upload(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<PendingUpload, ServiceError> {
+        self.pending_upload_repository
+            .find_pending_upload_by_id(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .map(PendingUpload::from)
+            .filter(|pending_upload| pending_upload.owner_user_id() == authenticated_user.id())
+            .ok_or_else(|| ServiceError::NotFound(format!("Pending upload with id {} not found", id)))
+    }
+
+    /// Removes an uploaded object that will not become an image, whatever the outcome.
+    async fn discard_upload(&self, upload_id: &Uuid) {
+        if let Err(err) = self.image_repository.delete_upload(upload_id).await {
+            log::warn!("Uploaded object of pending upload {} was not deleted: {:#}", upload_id, err);
+        }
+    }
+
+    /// The presigned URL of an expired pending upload no longer accepts an object, so it is
+    /// deleted along with the object uploaded in time, if any.
+    async fn purge_expired_pending_uploads(&self) {
+        let expired_ids = match self.pending_upload_repository.delete_expired_pending_uploads(Self::PURGED_PENDING_UPLOADS).await {
+            Ok(expired_ids) => expired_ids,
+            Err(err) => {
+                log::warn!("Expired pending uploads were not purged: {:#}", err);
+                return;
+            }
+        };
+        for expired_id in &expired_ids {
+            self.discard_upload(expired_id).await;
+        }
+    }
+
+    /// Creates the photo of a finalized upload. Its image is decoded only now that the client
+    /// can no longer overwrite it, the uploaded object was merely recognized.
+    async fn create_finalized_photo(
+        &self,
+        authenticated_user: &AuthenticatedUser,
+        pending_upload: &PendingUpload,
+        uploaded_image: &UploadedImage,
+        image_id: &Uuid,
+        image_url: &Url,
+    ) -> Result<Photo, ServiceError> {
+        let finalized_image = self.download_finalized_image(pending_upload, image_id).await?;
+        let (dyn_image, image_properties) = self.image_pipeline.validate_image(finalized_image).await?;
+        let image_reference_url = self.image_reference_url_builder.build(image_id);
+        let image_renditions = self.image_pipeline
+            .upload_renditions(self.image_repository.as_ref(), image_id, pending_upload.filename(), *pending_upload.visibility(), dyn_image)
+            .await;
+
+        let create_photo = CreatePhoto::new(
+            pending_upload.title(),
+            pending_upload.description(),
+            pending_upload.category(),
+            pending_upload.tags(),
+            authenticated_user.id(),
+            image_id,
+            pending_upload.album_id(),
+            pending_upload.visibility(),
+            image_url,
+            &image_reference_url,
+            uploaded_image.size(),
+            &uploaded_image.format(),
+        ).with_image_renditions(image_renditions)
+            .with_image_properties(image_properties);
+
+        self.photo_repository
+            .create_photo(&create_photo)
+            .await
+            .map(Photo::from)
+            .map_err(ServiceError::Storage)
+    }
+
+    /// The finalized image is only read into memory once its length is known to be within the
+    /// limit of the uploaded images.
+    async fn download_finalized_image(&self, pending_upload: &PendingUpload, image_id: &Uuid) -> Result<Image, ServiceError> {
+        let image_stream = self.image_repository
+            .download_image_stream(image_id, None)
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::Storage(anyhow::anyhow!("Image {} of pending upload {} not found", image_id, pending_upload.id())))?;
+        let content_length = image_stream.content_length();
+        if content_length > UploadImage::MAX_SIZE {
+            return Err(ServiceError::Validation(format!("The uploaded image exceeds {} bytes", UploadImage::MAX_SIZE)));
+        }
+
+        let format = image_stream.format();
+        let bytes = image_stream.into_bytes()
+            .try_fold(Vec::with_capacity(content_length as usize), |mut bytes, chunk| async move {
+                bytes.extend_from_slice(&chunk);
+                Ok(bytes)
+            })
+            .await
+            .map_err(ServiceError::Storage)?;
+
+        Ok(Image::new(image_id, pending_upload.filename(), &format, pending_upload.visibility(), bytes, content_length as u32))
+    }
+
+    /// Recognizes the format of an uploaded object out of its signature, it is decoded once
+    /// finalized.
+    fn sniff_uploaded_image(&self, pending_upload: &PendingUpload, uploaded_object: &UploadedObject) -> Result<UploadedImage, ServiceError> {
+        if uploaded_object.size() > UploadImage::MAX_SIZE {
+            return Err(ServiceError::Validation(format!("The uploaded image exceeds {} bytes", UploadImage::MAX_SIZE)));
+        }
+        let format = self.image_pipeline.upload_validator().recognize(uploaded_object.head())?;
+
+        Ok(UploadedImage::new(pending_upload.id(), pending_upload.filename(), format, *pending_upload.visibility(), uploaded_object))
+    }
+
+    async fn find_photo_by_id(&self, id: &Uuid) -> Result<Photo, ServiceError> {
+        self.photo_repository
+            .find_photo_by_id(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .map(Photo::from)
+            .ok_or_else(|| ServiceError::NotFound(format!("Photo with id {} not found", id)))
+    }
+
+    fn check_version(photo: &Photo, expected_version: &ExpectedVersion) -> Result<(), ServiceError> {
+        if !expected_version.matches(photo.version()) {
+            return Err(ServiceError::PreconditionFailed(format!("Photo with id {} has been modified", photo.id())));
+        }
+        Ok(())
+    }
+
+    /// The renditions of the image of a photo carry its visibility, they are dropped once it
+    /// changes or the photo is deleted.
+    async fn invalidate_renditions(&self, photo: &Photo) {
+        let image_id = photo.image().id();
+        if let Err(err) = self.image_pipeline.rendition_cache().invalidate(image_id).await {
+            log::warn!("Photo {} image {} renditions were not invalidated: {:#}", photo.id(), image_id, err);
         }
     }
 }
 
 #[async_trait::async_trait]
-impl<R, I, P> PhotoService for PhotoServiceImpl<R, I, P>
+impl<R, I, P, C, U> PhotoService for PhotoServiceImpl<R, I, P, C, U>
     where
         R: PhotoRepository,
         I: ImageStorage,
-        P: PhotoPolicyEnforcer
+        P: PhotoPolicyEnforcer,
+        C: RenditionCache,
+        U: PendingUploadRepository,
 {
-    async fn get_all_photos(&self, authenticated_user: &AuthenticatedUser) -> anyhow::Result<Page<Photo>> {
-        let photos = self.photo_repository
-            .find_all_photos(30, 0) // TODO: add pagination
-            .await?
-            .into_iter()
-            .map(Photo::from)
-            .collect::<Vec<_>>();
+    async fn get_all_photos(&self, authenticated_user: &AuthenticatedUser, photo_query: &PhotoQuery, page_request: &PageRequest) -> Result<Page<Photo>, ServiceError> {
+        fetch_filtered_page(
+            page_request,
+            |limit, cursor| async move {
+                Ok(self.photo_repository
+                    .find_all_photos(photo_query, limit, cursor.as_ref())
+                    .await
+                    .map_err(ServiceError::Storage)?
+                    .into_iter()
+                    .map(Photo::from)
+                    .collect())
+            },
+            |photos| async move {
+                self.photo_policy_enforcer
+                    .filter_photos_by_view_permission(authenticated_user, photos)
+                    .await
+                    .map_err(ServiceError::UpstreamUnavailable)
+            },
+        ).await
+    }
+
+    async fn search_photos(&self, authenticated_user: &AuthenticatedUser, query: &str, page_request: &PageRequest) -> Result<Page<PhotoSearchResult>, ServiceError> {
+        let query = query.trim();
+        if query.is_empty() || query.chars().count() > Self::MAX_SEARCH_QUERY_LENGTH {
+            return Err(ServiceError::Validation(format!(
+                "The search query must contain between 1 and {} characters",
+                Self::MAX_SEARCH_QUERY_LENGTH
+            )));
+        }
+        if page_request.cursor().is_some_and(|cursor| cursor.rank().is_none()) {
+            return Err(ServiceError::Validation("The cursor does not belong to a search".to_string()));
+        }
 
-        let photos = self.photo_policy_enforcer.filter_photos_by_view_permission(authenticated_user, photos).await?;
+        fetch_filtered_page(
+            page_request,
+            |limit, cursor| async move {
+                Ok(self.photo_repository
+                    .search_photos(query, limit, cursor.as_ref())
+                    .await
+                    .map_err(ServiceError::Storage)?
+                    .into_iter()
+                    .map(PhotoSearchResult::from)
+                    .collect())
+            },
+            |search_results: Vec<PhotoSearchResult>| async move {
+                let photos = search_results.iter().map(|search_result| search_result.photo().clone()).collect();
+                let visible_photo_ids: HashSet<Uuid> = self.photo_policy_enforcer
+                    .filter_photos_by_view_permission(authenticated_user, photos)
+                    .await
+                    .map_err(ServiceError::UpstreamUnavailable)?
+                    .iter()
+                    .map(|photo| *photo.id())
+                    .collect();
 
-        let tot_photos = photos.len();
-        Ok(Page::new(photos, 0, tot_photos as u32))
+                Ok(search_results
+                    .into_iter()
+                    .filter(|search_result| visible_photo_ids.contains(search_result.photo().id()))
+                    .collect())
+            },
+        ).await
     }
 
     async fn get_photo_by_id(
         &self,
         authenticated_user: &AuthenticatedUser,
         id: &Uuid,
-    ) -> anyhow::Result<Option<Photo>> {
-        let photo_option = self
-            .photo_repository
-            .find_photo_by_id(id)
-            .await?
-            .map(Photo::from);
-        
-        if let Some(photo) = &photo_option {
-            let can_view_photo = self.photo_policy_enforcer.can_view_photo(authenticated_user, photo).await?;
-            if !can_view_photo {
-                return Err(anyhow::anyhow!("Unauthorized to view photo with id: {}", id).into()); // TODO: Error Handling
-            }
+    ) -> Result<Photo, ServiceError> {
+        let photo = self.find_photo_by_id(id).await?;
+
+        let can_view_photo = self.photo_policy_enforcer
+            .can_view_photo(authenticated_user, &photo)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_view_photo {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to view photo with id {}", id)));
         }
-        
-        Ok(photo_option)
+
+        Ok(photo)
     }
 
     async fn create_photo(
         &self,
         authenticated_user: &AuthenticatedUser,
         upload_photo: &UploadPhoto,
-    ) -> anyhow::Result<Photo> {
-        let can_create_photo = self.photo_policy_enforcer.can_create_photo(authenticated_user).await?;
-        if !can_create_photo {
-            return Err(anyhow::anyhow!("Unauthorized to create a photo").into()); // TODO: Error Handling
-        }
+    ) -> Result<Photo, ServiceError> {
+        self.check_can_create_photo(authenticated_user).await?;
 
         let upload_image = upload_photo.upload_image();
-        let (created_image_id, created_image_url) = self.image_repository.upload_image(upload_image).await?;
+        let (dyn_image, image_properties) = self.image_pipeline.validate(upload_image).await?;
+        let (created_image_id, created_image_url) = self.image_repository
+            .upload_image(upload_image, image_properties.sha256())
+            .await
+            .map_err(ServiceError::Storage)?;
         let image_reference_url = self.image_reference_url_builder.build(&created_image_id);
+        let image_renditions = self.image_pipeline
+            .upload_renditions(self.image_repository.as_ref(), &created_image_id, upload_image.filename(), upload_image.visibility(), dyn_image)
+            .await;
 
         let create_photo = CreatePhoto::new(
             upload_photo.title(),
@@ -113,45 +332,219 @@ impl<R, I, P> PhotoService for PhotoServiceImpl<R, I, P>
             &image_reference_url,
             upload_image.size() as u64,
             &upload_image.format(),
-        );
+        ).with_image_renditions(image_renditions)
+            .with_image_properties(image_properties);
 
-        self.photo_repository.create_photo(&create_photo).await.map(Photo::from)
+        self.photo_repository
+            .create_photo(&create_photo)
+            .await
+            .map(Photo::from)
+            .map_err(ServiceError::Storage)
     }
 
     async fn update_photo(
         &self, 
         authenticated_user: &AuthenticatedUser, 
-        update_photo: &UpdatePhoto
-    ) -> anyhow::Result<Photo> {
+        update_photo: &UpdatePhoto,
+        expected_version: &ExpectedVersion,
+    ) -> Result<Photo, ServiceError> {
         let photo_id = update_photo.id();
+        if update_photo.is_empty() {
+            let photo = self.get_photo_by_id(authenticated_user, photo_id).await?;
+            Self::check_version(&photo, expected_version)?;
+            return Ok(photo);
+        }
+        let photo = self.find_photo_by_id(photo_id).await?;
         
-        let photo = self.photo_repository
-            .find_photo_by_id(&photo_id)
-            .await?
-            .map(Photo::from)
-            .ok_or(anyhow::anyhow!("Not found"))?;
-        
-        let can_edit_photo = self.photo_policy_enforcer.can_edit_photo(authenticated_user, &photo, update_photo).await?;
+        let can_edit_photo = self.photo_policy_enforcer
+            .can_edit_photo(authenticated_user, &photo, update_photo)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
         if !can_edit_photo {
-            return Err(anyhow::anyhow!("Unauthorized to edit photo with id {}", update_photo.id()).into()); // TODO: Error Handling
+            return Err(ServiceError::Forbidden(format!("Unauthorized to edit photo with id {}", photo_id)));
         }
+        Self::check_version(&photo, expected_version)?;
         
+        let updated_photo = self.photo_repository
+            .update_photo(update_photo, expected_version)
+            .await
+            .map_err(ServiceError::Storage)?;
+        if let Some(updated_photo) = updated_photo {
+            if update_photo.visibility().is_some() {
+                self.invalidate_renditions(&photo).await;
+            }
+            return Ok(Photo::from(updated_photo));
+        }
+
+        // The conditional update matched no row, the photo may have changed in the meantime
+        let current_photo = self.find_photo_by_id(photo_id).await?;
+        Self::check_version(&current_photo, expected_version)?;
+        match update_photo.album_id() {
+            Some(Some(album_id)) => Err(ServiceError::Validation(format!("Album with id {} does not exist", album_id))),
+            _ => Err(ServiceError::NotFound(format!("Photo with id {} not found", photo_id))),
+        }
+    }
+
+    async fn delete_photo(&self, authenticated_user: &AuthenticatedUser, id: &Uuid, expected_version: &ExpectedVersion) -> Result<(), ServiceError> {
+        let photo = self.find_photo_by_id(id).await?;
+
+        let can_delete_photo = self.photo_policy_enforcer
+            .can_delete_photo(authenticated_user, &photo)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_delete_photo {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to delete photo with id {}", id)));
+        }
+        Self::check_version(&photo, expected_version)?;
+
+        let is_deleted = self.photo_repository
+            .delete_photo(id, expected_version)
+            .await
+            .map_err(ServiceError::Storage)?;
+        if !is_deleted {
+            let current_photo = self.find_photo_by_id(id).await?;
+            Self::check_version(&current_photo, expected_version)?;
+            return Err(ServiceError::NotFound(format!("Photo with id {} not found", id)));
+        }
+        self.invalidate_renditions(&photo).await;
+
+        Ok(())
+    }
+
+    async fn get_deleted_photos(&self, authenticated_user: &AuthenticatedUser, page_request: &PageRequest) -> Result<Page<Photo>, ServiceError> {
+        fetch_filtered_page(
+            page_request,
+            |limit, cursor| async move {
+                Ok(self.photo_repository
+                    .find_all_deleted_photos(authenticated_user.id(), limit, cursor.as_ref())
+                    .await
+                    .map_err(ServiceError::Storage)?
+                    .into_iter()
+                    .map(Photo::from)
+                    .collect())
+            },
+            |photos| async move { Ok(photos) },
+        ).await
+    }
+
+    async fn restore_photo(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Photo, ServiceError> {
+        let photo = self.photo_repository
+            .find_deleted_photo_by_id(id)
+            .await
+            .map_err(ServiceError::Storage)?
+            .map(Photo::from)
+            .ok_or_else(|| ServiceError::NotFound(format!("Deleted photo with id {} not found", id)))?;
+
+        let can_restore_photo = self.photo_policy_enforcer
+            .can_delete_photo(authenticated_user, &photo)
+            .await
+            .map_err(ServiceError::UpstreamUnavailable)?;
+        if !can_restore_photo {
+            return Err(ServiceError::Forbidden(format!("Unauthorized to restore photo with id {}", id)));
+        }
+
         self.photo_repository
-            .update_photo(update_photo)
+            .restore_photo(id)
             .await
             .map(Photo::from)
+            .map_err(ServiceError::Storage)
+    }
+
+    async fn create_pending_upload(
+        &self,
+        authenticated_user: &AuthenticatedUser,
+        create_pending_upload: &CreatePendingUpload,
+    ) -> Result<PresignedUpload, ServiceError> {
+        self.check_can_create_photo(authenticated_user).await?;
+        self.purge_expired_pending_uploads().await;
+
+        let id = Uuid::new_v4();
+        let (upload_url, expires_at) = self.image_repository
+            .presign_upload(&id)
+            .await
+            .map_err(ServiceError::Storage)?;
+        let pending_upload = self.pending_upload_repository
+            .create_pending_upload(&id, authenticated_user.id(), create_pending_upload, expires_at)
+            .await
+            .map(PendingUpload::from)
+            .map_err(ServiceError::Storage)?;
+
+        Ok(PresignedUpload::new(pending_upload, upload_url))
+    }
+
+    async fn complete_pending_upload(&self, authenticated_user: &AuthenticatedUser, id: &Uuid) -> Result<Photo, ServiceError> {
+        let pending_upload = self.find_pending_upload(authenticated_user, id).await?;
+        self.check_can_create_photo(authenticated_user).await?;
+        if pending_upload.is_expired() {
+            if let Ok(true) = self.pending_upload_repository.delete_pending_upload(id).await {
+                self.discard_upload(id).await;
+            }
+            return Err(ServiceError::NotFound(format!("Pending upload with id {} has expired", id)));
+        }
+
+        let uploaded_object = self.image_repository
+            .inspect_upload(id, UploadValidator::SNIFFED_LENGTH)
+            .await
+            .map_err(ServiceError::Storage)?
+            .ok_or_else(|| ServiceError::Conflict(format!("The image of pending upload {} has not been uploaded", id)))?;
+        // The client may upload another image until the pending upload expires
+        let uploaded_image = match self.sniff_uploaded_image(&pending_upload, &uploaded_object) {
+            Ok(uploaded_image) => uploaded_image,
+            Err(err) => {
+                self.discard_upload(id).await;
+                return Err(err);
+            }
+        };
+
+        // Deleting the pending upload first lets a single request complete it
+        let is_deleted = self.pending_upload_repository
+            .delete_pending_upload(id)
+            .await
+            .map_err(ServiceError::Storage)?;
+        if !is_deleted {
+            return Err(ServiceError::NotFound(format!("Pending upload with id {} not found", id)));
+        }
+
+        let (created_image_id, created_image_url) = match self.image_repository.finalize_upload(&uploaded_image).await {
+            Ok(Some(created_image)) => created_image,
+            Ok(None) => {
+                self.discard_upload(id).await;
+                return Err(ServiceError::Conflict(format!("The image of pending upload {} was replaced while being completed", id)));
+            }
+            Err(err) => {
+                self.discard_upload(id).await;
+                return Err(ServiceError::Storage(err));
+            }
+        };
+
+        let created_photo = self
+            .create_finalized_photo(authenticated_user, &pending_upload, &uploaded_image, &created_image_id, &created_image_url)
+            .await;
+        if created_photo.is_err() {
+            if let Err(err) = self.image_repository.delete_image(&created_image_id).await {
+                log::warn!("Image {} of pending upload {} is still in storage: {:#}", created_image_id, id, err);
+            }
+        }
+        created_photo
     }
 }
 
 #[allow(unused_imports, dead_code)]
 mod tests {
+    use std::io::{Cursor, Write};
+
     use actix_web::web::service;
     use async_trait::async_trait;
-    use image::ImageFormat;
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+    use chrono::{DateTime, Duration, Utc};
+    use futures::StreamExt;
+    use tempfile::NamedTempFile;
 
-    use crate::models::service::image::{Image, UploadImage};
+    use crate::models::service::image::{ContentHash, ContentRange, Image, ImageStream, RenditionSize, UploadImage};
     use crate::models::service::Visibility;
     use crate::repository::PostgresDatabase;
+    use crate::service::image::{DecodeLimits, RenditionGenerator};
+    use crate::service::transform_executor::TransformExecutor;
     use crate::security::auth::oauth::OAuthAccessTokenHolder;
 
     use super::*;
@@ -159,18 +552,84 @@ mod tests {
     #[derive(Clone)]
     struct MockImageRepository;
 
+    fn uploaded_png_bytes() -> Vec<u8> {
+        let mut png_bytes = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(32, 32)).write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png).unwrap();
+        png_bytes
+    }
+
     #[derive(Clone)]
     struct MockPhotoPolicyEnforcer;
 
+    #[derive(Clone)]
+    struct MockRenditionCache;
+
     #[async_trait::async_trait]
     impl ImageStorage for MockImageRepository {
-        async fn upload_image(&self, _bytes: &UploadImage) -> anyhow::Result<(Uuid, url::Url)> {
+        async fn upload_image(&self, _bytes: &UploadImage, _sha256: Option<&[u8; 32]>) -> anyhow::Result<(Uuid, url::Url)> {
             Ok((Uuid::new_v4(), Url::parse("https://localhost:8080/").unwrap()))
         }
 
-        async fn download_image(&self, _id: &Uuid) -> anyhow::Result<Option<Image>> {
+        async fn download_image(&self, id: &Uuid) -> anyhow::Result<Option<Image>> {
+            let png_bytes = uploaded_png_bytes();
+            let png_size = png_bytes.len() as u32;
+            Ok(Some(Image::new(id, "photo.png", &ImageFormat::Png, &Visibility::Public, png_bytes, png_size)))
+        }
+
+        async fn download_image_stream(&self, _id: &Uuid, _content_range: Option<&ContentRange>) -> anyhow::Result<Option<ImageStream>> {
+            let png_bytes = uploaded_png_bytes();
+            let png_size = png_bytes.len() as u64;
+            let bytes = futures::stream::once(async move { Ok(bytes::Bytes::from(png_bytes)) });
+            Ok(Some(ImageStream::new("photo.png", ImageFormat::Png, png_size, bytes.boxed())))
+        }
+
+        async fn content_hash(&self, _id: &Uuid) -> anyhow::Result<Option<ContentHash>> {
             Ok(None)
         }
+
+        async fn delete_image(&self, _id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn upload_rendition(&self, _image_id: &Uuid, _rendition_size: RenditionSize, _rendition: &Image) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn download_rendition(&self, _image_id: &Uuid, _rendition_size: RenditionSize) -> anyhow::Result<Option<Image>> {
+            Ok(None)
+        }
+
+        async fn presign_upload(&self, upload_id: &Uuid) -> anyhow::Result<(url::Url, DateTime<Utc>)> {
+            Ok((Url::parse(&format!("https://localhost:8080/uploads/{}", upload_id)).unwrap(), Utc::now() + Duration::minutes(15)))
+        }
+
+        async fn inspect_upload(&self, _upload_id: &Uuid, _head_length: u64) -> anyhow::Result<Option<UploadedObject>> {
+            let png_bytes = uploaded_png_bytes();
+            Ok(Some(UploadedObject::new(png_bytes.len() as u64, "\"etag\"", png_bytes)))
+        }
+
+        async fn finalize_upload(&self, _uploaded_image: &UploadedImage) -> anyhow::Result<Option<(Uuid, url::Url)>> {
+            Ok(Some((Uuid::new_v4(), Url::parse("https://localhost:8080/").unwrap())))
+        }
+
+        async fn delete_upload(&self, _upload_id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl RenditionCache for MockRenditionCache {
+        async fn get_rendition(&self, _image_id: &Uuid, _rendition_key: &str) -> anyhow::Result<Option<Image>> {
+            Ok(None)
+        }
+
+        async fn put_rendition(&self, _image_id: &Uuid, _rendition_key: &str, _rendition: &Image) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn invalidate(&self, _image_id: &Uuid) -> anyhow::Result<()> {
+            Ok(())
+        }
     }
 
     #[async_trait()]
@@ -194,6 +653,10 @@ mod tests {
             Ok(true)
         }
 
+        async fn can_delete_photo(&self, _authenticated_user: &AuthenticatedUser, _photo: &Photo) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
         async fn filter_photos_by_view_permission<'a>(&self, authenticated_user: &AuthenticatedUser, photos: Vec<Photo>) -> anyhow::Result<Vec<Photo>> {
             Ok(photos)
         }
@@ -203,12 +666,18 @@ mod tests {
     async fn test_get_all_photos() {
         let (photo_service, authenticated_user) = fixtures().await;
 
-        dbg!(photo_service.get_all_photos(&authenticated_user).await.unwrap());
+        dbg!(photo_service.get_all_photos(&authenticated_user, &PhotoQuery::default(), &PageRequest::default()).await.unwrap());
     }
 
     #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
     async fn test_get_create_photo() {
         let (photo_service, authenticated_user) = fixtures().await;
+        let mut png_bytes = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(4, 4))
+            .write_to(&mut Cursor::new(&mut png_bytes), ImageFormat::Png)
+            .unwrap();
+        let mut png_file = NamedTempFile::new().unwrap();
+        png_file.write_all(&png_bytes).unwrap();
 
         let upload_photo = UploadPhoto::new(
             "title".to_string(),
@@ -217,13 +686,61 @@ mod tests {
             "category".to_string(),
             vec!["tag".to_string(), "tag2".to_string()],
             Visibility::Public,
-            UploadImage::new("", vec![], ImageFormat::Png, Visibility::Private, 0),
+            UploadImage::new("photo.png", png_file, ImageFormat::Png, Visibility::Private, png_bytes.len()),
         );
 
         let created_photo = photo_service.create_photo(&authenticated_user, &upload_photo).await.unwrap();
-        let option_photo = photo_service.get_photo_by_id(&authenticated_user, created_photo.id()).await.unwrap();
-        assert!(option_photo.is_some());
-        assert_eq!(option_photo.unwrap().id(), created_photo.id());
+        let photo = photo_service.get_photo_by_id(&authenticated_user, created_photo.id()).await.unwrap();
+        assert_eq!(photo.id(), created_photo.id());
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_complete_a_pending_upload_once() {
+        let (photo_service, authenticated_user) = fixtures().await;
+
+        let create_pending_upload = CreatePendingUpload::new(
+            "photo.png".to_string(),
+            "title".to_string(),
+            None,
+            "description".to_string(),
+            "category".to_string(),
+            vec!["tag".to_string()],
+            Visibility::Public,
+        );
+        let presigned_upload = photo_service.create_pending_upload(&authenticated_user, &create_pending_upload).await.unwrap();
+        let id = presigned_upload.pending_upload().id();
+
+        let photo = photo_service.complete_pending_upload(&authenticated_user, id).await.unwrap();
+        assert_eq!(photo.title(), "title");
+        assert_eq!(photo.image().format(), ImageFormat::Png);
+        assert_eq!(photo.image().size(), uploaded_png_bytes().len() as u64);
+        assert_eq!(photo.image().properties().width(), Some(32));
+        assert!(photo.image().properties().sha256().is_some());
+
+        let err = photo_service.complete_pending_upload(&authenticated_user, id).await.unwrap_err();
+        assert!(matches!(err, ServiceError::NotFound(_)));
+    }
+
+    #[actix_web::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn should_not_complete_an_expired_pending_upload() {
+        let (photo_service, authenticated_user) = fixtures().await;
+        let pg = PostgresDatabase::connect(env!("DATABASE_URL")).await.unwrap();
+
+        let create_pending_upload = CreatePendingUpload::new(
+            "photo.png".to_string(),
+            "title".to_string(),
+            None,
+            "description".to_string(),
+            "category".to_string(),
+            Vec::new(),
+            Visibility::Public,
+        );
+        let id = Uuid::new_v4();
+        pg.create_pending_upload(&id, authenticated_user.id(), &create_pending_upload, Utc::now() - Duration::minutes(1)).await.unwrap();
+
+        let err = photo_service.complete_pending_upload(&authenticated_user, &id).await.unwrap_err();
+        assert!(matches!(err, ServiceError::NotFound(_)));
+        assert!(pg.find_pending_upload_by_id(&id).await.unwrap().is_none());
     }
 
     async fn fixtures() -> (impl PhotoService, AuthenticatedUser) {
@@ -232,11 +749,20 @@ mod tests {
         let mock_image_repository = Arc::new(MockImageRepository {});
         let mock_photo_policy_enforcer = Arc::new(MockPhotoPolicyEnforcer {});
         let mock_image_reference_url_builder = Arc::new(ImageReferenceUrlBuilder::new(&Url::parse("http://localhost:8080/images/").unwrap()));
+        let decode_limits = Arc::new(DecodeLimits::new(4096, 4096, 4096 * 4096, 512 * 1024 * 1024));
         let service = PhotoServiceImpl {
             photo_repository: pg.clone(),
             image_repository: mock_image_repository.clone(),
             photo_policy_enforcer: mock_photo_policy_enforcer,
-            image_reference_url_builder: mock_image_reference_url_builder
+            image_reference_url_builder: mock_image_reference_url_builder,
+            image_pipeline: Arc::new(ImagePipeline::new(
+                Arc::new(MockRenditionCache {}),
+                Arc::new(RenditionGenerator::new(ImageFormat::WebP, vec![(RenditionSize::Small, 160)])),
+                Arc::new(TransformExecutor::new(1, 1, std::time::Duration::from_secs(1))),
+                Arc::new(UploadValidator::new(vec![ImageFormat::Png], Arc::clone(&decode_limits))),
+                decode_limits,
+            )),
+            pending_upload_repository: pg.clone(),
         };
         let authenticated_user = AuthenticatedUser::new(
             &Uuid::new_v4(),
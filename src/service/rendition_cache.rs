@@ -0,0 +1,142 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use uuid::Uuid;
+
+use crate::models::service::image::Image;
+use crate::service::image_storage::{AwsS3Client, ImageMetadata};
+
+/// Stores the renditions of the images, i.e. the images derived from an original one by a set
+/// of transform options, so that they are not decoded and encoded again on every download.
+///
+/// A rendition is identified by the id of the original image and the rendition key of the
+/// options. It is only a cache: the authorization of the download is checked beforehand.
+#[async_trait::async_trait]
+pub trait RenditionCache: Clone + Send + Sync + 'static {
+    async fn get_rendition(&self, image_id: &Uuid, rendition_key: &str) -> anyhow::Result<Option<Image>>;
+    async fn put_rendition(&self, image_id: &Uuid, rendition_key: &str, rendition: &Image) -> anyhow::Result<()>;
+    /// Removes all the renditions of an image.
+    async fn invalidate(&self, image_id: &Uuid) -> anyhow::Result<()>;
+}
+
+/// Keeps the renditions in a directory per image, each rendition being written alongside
+/// its metadata.
+#[derive(Debug, Clone)]
+pub struct FileSystemRenditionCache {
+    directory: PathBuf,
+}
+
+impl FileSystemRenditionCache {
+    const METADATA_EXTENSION: &'static str = "json";
+
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    fn image_directory(&self, image_id: &Uuid) -> PathBuf {
+        self.directory.join(image_id.to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl RenditionCache for FileSystemRenditionCache {
+    async fn get_rendition(&self, image_id: &Uuid, rendition_key: &str) -> anyhow::Result<Option<Image>> {
+        let rendition_path = self.image_directory(image_id).join(rendition_key);
+        let metadata = match tokio::fs::read(rendition_path.with_extension(Self::METADATA_EXTENSION)).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).context("Failed to read rendition metadata"),
+        };
+        let image_metadata: ImageMetadata = serde_json::from_slice(&metadata)
+            .context("Failed to deserialize rendition metadata")?;
+
+        let bytes = match tokio::fs::read(&rendition_path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).context("Failed to read rendition"),
+        };
+
+        Ok(Some(image_metadata.into_image(image_id, bytes)))
+    }
+
+    async fn put_rendition(&self, image_id: &Uuid, rendition_key: &str, rendition: &Image) -> anyhow::Result<()> {
+        let image_directory = self.image_directory(image_id);
+        tokio::fs::create_dir_all(&image_directory).await
+            .context("Failed to create rendition directory")?;
+
+        // The metadata is written last, a rendition without metadata is never served
+        let rendition_path = image_directory.join(rendition_key);
+        tokio::fs::write(&rendition_path, rendition.bytes()).await
+            .context("Failed to write rendition")?;
+        let image_metadata = serde_json::to_vec(&ImageMetadata::from(rendition))?;
+        tokio::fs::write(rendition_path.with_extension(Self::METADATA_EXTENSION), image_metadata).await
+            .context("Failed to write rendition metadata")
+    }
+
+    async fn invalidate(&self, image_id: &Uuid) -> anyhow::Result<()> {
+        match tokio::fs::remove_dir_all(self.image_directory(image_id)).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err).context("Failed to remove renditions"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The rendition cache selected by the configuration.
+#[derive(Debug, Clone)]
+pub enum RenditionCacheStorage {
+    S3(AwsS3Client),
+    FileSystem(FileSystemRenditionCache),
+}
+
+#[async_trait::async_trait]
+impl RenditionCache for RenditionCacheStorage {
+    async fn get_rendition(&self, image_id: &Uuid, rendition_key: &str) -> anyhow::Result<Option<Image>> {
+        match self {
+            RenditionCacheStorage::S3(aws_s3_client) => aws_s3_client.get_rendition(image_id, rendition_key).await,
+            RenditionCacheStorage::FileSystem(file_system) => file_system.get_rendition(image_id, rendition_key).await,
+        }
+    }
+
+    async fn put_rendition(&self, image_id: &Uuid, rendition_key: &str, rendition: &Image) -> anyhow::Result<()> {
+        match self {
+            RenditionCacheStorage::S3(aws_s3_client) => aws_s3_client.put_rendition(image_id, rendition_key, rendition).await,
+            RenditionCacheStorage::FileSystem(file_system) => file_system.put_rendition(image_id, rendition_key, rendition).await,
+        }
+    }
+
+    async fn invalidate(&self, image_id: &Uuid) -> anyhow::Result<()> {
+        match self {
+            RenditionCacheStorage::S3(aws_s3_client) => aws_s3_client.invalidate(image_id).await,
+            RenditionCacheStorage::FileSystem(file_system) => file_system.invalidate(image_id).await,
+        }
+    }
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use image::ImageFormat;
+
+    use crate::models::service::Visibility;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn should_store_then_invalidate_renditions() {
+        let rendition_cache = FileSystemRenditionCache::new(std::env::temp_dir().join(format!("renditions-{}", Uuid::new_v4())));
+        let image_id = Uuid::new_v4();
+        let rendition = Image::new(&image_id, "photo.webp", &ImageFormat::WebP, &Visibility::Public, vec![1, 2, 3], 3);
+
+        assert!(rendition_cache.get_rendition(&image_id, "key").await.unwrap().is_none());
+        rendition_cache.put_rendition(&image_id, "key", &rendition).await.unwrap();
+
+        let cached_rendition = rendition_cache.get_rendition(&image_id, "key").await.unwrap().unwrap();
+        assert_eq!(cached_rendition.bytes(), rendition.bytes());
+        assert_eq!(cached_rendition.format(), ImageFormat::WebP);
+        assert_eq!(cached_rendition.filename(), "photo.webp");
+
+        rendition_cache.invalidate(&image_id).await.unwrap();
+        assert!(rendition_cache.get_rendition(&image_id, "key").await.unwrap().is_none());
+        rendition_cache.invalidate(&image_id).await.unwrap();
+    }
+}
@@ -0,0 +1,145 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::rt::task;
+use tokio::sync::Semaphore;
+
+use crate::service::ServiceError;
+
+/// Runs the CPU-bound image work, decoding, transforming and encoding, on the blocking threads
+/// rather than on the workers serving the requests. At most `concurrency` tasks run at once and
+/// `queue_depth` more wait for their turn, any other task is refused until the load decreases.
+#[derive(Debug)]
+pub struct TransformExecutor {
+    permits: Arc<Semaphore>,
+    concurrency: usize,
+    queue_depth: usize,
+    retry_after: Duration,
+    admitted: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl TransformExecutor {
+    pub fn new(concurrency: usize, queue_depth: usize, retry_after: Duration) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(concurrency)),
+            concurrency,
+            queue_depth,
+            retry_after,
+            admitted: AtomicUsize::new(0),
+            completed: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// Fails with `ServiceError::Overloaded` when the queue is full. A task whose request is
+    /// dropped while it waits leaves the queue, once started it runs to completion.
+    pub async fn execute<T, F>(&self, transform: F) -> Result<T, ServiceError>
+        where
+            T: Send + 'static,
+            F: FnOnce() -> Result<T, ServiceError> + Send + 'static,
+    {
+        let admission = self.admit()?;
+        let permit = Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|err| ServiceError::Storage(err.into()))?;
+
+        let result = task::spawn_blocking(move || {
+            let _permit = permit;
+            transform()
+        }).await;
+        drop(admission);
+        self.completed.fetch_add(1, Ordering::Relaxed);
+
+        result.map_err(|err| ServiceError::Storage(anyhow::anyhow!("The image transformation did not complete: {}", err)))?
+    }
+
+    pub fn metrics(&self) -> TransformExecutorMetrics {
+        let running = self.concurrency - self.permits.available_permits();
+        TransformExecutorMetrics {
+            concurrency: self.concurrency,
+            queue_depth: self.queue_depth,
+            running,
+            queued: self.admitted.load(Ordering::Relaxed).saturating_sub(running),
+            completed: self.completed.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    fn admit(&self) -> Result<Admission<'_>, ServiceError> {
+        let capacity = self.concurrency + self.queue_depth;
+        let admitted = self.admitted.fetch_update(Ordering::AcqRel, Ordering::Acquire, |admitted| {
+            (admitted < capacity).then_some(admitted + 1)
+        });
+        if admitted.is_err() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(ServiceError::Overloaded(self.retry_after.as_secs().max(1)));
+        }
+        Ok(Admission { admitted: &self.admitted })
+    }
+}
+
+/// Holds a place in the queue until the task completes or is dropped.
+struct Admission<'a> {
+    admitted: &'a AtomicUsize,
+}
+
+impl Drop for Admission<'_> {
+    fn drop(&mut self) {
+        self.admitted.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A snapshot of the load of the executor, the completed and rejected tasks are counted since
+/// the server started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformExecutorMetrics {
+    pub concurrency: usize,
+    pub queue_depth: usize,
+    pub running: usize,
+    pub queued: usize,
+    pub completed: u64,
+    pub rejected: u64,
+}
+
+#[allow(unused_imports)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[actix_web::test]
+    async fn should_refuse_the_tasks_beyond_the_queue() {
+        let transform_executor = Arc::new(TransformExecutor::new(1, 1, Duration::from_secs(2)));
+        let (release_sender, release_receiver) = mpsc::channel::<()>();
+
+        let running_executor = Arc::clone(&transform_executor);
+        let running = actix_web::rt::spawn(async move {
+            running_executor.execute(move || {
+                release_receiver.recv().unwrap();
+                Ok(1)
+            }).await
+        });
+        while transform_executor.metrics().running < 1 {
+            actix_web::rt::task::yield_now().await;
+        }
+        let queued_executor = Arc::clone(&transform_executor);
+        let queued = actix_web::rt::spawn(async move { queued_executor.execute(|| Ok(2)).await });
+        while transform_executor.metrics().queued < 1 {
+            actix_web::rt::task::yield_now().await;
+        }
+
+        let rejected = transform_executor.execute(|| Ok(3)).await;
+        assert!(matches!(rejected, Err(ServiceError::Overloaded(2))));
+
+        release_sender.send(()).unwrap();
+        assert_eq!(running.await.unwrap().unwrap(), 1);
+        assert_eq!(queued.await.unwrap().unwrap(), 2);
+        let metrics = transform_executor.metrics();
+        assert_eq!((metrics.running, metrics.queued, metrics.completed, metrics.rejected), (0, 0, 2, 1));
+    }
+}
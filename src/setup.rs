@@ -11,25 +11,41 @@ use yaml_rust2::{Yaml, YamlLoader};
 use s3::setup_aws_s3_config;
 pub use setup::{
     database::DatabaseConfig,
+    decode_limits::DecodeLimitsConfig,
     http::AlbumRoutesState,
     http::ImageRoutesState,
+    http::MetricsRoutesState,
     http::PhotoRoutesState,
     oidc::OidcConfig,
     redis::RedisConfig,
-    s3::AwsS3Config};
+    rendition_cache::RenditionCacheConfig,
+    renditions::RenditionsConfig,
+    s3::AwsS3Config,
+    transforms::TransformsConfig,
+    uploads::UploadsConfig};
 
 use crate::setup;
 use crate::setup::database::setup_database_config;
+use crate::setup::decode_limits::setup_decode_limits_config;
 use crate::setup::http::create_http_server;
 use crate::setup::logging::init_logging;
 use crate::setup::oidc::setup_oidc_config;
 use crate::setup::redis::setup_redis_config;
+use crate::setup::rendition_cache::setup_rendition_cache_config;
+use crate::setup::renditions::setup_renditions_config;
+use crate::setup::transforms::setup_transforms_config;
+use crate::setup::uploads::setup_uploads_config;
 
 mod database;
+mod decode_limits;
 mod http;
 mod oidc;
 mod redis;
+mod rendition_cache;
+mod renditions;
 mod s3;
+mod transforms;
+mod uploads;
 mod utils;
 mod logging;
 
@@ -50,7 +66,12 @@ pub struct Config {
     database_config: DatabaseConfig,
     aws_s3_config: AwsS3Config,
     server_port: u16,
-    image_reference_endpoint_url: Url
+    image_reference_endpoint_url: Url,
+    rendition_cache_config: RenditionCacheConfig,
+    renditions_config: RenditionsConfig,
+    uploads_config: UploadsConfig,
+    decode_limits_config: DecodeLimitsConfig,
+    transforms_config: TransformsConfig,
 }
 
 impl Config {
@@ -95,6 +116,11 @@ async fn setup() -> anyhow::Result<Config> {
     let server_port = get_server_port(&root_application_properties);
     let database_config = setup_database_config(&application_properties_path, &secrets_path)?;
     let image_reference_endpoint_url = extract_image_reference_endpoint_url(&root_application_properties)?;
+    let rendition_cache_config = setup_rendition_cache_config(&root_application_properties)?;
+    let renditions_config = setup_renditions_config(&root_application_properties)?;
+    let uploads_config = setup_uploads_config(&root_application_properties)?;
+    let decode_limits_config = setup_decode_limits_config(&root_application_properties)?;
+    let transforms_config = setup_transforms_config(&root_application_properties)?;
 
     Ok(Config {
         oidc_config,
@@ -103,6 +129,11 @@ async fn setup() -> anyhow::Result<Config> {
         aws_s3_config,
         server_port,
         image_reference_endpoint_url,
+        rendition_cache_config,
+        renditions_config,
+        uploads_config,
+        decode_limits_config,
+        transforms_config,
     })
 }
 
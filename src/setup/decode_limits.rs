@@ -0,0 +1,72 @@
+use anyhow::{ensure, Context};
+use yaml_rust2::Yaml;
+
+const DECODE_LIMITS_CONFIG_KEY: &str = "decode-limits";
+const MAX_WIDTH_KEY: &str = "max-width";
+const MAX_HEIGHT_KEY: &str = "max-height";
+const MAX_PIXELS_KEY: &str = "max-pixels";
+const MAX_ALLOC_KEY: &str = "max-alloc-mib";
+const DEFAULT_MAX_WIDTH: u64 = 16384;
+const DEFAULT_MAX_HEIGHT: u64 = 16384;
+const DEFAULT_MAX_PIXELS: u64 = 100_000_000;
+/// The default of the image crate.
+const DEFAULT_MAX_ALLOC_MIB: u64 = 512;
+const MIB: u64 = 1024 * 1024;
+
+/// The largest images decoded, at upload and before being transformed: their dimensions and
+/// pixel count, and the memory in bytes the decoder may allocate.
+#[derive(Debug, Clone)]
+pub struct DecodeLimitsConfig {
+    max_width: u32,
+    max_height: u32,
+    max_pixels: u64,
+    max_alloc: u64,
+}
+
+impl DecodeLimitsConfig {
+    pub fn max_width(&self) -> u32 {
+        self.max_width
+    }
+
+    pub fn max_height(&self) -> u32 {
+        self.max_height
+    }
+
+    pub fn max_pixels(&self) -> u64 {
+        self.max_pixels
+    }
+
+    pub fn max_alloc(&self) -> u64 {
+        self.max_alloc
+    }
+}
+
+pub fn setup_decode_limits_config(root: &Yaml) -> anyhow::Result<DecodeLimitsConfig> {
+    let decode_limits = &root[DECODE_LIMITS_CONFIG_KEY];
+
+    let max_width = extract_positive(decode_limits, MAX_WIDTH_KEY, DEFAULT_MAX_WIDTH)?;
+    let max_height = extract_positive(decode_limits, MAX_HEIGHT_KEY, DEFAULT_MAX_HEIGHT)?;
+    let max_pixels = extract_positive(decode_limits, MAX_PIXELS_KEY, DEFAULT_MAX_PIXELS)?;
+    let max_alloc_mib = extract_positive(decode_limits, MAX_ALLOC_KEY, DEFAULT_MAX_ALLOC_MIB)?;
+    ensure!(
+        u32::try_from(max_width).is_ok() && u32::try_from(max_height).is_ok(),
+        "Invalid 'decode-limits' dimensions, expected at most {} pixels", u32::MAX
+    );
+
+    Ok(DecodeLimitsConfig {
+        max_width: max_width as u32,
+        max_height: max_height as u32,
+        max_pixels,
+        max_alloc: max_alloc_mib * MIB,
+    })
+}
+
+fn extract_positive(decode_limits: &Yaml, key: &str, default_value: u64) -> anyhow::Result<u64> {
+    match &decode_limits[key] {
+        Yaml::BadValue => Ok(default_value),
+        value => value.as_i64()
+            .and_then(|value| u64::try_from(value).ok())
+            .filter(|value| *value > 0)
+            .context(format!("Invalid 'decode-limits.{}' field, expected a positive number", key)),
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::{Arc, Mutex};
+use actix_multipart::form::MultipartFormConfig;
 use actix_session::config::BrowserSession;
 use actix_session::storage::RedisSessionStore;
 use actix_session::SessionMiddleware;
@@ -8,14 +9,17 @@ use actix_web::{App, HttpServer, web};
 use actix_web::dev::Server;
 use anyhow::Context;
 
-use crate::setup::Config;
+use crate::setup::{Config, RenditionCacheConfig};
 use crate::{routes, security, service};
 use crate::service::image_storage::AwsS3Client;
 use crate::repository::PostgresDatabase;
 use crate::security::auth::oauth::OAuthClientSession;
-use crate::security::authz::{PhotoPolicyEnforcerKc, AlbumPolicyEnforcerKc, ImagePolicyEnforcerKc, KcAuthzService};
-use crate::service::{AlbumService, PhotoService};
-use crate::service::image::ImageReferenceUrlBuilder;
+use crate::security::authz::{PhotoPolicyEnforcerKc, AlbumPolicyEnforcerKc, ImagePolicyEnforcerKc, MetricsPolicyEnforcerKc, KcAuthzService};
+use crate::service::{AlbumService, PhotoService, ServiceError};
+use crate::service::image::{DecodeLimits, ImageReferenceUrlBuilder, RenditionGenerator, UploadValidator};
+use crate::service::image_pipeline::ImagePipeline;
+use crate::service::rendition_cache::{FileSystemRenditionCache, RenditionCacheStorage};
+use crate::service::transform_executor::TransformExecutor;
 
 #[derive(Debug, Clone)]
 pub struct PhotoRoutesState<PS: PhotoService> {
@@ -50,6 +54,21 @@ impl<IS> ImageRoutesState<IS> {
     }
 }
 
+#[derive(Clone)]
+pub struct MetricsRoutesState<MP> {
+    transform_executor: Arc<TransformExecutor>,
+    metrics_policy_enforcer: Arc<MP>,
+}
+
+impl<MP> MetricsRoutesState<MP> {
+    pub fn transform_executor(&self) -> &Arc<TransformExecutor> {
+        &self.transform_executor
+    }
+    pub fn metrics_policy_enforcer(&self) -> &Arc<MP> {
+        &self.metrics_policy_enforcer
+    }
+}
+
 
 pub async fn create_http_server(config: Config) -> anyhow::Result<Server> {
     log::info!("Init http server...");
@@ -65,32 +84,73 @@ pub async fn create_http_server(config: Config) -> anyhow::Result<Server> {
     let photo_policy_enforcer = Arc::new(PhotoPolicyEnforcerKc::new(Arc::clone(&kc_authz_service)));
     let album_policy_enforcer = Arc::new(AlbumPolicyEnforcerKc::new(Arc::clone(&kc_authz_service)));
     let image_policy_enforcer = Arc::new(ImagePolicyEnforcerKc::new(Arc::clone(&kc_authz_service)));
+    let metrics_policy_enforcer = Arc::new(MetricsPolicyEnforcerKc::new(Arc::clone(&kc_authz_service)));
 
-    let aws_s3_client = Arc::new(AwsS3Client::new(&config.aws_s3_config));
+    let aws_s3_client = Arc::new(AwsS3Client::new(&config.aws_s3_config, &config.uploads_config));
+    let rendition_cache = Arc::new(match &config.rendition_cache_config {
+        RenditionCacheConfig::S3 => RenditionCacheStorage::S3(AwsS3Client::clone(&aws_s3_client)),
+        RenditionCacheConfig::FileSystem { directory } => RenditionCacheStorage::FileSystem(FileSystemRenditionCache::new(directory.clone())),
+    });
     let database = Arc::new(PostgresDatabase::connect_with_db_config(&config.database_config).await?);
     
     let image_reference_endpoint_url_builder = Arc::new(ImageReferenceUrlBuilder::new(&config.image_reference_endpoint_url));
+    let rendition_generator = Arc::new(RenditionGenerator::new(
+        config.renditions_config.format(),
+        config.renditions_config.long_edges().to_vec(),
+    ));
+    let decode_limits = Arc::new(DecodeLimits::new(
+        config.decode_limits_config.max_width(),
+        config.decode_limits_config.max_height(),
+        config.decode_limits_config.max_pixels(),
+        config.decode_limits_config.max_alloc(),
+    ));
+    let transform_executor = Arc::new(TransformExecutor::new(
+        config.transforms_config.concurrency(),
+        config.transforms_config.queue_depth(),
+        config.transforms_config.retry_after(),
+    ));
+    let upload_validator = Arc::new(UploadValidator::new(
+        config.uploads_config.accepted_formats().to_vec(),
+        Arc::clone(&decode_limits),
+    ));
+    let image_pipeline = Arc::new(ImagePipeline::new(
+        Arc::clone(&rendition_cache),
+        Arc::clone(&rendition_generator),
+        Arc::clone(&transform_executor),
+        Arc::clone(&upload_validator),
+        Arc::clone(&decode_limits),
+    ));
     let photo_service = service::photo::PhotoServiceImpl::new(
         Arc::clone(&database), 
         Arc::clone(&aws_s3_client), 
         Arc::clone(&photo_policy_enforcer),
         Arc::clone(&image_reference_endpoint_url_builder),
+        Arc::clone(&image_pipeline),
+        Arc::clone(&database),
     );
     let album_service = service::album::AlbumServiceImpl::new(
         Arc::clone(&database), 
         Arc::clone(&aws_s3_client), 
         Arc::clone(&album_policy_enforcer),
         Arc::clone(&image_reference_endpoint_url_builder),
+        Arc::clone(&database),
+        Arc::clone(&photo_policy_enforcer),
+        Arc::clone(&image_pipeline),
     );
     let image_service = service::image::ImageServiceImpl::new(
         Arc::clone(&database), 
         Arc::clone(&aws_s3_client),
         Arc::clone(&image_policy_enforcer),
+        Arc::clone(&rendition_cache),
+        Arc::clone(&rendition_generator),
+        Arc::clone(&decode_limits),
+        Arc::clone(&transform_executor),
     );
     
     let photo_routes_state = PhotoRoutesState { photo_service: Arc::new(photo_service) };
     let album_routes_state = AlbumRoutesState { album_service: Arc::new(album_service) };
     let image_routes_state = ImageRoutesState { image_service: Arc::new(image_service) };
+    let metrics_routes_state = MetricsRoutesState { transform_executor: Arc::clone(&transform_executor), metrics_policy_enforcer };
     
     let server_port = config.server_port;
     let server = HttpServer::new(move || {
@@ -117,6 +177,13 @@ pub async fn create_http_server(config: Config) -> anyhow::Result<Server> {
             .app_data(web::Data::new(photo_routes_state.clone()))
             .app_data(web::Data::new(album_routes_state.clone()))
             .app_data(web::Data::new(image_routes_state.clone()))
+            .app_data(web::Data::new(metrics_routes_state.clone()))
+            .app_data(web::QueryConfig::default().error_handler(|err, _| ServiceError::Validation(err.to_string()).into()))
+            .app_data(web::PathConfig::default().error_handler(|err, _| ServiceError::Validation(err.to_string()).into()))
+            .app_data(web::JsonConfig::default()
+                .content_type(|mime| mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON))
+                .error_handler(|err, _| ServiceError::Validation(err.to_string()).into()))
+            .app_data(MultipartFormConfig::default().error_handler(|err, _| ServiceError::from(err).into()))
             .route(
                 &oauth_redirect_uri_path,
                 web::get().to(security::auth::oauth::oidc_redirect_endpoint),
@@ -125,41 +192,77 @@ pub async fn create_http_server(config: Config) -> anyhow::Result<Server> {
                 routes::health_check::HEALTH_CHECK_ROUTE,
                 web::get().to(routes::health_check::health_check),
             )
+            .route(
+                routes::metrics::METRICS_ROUTE,
+                web::get().to(routes::metrics::get_metrics::<MetricsPolicyEnforcerKc>),
+            )
             .route(
                 routes::photo::PHOTOS_ROUTE,
-                web::post().to(routes::photo::post_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc>>),
+                web::post().to(routes::photo::post_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
             )
             .route(
                 routes::photo::PHOTOS_ROUTE,
-                web::get().to(routes::photo::get_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc>>),
+                web::get().to(routes::photo::get_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::photo::PHOTOS_SEARCH_ROUTE,
+                web::get().to(routes::photo::search_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::photo::PHOTOS_TRASH_ROUTE,
+                web::get().to(routes::photo::get_deleted_photos::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
             )
             .route(
                 routes::photo::PHOTO_BY_ID_ROUTE,
-                web::get().to(routes::photo::get_photo_by_id::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc>>),
+                web::get().to(routes::photo::get_photo_by_id::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
             )
             .route(
                 routes::photo::PHOTO_BY_ID_ROUTE,
-                web::patch().to(routes::photo::patch_photo::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc>>),
+                web::patch().to(routes::photo::patch_photo::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::photo::PHOTO_BY_ID_ROUTE,
+                web::delete().to(routes::photo::delete_photo::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::photo::RESTORE_PHOTO_ROUTE,
+                web::post().to(routes::photo::restore_photo::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::upload::UPLOADS_ROUTE,
+                web::post().to(routes::upload::post_uploads::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
+            )
+            .route(
+                routes::upload::COMPLETE_UPLOAD_ROUTE,
+                web::post().to(routes::upload::complete_upload::<service::photo::PhotoServiceImpl<PostgresDatabase, AwsS3Client, PhotoPolicyEnforcerKc, RenditionCacheStorage, PostgresDatabase>>),
             )
             .route(
                 routes::album::ALBUMS_ROUTE,
-                web::get().to(routes::album::get_albums::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc>>),
+                web::get().to(routes::album::get_albums::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
+            )
+            .route(
+                routes::album::ALBUM_BY_ID_ROUTE,
+                web::get().to(routes::album::get_album_by_id::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
+            )
+            .route(
+                routes::album::ALBUM_PHOTOS_ROUTE,
+                web::get().to(routes::album::get_album_photos::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
             )
             .route(
                 routes::album::ALBUM_BY_ID_ROUTE,
-                web::get().to(routes::album::get_album_by_id::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc>>),
+                web::patch().to(routes::album::patch_album::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
             )
             .route(
                 routes::album::ALBUM_BY_ID_ROUTE,
-                web::patch().to(routes::album::patch_album::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc>>),
+                web::delete().to(routes::album::delete_album::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
             )
             .route(
                 routes::album::ALBUMS_ROUTE,
-                web::post().to(routes::album::post_albums::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc>>),
+                web::post().to(routes::album::post_albums::<service::album::AlbumServiceImpl<PostgresDatabase, AwsS3Client, AlbumPolicyEnforcerKc, PostgresDatabase, PhotoPolicyEnforcerKc, RenditionCacheStorage>>),
             )
             .route(
                 routes::image::IMAGE_BY_ID_ROUTE,
-                web::get().to(routes::image::get_image_by_id::<service::image::ImageServiceImpl<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc>>),
+                web::get().to(routes::image::get_image_by_id::<service::image::ImageServiceImpl<PostgresDatabase, AwsS3Client, ImagePolicyEnforcerKc, RenditionCacheStorage>>),
             )
             .service(routes::home);
         app
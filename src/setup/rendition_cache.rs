@@ -0,0 +1,37 @@
+use std::env;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use yaml_rust2::Yaml;
+
+const RENDITION_CACHE_DIRECTORY_ENV_VAR: &str = "RENDITION_CACHE_DIRECTORY";
+const RENDITION_CACHE_CONFIG_KEY: &str = "rendition-cache";
+const RENDITION_CACHE_STORAGE_KEY: &str = "storage";
+const RENDITION_CACHE_DIRECTORY_KEY: &str = "directory";
+const S3_STORAGE: &str = "s3";
+const FILE_SYSTEM_STORAGE: &str = "filesystem";
+
+/// Where the renditions of the images are cached, the S3 bucket of the images by default.
+#[derive(Debug, Clone)]
+pub enum RenditionCacheConfig {
+    S3,
+    FileSystem { directory: PathBuf },
+}
+
+pub fn setup_rendition_cache_config(root: &Yaml) -> anyhow::Result<RenditionCacheConfig> {
+    let rendition_cache = &root[RENDITION_CACHE_CONFIG_KEY];
+
+    match rendition_cache[RENDITION_CACHE_STORAGE_KEY].as_str().unwrap_or(S3_STORAGE) {
+        S3_STORAGE => Ok(RenditionCacheConfig::S3),
+        FILE_SYSTEM_STORAGE => Ok(RenditionCacheConfig::FileSystem { directory: extract_directory(rendition_cache)? }),
+        storage => bail!("Invalid 'rendition-cache.storage' field: {}, expected {} or {}", storage, S3_STORAGE, FILE_SYSTEM_STORAGE),
+    }
+}
+
+fn extract_directory(rendition_cache: &Yaml) -> anyhow::Result<PathBuf> {
+    env::var(RENDITION_CACHE_DIRECTORY_ENV_VAR)
+        .ok()
+        .or_else(|| rendition_cache[RENDITION_CACHE_DIRECTORY_KEY].as_str().map(str::to_string))
+        .map(PathBuf::from)
+        .context("Missing or invalid 'directory' field in rendition-cache configuration")
+}
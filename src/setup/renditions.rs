@@ -0,0 +1,58 @@
+use anyhow::Context;
+use image::ImageFormat;
+use yaml_rust2::Yaml;
+
+use crate::models::service::image::RenditionSize;
+
+const RENDITIONS_CONFIG_KEY: &str = "renditions";
+const RENDITIONS_FORMAT_KEY: &str = "format";
+const DEFAULT_FORMAT: ImageFormat = ImageFormat::WebP;
+const DEFAULT_LONG_EDGES: [(RenditionSize, u32); 3] = [
+    (RenditionSize::Small, 160),
+    (RenditionSize::Medium, 480),
+    (RenditionSize::Large, 1280),
+];
+
+/// The renditions generated at upload: the format and the long edge of each size, in pixels.
+#[derive(Debug, Clone)]
+pub struct RenditionsConfig {
+    format: ImageFormat,
+    long_edges: Vec<(RenditionSize, u32)>,
+}
+
+impl RenditionsConfig {
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn long_edges(&self) -> &[(RenditionSize, u32)] {
+        &self.long_edges
+    }
+}
+
+pub fn setup_renditions_config(root: &Yaml) -> anyhow::Result<RenditionsConfig> {
+    let renditions = &root[RENDITIONS_CONFIG_KEY];
+
+    let format = match renditions[RENDITIONS_FORMAT_KEY].as_str() {
+        Some(extension) => ImageFormat::from_extension(extension)
+            .filter(ImageFormat::writing_enabled)
+            .context(format!("Invalid 'renditions.format' field: {}", extension))?,
+        None => DEFAULT_FORMAT,
+    };
+
+    let long_edges = DEFAULT_LONG_EDGES
+        .iter()
+        .map(|(rendition_size, default_long_edge)| {
+            let long_edge = match &renditions[rendition_size.as_str()] {
+                Yaml::BadValue => *default_long_edge,
+                long_edge => long_edge.as_i64()
+                    .and_then(|long_edge| u32::try_from(long_edge).ok())
+                    .filter(|long_edge| *long_edge > 0)
+                    .context(format!("Invalid 'renditions.{}' field, expected a positive number of pixels", rendition_size.as_str()))?,
+            };
+            Ok((*rendition_size, long_edge))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(RenditionsConfig { format, long_edges })
+}
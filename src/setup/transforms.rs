@@ -0,0 +1,66 @@
+use std::num::NonZeroUsize;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::Context;
+use yaml_rust2::Yaml;
+
+const TRANSFORMS_CONFIG_KEY: &str = "transforms";
+const CONCURRENCY_KEY: &str = "concurrency";
+const QUEUE_DEPTH_KEY: &str = "queue-depth";
+const RETRY_AFTER_KEY: &str = "retry-after-seconds";
+const DEFAULT_QUEUE_DEPTH: u64 = 64;
+const DEFAULT_RETRY_AFTER_SECONDS: u64 = 5;
+
+/// How much image work runs at once, one task per CPU by default, and how many tasks wait for
+/// their turn before the server answers that it is busy.
+#[derive(Debug, Clone)]
+pub struct TransformsConfig {
+    concurrency: usize,
+    queue_depth: usize,
+    retry_after: Duration,
+}
+
+impl TransformsConfig {
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    pub fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+pub fn setup_transforms_config(root: &Yaml) -> anyhow::Result<TransformsConfig> {
+    let transforms = &root[TRANSFORMS_CONFIG_KEY];
+
+    let default_concurrency = thread::available_parallelism().map(NonZeroUsize::get).unwrap_or(1) as u64;
+    let concurrency = extract_positive(transforms, CONCURRENCY_KEY, default_concurrency)?;
+    let queue_depth = match &transforms[QUEUE_DEPTH_KEY] {
+        Yaml::BadValue => DEFAULT_QUEUE_DEPTH,
+        value => value.as_i64()
+            .and_then(|value| u64::try_from(value).ok())
+            .context(format!("Invalid 'transforms.{}' field, expected a number", QUEUE_DEPTH_KEY))?,
+    };
+    let retry_after_seconds = extract_positive(transforms, RETRY_AFTER_KEY, DEFAULT_RETRY_AFTER_SECONDS)?;
+
+    Ok(TransformsConfig {
+        concurrency: concurrency as usize,
+        queue_depth: queue_depth as usize,
+        retry_after: Duration::from_secs(retry_after_seconds),
+    })
+}
+
+fn extract_positive(transforms: &Yaml, key: &str, default_value: u64) -> anyhow::Result<u64> {
+    match &transforms[key] {
+        Yaml::BadValue => Ok(default_value),
+        value => value.as_i64()
+            .and_then(|value| u64::try_from(value).ok())
+            .filter(|value| *value > 0)
+            .context(format!("Invalid 'transforms.{}' field, expected a positive number", key)),
+    }
+}
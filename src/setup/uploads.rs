@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use image::ImageFormat;
+use yaml_rust2::Yaml;
+
+const UPLOADS_CONFIG_KEY: &str = "uploads";
+const MULTIPART_THRESHOLD_KEY: &str = "multipart-threshold-mib";
+const MULTIPART_PART_SIZE_KEY: &str = "multipart-part-size-mib";
+const DEFAULT_MULTIPART_THRESHOLD_MIB: u64 = 16;
+const DEFAULT_MULTIPART_PART_SIZE_MIB: u64 = 8;
+/// S3 rejects the parts smaller than 5 MiB, except the last one.
+const MIN_MULTIPART_PART_SIZE_MIB: u64 = 5;
+const MIB: u64 = 1024 * 1024;
+const PRESIGNED_URL_EXPIRY_KEY: &str = "presigned-url-expiry-minutes";
+const DEFAULT_PRESIGNED_URL_EXPIRY_MINUTES: u64 = 15;
+const ACCEPTED_FORMATS_KEY: &str = "accepted-formats";
+/// The formats the uploads can be accepted in, all of them by default.
+const SUPPORTED_FORMATS: [ImageFormat; 8] = [
+    ImageFormat::Jpeg,
+    ImageFormat::Png,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+    ImageFormat::Avif,
+    ImageFormat::Tiff,
+    ImageFormat::Bmp,
+    ImageFormat::Qoi,
+];
+
+/// How the uploaded images are sent to S3: the images larger than the threshold are sent in
+/// parts with a multipart upload, both sizes in bytes. The images uploaded by the clients
+/// straight to S3 must be sent before their presigned URL expires. Only the images in one of
+/// the accepted formats, recognized out of their bytes, are stored.
+#[derive(Debug, Clone)]
+pub struct UploadsConfig {
+    multipart_threshold: u64,
+    multipart_part_size: u64,
+    presigned_url_expiry: Duration,
+    accepted_formats: Vec<ImageFormat>,
+}
+
+impl UploadsConfig {
+    pub fn multipart_threshold(&self) -> u64 {
+        self.multipart_threshold
+    }
+
+    pub fn multipart_part_size(&self) -> u64 {
+        self.multipart_part_size
+    }
+
+    pub fn presigned_url_expiry(&self) -> Duration {
+        self.presigned_url_expiry
+    }
+
+    pub fn accepted_formats(&self) -> &[ImageFormat] {
+        &self.accepted_formats
+    }
+}
+
+pub fn setup_uploads_config(root: &Yaml) -> anyhow::Result<UploadsConfig> {
+    let uploads = &root[UPLOADS_CONFIG_KEY];
+
+    let multipart_threshold = extract_positive(uploads, MULTIPART_THRESHOLD_KEY, DEFAULT_MULTIPART_THRESHOLD_MIB)?;
+    let multipart_part_size = extract_positive(uploads, MULTIPART_PART_SIZE_KEY, DEFAULT_MULTIPART_PART_SIZE_MIB)?;
+    let presigned_url_expiry_minutes = extract_positive(uploads, PRESIGNED_URL_EXPIRY_KEY, DEFAULT_PRESIGNED_URL_EXPIRY_MINUTES)?;
+    let accepted_formats = extract_accepted_formats(uploads)?;
+    ensure!(
+        multipart_part_size >= MIN_MULTIPART_PART_SIZE_MIB,
+        "Invalid 'uploads.{}' field, the parts must be of at least {} MiB", MULTIPART_PART_SIZE_KEY, MIN_MULTIPART_PART_SIZE_MIB
+    );
+
+    Ok(UploadsConfig {
+        multipart_threshold: multipart_threshold * MIB,
+        multipart_part_size: multipart_part_size * MIB,
+        presigned_url_expiry: Duration::from_secs(presigned_url_expiry_minutes * 60),
+        accepted_formats,
+    })
+}
+
+fn extract_accepted_formats(uploads: &Yaml) -> anyhow::Result<Vec<ImageFormat>> {
+    let accepted_formats = match &uploads[ACCEPTED_FORMATS_KEY] {
+        Yaml::BadValue => return Ok(SUPPORTED_FORMATS.to_vec()),
+        Yaml::Array(extensions) => extensions,
+        _ => anyhow::bail!("Invalid 'uploads.{}' field, expected a list of image formats", ACCEPTED_FORMATS_KEY),
+    };
+    ensure!(!accepted_formats.is_empty(), "Invalid 'uploads.{}' field, at least one format must be accepted", ACCEPTED_FORMATS_KEY);
+
+    accepted_formats
+        .iter()
+        .map(|extension| extension.as_str()
+            .and_then(ImageFormat::from_extension)
+            .filter(|format| SUPPORTED_FORMATS.contains(format))
+            .with_context(|| format!("Invalid 'uploads.{}' field, unsupported format: {:?}", ACCEPTED_FORMATS_KEY, extension)))
+        .collect()
+}
+
+fn extract_positive(uploads: &Yaml, key: &str, default_value: u64) -> anyhow::Result<u64> {
+    match &uploads[key] {
+        Yaml::BadValue => Ok(default_value),
+        value => value.as_i64()
+            .and_then(|value| u64::try_from(value).ok())
+            .filter(|value| *value > 0)
+            .context(format!("Invalid 'uploads.{}' field, expected a positive number", key)),
+    }
+}